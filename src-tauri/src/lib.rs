@@ -3,8 +3,10 @@ use std::path::PathBuf;
 use std::process::{Command, Child, Stdio};
 use std::fs::File;
 use std::io::Write;
-use std::sync::Mutex;
-use tauri::{Emitter, State};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use tauri::{Emitter, Manager, State};
 
 // Native video player module removed - using video.js in frontend instead
 // mod video_player;
@@ -63,2318 +65,8105 @@ fn round_to_millis(value: f64) -> f64 {
     (value * 1000.0).round() / 1000.0
 }
 
-// Helper function to find ScreenRecorder Swift helper
-#[cfg(target_os = "macos")]
-fn find_screen_recorder() -> PathBuf {
-    // The binary is compiled to src-tauri/swift-helper/ScreenRecorder during build
-    let manifest_dir = env!("CARGO_MANIFEST_DIR");
-    PathBuf::from(manifest_dir).join("swift-helper").join("ScreenRecorder")
+// Helper function to read the output position (in seconds) from an FFmpeg `-progress` line
+// FFmpeg writes both out_time_us and out_time_ms, and both are in microseconds
+fn parse_progress_out_time(line: &str) -> Option<f64> {
+    let value = line.strip_prefix("out_time_us=")
+        .or_else(|| line.strip_prefix("out_time_ms="))?;
+    value.trim().parse::<f64>().ok().map(|us| us / 1_000_000.0)
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct VideoMetadata {
-    path: String,
-    filename: String,
-    duration: Option<f64>,
-    width: Option<u32>,
-    height: Option<u32>,
-    thumbnail_path: Option<String>,
+// Helper function to run an FFmpeg command while reporting how far it has encoded
+// Adds `-progress pipe:1` so FFmpeg writes key=value progress to stdout, and calls
+// `on_progress` with the current output position in seconds. Returns stderr on failure.
+fn run_ffmpeg_with_progress<F: FnMut(f64)>(cmd: &mut Command, on_progress: F) -> Result<(), String> {
+    run_ffmpeg_for_job(cmd, None, on_progress)
 }
 
-#[tauri::command]
-fn import_video(path: String) -> Result<VideoMetadata, String> {
-    log::info!("import_video called with path: {}", path);
+// Same as run_ffmpeg_with_progress, but as one step of an export job: the child is handed to the
+// job while it runs so cancel_export can kill it mid-encode. A cancelled job returns EXPORT_CANCELLED_ERROR.
+fn run_ffmpeg_for_job<F: FnMut(f64)>(cmd: &mut Command, job: Option<&ExportJob>, on_progress: F) -> Result<(), String> {
+    run_ffmpeg_for_job_output(cmd, job, on_progress).map(|_| ())
+}
 
-    let path_buf = PathBuf::from(&path);
+// Same as run_ffmpeg_for_job, but also returns stderr on success, for passes that report there (loudnorm)
+fn run_ffmpeg_for_job_output<F: FnMut(f64)>(cmd: &mut Command, job: Option<&ExportJob>, mut on_progress: F) -> Result<String, String> {
+    use std::io::{BufRead, BufReader, Read};
 
-    if !path_buf.exists() {
-        log::error!("File does not exist: {}", path);
-        return Err("File does not exist".to_string());
+    if job.map(|job| job.is_cancelled()).unwrap_or(false) {
+        return Err(EXPORT_CANCELLED_ERROR.to_string());
     }
 
-    let filename = path_buf
-        .file_name()
-        .and_then(|n| n.to_str())
-        .unwrap_or("unknown")
-        .to_string();
+    cmd.arg("-progress").arg("pipe:1")
+        .arg("-nostats")
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
 
-    log::info!("Probing video metadata for: {}", filename);
+    let mut child = cmd.spawn()
+        .map_err(|e| format!("Failed to execute FFmpeg. Make sure FFmpeg is installed. Error: {}", e))?;
 
-    // Use FFmpeg to probe video metadata
-    let (duration, width, height) = match probe_video_metadata(&path) {
-        Ok(metadata) => {
-            log::info!("Successfully probed metadata - Duration: {}s, Resolution: {}x{}",
-                      metadata.0, metadata.1, metadata.2);
-            metadata
-        },
-        Err(e) => {
-            log::error!("Failed to probe video metadata: {}", e);
-            return Err(e);
-        }
-    };
+    // Drain stderr on a separate thread so a chatty FFmpeg can't block on a full pipe
+    let stderr_reader = child.stderr.take().map(|mut stderr| {
+        std::thread::spawn(move || {
+            let mut text = String::new();
+            let _ = stderr.read_to_string(&mut text);
+            text
+        })
+    });
+    let stdout = child.stdout.take();
 
-    // Generate thumbnail (non-fatal if it fails)
-    let thumbnail_path = match generate_thumbnail(&path, duration) {
-        Ok(thumb_path) => {
-            log::info!("Thumbnail generated: {}", thumb_path);
-            Some(thumb_path)
-        },
-        Err(e) => {
-            log::warn!("Failed to generate thumbnail: {}", e);
+    // While the job holds the child, cancelling kills it, which also ends the stdout loop below
+    let pid = child.id();
+    let local_child = match job {
+        Some(job) => {
+            job.attach_child(child);
             None
         }
+        None => Some(child),
     };
 
-    let result = VideoMetadata {
-        path: path.clone(),
-        filename,
-        duration: Some(duration),
-        width: Some(width),
-        height: Some(height),
-        thumbnail_path,
-    };
-
-    log::info!("Returning metadata: {:?}", result);
-    Ok(result)
-}
+    if let Some(stdout) = stdout {
+        for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+            if let Some(seconds) = parse_progress_out_time(&line) {
+                on_progress(seconds);
+            }
+        }
+    }
 
-// Helper function to probe video metadata using ffprobe
-fn probe_video_metadata(path: &str) -> Result<(f64, u32, u32), String> {
-    log::info!("Running ffprobe on: {}", path);
+    let mut child = local_child
+        .or_else(|| job.and_then(|job| job.detach_child(pid)))
+        .ok_or("FFmpeg process handle was lost")?;
+    let status = child.wait().map_err(|e| format!("Failed to wait for FFmpeg: {}", e))?;
+    let stderr = stderr_reader
+        .and_then(|handle| handle.join().ok())
+        .unwrap_or_default();
 
-    let ffprobe = find_ffprobe();
-    let output = Command::new(&ffprobe)
-        .args(&[
-            "-v", "quiet",
-            "-print_format", "json",
-            "-show_format",
-            "-show_streams",
-            path
-        ])
-        .output()
-        .map_err(|e| {
-            log::error!("Failed to execute ffprobe: {}", e);
-            format!("Failed to run ffprobe. Make sure FFmpeg is installed. Error: {}", e)
-        })?;
+    if job.map(|job| job.is_cancelled()).unwrap_or(false) {
+        return Err(EXPORT_CANCELLED_ERROR.to_string());
+    }
 
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        log::error!("ffprobe command failed. stderr: {}", stderr);
-        return Err(format!("ffprobe failed to read video metadata: {}", stderr));
+    if !status.success() {
+        return Err(stderr);
     }
 
-    let json_str = String::from_utf8_lossy(&output.stdout);
-    log::debug!("ffprobe JSON output: {}", json_str);
+    Ok(stderr)
+}
 
-    let json: serde_json::Value = serde_json::from_str(&json_str)
-        .map_err(|e| {
-            log::error!("Failed to parse ffprobe JSON: {}", e);
-            format!("Failed to parse ffprobe output: {}", e)
-        })?;
+// Helper function to find ScreenRecorder Swift helper
+#[cfg(target_os = "macos")]
+fn find_screen_recorder() -> PathBuf {
+    // The binary is compiled to src-tauri/swift-helper/ScreenRecorder during build
+    let manifest_dir = env!("CARGO_MANIFEST_DIR");
+    PathBuf::from(manifest_dir).join("swift-helper").join("ScreenRecorder")
+}
 
-    // Extract duration from format section
-    let duration = json["format"]["duration"]
-        .as_str()
-        .and_then(|s| {
-            log::debug!("Parsing duration string: {}", s);
-            s.parse::<f64>().ok()
-        })
-        .unwrap_or(0.0);
+// ===== MEDIA CACHE =====
 
-    log::info!("Extracted duration: {} seconds", duration);
+// Size limit for the media cache before least-recently-used entries are evicted
+// Preview proxies live here too and dominate the size, so leave room for a few of them
+const MEDIA_CACHE_LIMIT_BYTES: u64 = 4 * 1024 * 1024 * 1024;
 
-    // Extract width and height from first video stream
-    let streams = json["streams"].as_array()
-        .ok_or_else(|| {
-            log::error!("No streams array in ffprobe output");
-            "No streams found in video".to_string()
-        })?;
+// How much of the head and tail of a file goes into its cache key
+const MEDIA_KEY_SAMPLE_BYTES: u64 = 64 * 1024;
 
-    log::info!("Found {} streams", streams.len());
+static NEXT_CACHE_TEMP_FILE: AtomicU64 = AtomicU64::new(1);
 
-    let video_stream = streams.iter()
-        .find(|s| s["codec_type"] == "video")
-        .ok_or_else(|| {
-            log::error!("No video stream found in {} streams", streams.len());
-            "No video stream found".to_string()
-        })?;
+// Persistent cache for per-file analysis results (thumbnails, waveforms, probe results).
+// Entries live in <app cache dir>/media/<key>/, where the key identifies the file's
+// contents rather than its path, so a file replaced at the same path gets a fresh entry.
+struct MediaCache {
+    root: PathBuf,
+    limit_bytes: u64,
+    eviction_lock: Mutex<()>,
+}
 
-    let width = video_stream["width"].as_u64().unwrap_or(1920) as u32;
-    let height = video_stream["height"].as_u64().unwrap_or(1080) as u32;
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MediaCacheStats {
+    path: String,
+    entries: usize,
+    size_bytes: u64,
+    limit_bytes: u64,
+}
 
-    log::info!("Extracted resolution: {}x{}", width, height);
+// Helper function to feed bytes into a 64-bit FNV-1a hash
+// Unlike DefaultHasher, FNV-1a is fully specified, so keys stay stable across Rust versions
+fn fnv1a_64(mut hash: u64, bytes: &[u8]) -> u64 {
+    for byte in bytes {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
 
-    Ok((duration, width, height))
+// Helper function to compute a stable identity key for a media file
+// Combines size, modification time and a hash of the first and last 64 KB of content
+fn media_file_key(path: &str) -> Result<String, String> {
+    media_identity_hash(path, true)
 }
 
-// Helper function to generate thumbnail for a video
-fn generate_thumbnail(video_path: &str, duration: f64) -> Result<String, String> {
-    log::info!("Generating thumbnail for: {}", video_path);
+// Helper function to compute a content fingerprint that survives moves and copies
+// Same as the cache key but without the modification time, which copying can change
+fn media_content_fingerprint(path: &str) -> Result<String, String> {
+    media_identity_hash(path, false)
+}
 
-    // Create thumbnails directory in temp folder
-    let temp_dir = std::env::temp_dir();
-    let thumbnails_dir = temp_dir.join("clipforge_thumbnails");
+fn media_identity_hash(path: &str, include_mtime: bool) -> Result<String, String> {
+    use std::io::{Read, Seek, SeekFrom};
 
-    // Create directory if it doesn't exist
-    std::fs::create_dir_all(&thumbnails_dir)
-        .map_err(|e| format!("Failed to create thumbnails directory: {}", e))?;
+    let mut file = File::open(path)
+        .map_err(|e| format!("Failed to open file for cache key: {}", e))?;
+    let metadata = file.metadata()
+        .map_err(|e| format!("Failed to read file metadata: {}", e))?;
 
-    // Generate thumbnail filename based on video path hash
-    use std::collections::hash_map::DefaultHasher;
-    use std::hash::{Hash, Hasher};
-    let mut hasher = DefaultHasher::new();
-    video_path.hash(&mut hasher);
-    let hash = hasher.finish();
-    let thumbnail_filename = format!("thumb_{}.jpg", hash);
-    let thumbnail_path = thumbnails_dir.join(thumbnail_filename);
+    let size = metadata.len();
+    let mtime = metadata.modified()
+        .ok()
+        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|d| d.as_nanos())
+        .unwrap_or(0);
 
-    // Extract frame at 2 seconds or 10% of duration (whichever is smaller)
-    let timestamp = (duration * 0.1).min(2.0);
+    let mut hash: u64 = 0xcbf29ce484222325; // FNV-1a offset basis
+    hash = fnv1a_64(hash, &size.to_le_bytes());
+    if include_mtime {
+        hash = fnv1a_64(hash, &mtime.to_le_bytes());
+    }
 
-    log::info!("Extracting frame at {}s to: {:?}", timestamp, thumbnail_path);
+    let sample_len = size.min(MEDIA_KEY_SAMPLE_BYTES) as usize;
+    let mut buffer = vec![0u8; sample_len];
 
-    // Use FFmpeg to extract a single frame
-    let ffmpeg = find_ffmpeg();
-    let output = Command::new(&ffmpeg)
-        .args(&[
-            "-ss", &timestamp.to_string(),  // Seek to timestamp
-            "-i", video_path,                // Input video
-            "-vframes", "1",                 // Extract 1 frame
-            "-vf", "scale=320:-1",          // Scale to width 320, maintain aspect ratio
-            "-q:v", "2",                     // High quality JPEG
-            "-y",                            // Overwrite if exists
-            thumbnail_path.to_str().unwrap()
-        ])
-        .output()
-        .map_err(|e| {
-            log::error!("Failed to execute ffmpeg for thumbnail: {}", e);
-            format!("Failed to generate thumbnail: {}", e)
-        })?;
+    file.read_exact(&mut buffer)
+        .map_err(|e| format!("Failed to read file head: {}", e))?;
+    hash = fnv1a_64(hash, &buffer);
 
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        log::error!("FFmpeg thumbnail generation failed: {}", stderr);
-        return Err(format!("Failed to generate thumbnail: {}", stderr));
+    if size > MEDIA_KEY_SAMPLE_BYTES {
+        file.seek(SeekFrom::End(-(sample_len as i64)))
+            .map_err(|e| format!("Failed to seek file tail: {}", e))?;
+        file.read_exact(&mut buffer)
+            .map_err(|e| format!("Failed to read file tail: {}", e))?;
+        hash = fnv1a_64(hash, &buffer);
     }
 
-    let thumbnail_path_str = thumbnail_path.to_string_lossy().to_string();
-    log::info!("Thumbnail generated successfully: {}", thumbnail_path_str);
-
-    Ok(thumbnail_path_str)
+    Ok(format!("{:016x}", hash))
 }
 
-// Generate waveform data for audio visualization
-#[tauri::command]
-fn generate_waveform(video_path: String, samples: Option<usize>) -> Result<Vec<f32>, String> {
-    let num_samples = samples.unwrap_or(200);
-    log::info!("Generating waveform for: {} with {} samples", video_path, num_samples);
-
-    // First, check if the video has an audio stream
-    let ffprobe = find_ffprobe();
-    let probe_output = Command::new(&ffprobe)
-        .args(&[
-            "-v", "quiet",
-            "-print_format", "json",
-            "-show_streams",
-            "-select_streams", "a",  // Only select audio streams
-            &video_path
-        ])
-        .output()
-        .map_err(|e| format!("Failed to probe video: {}", e))?;
+// Helper function to get the name prefix of cache scratch files written by this process
+// Scratch files left behind by an earlier run have another process ID and don't count as pending
+fn pending_temp_prefix() -> String {
+    format!(".tmp-{}-", std::process::id())
+}
 
-    let json_str = String::from_utf8_lossy(&probe_output.stdout);
-    let json: serde_json::Value = serde_json::from_str(&json_str)
-        .map_err(|e| format!("Failed to parse ffprobe output: {}", e))?;
+// Helper function to check whether a cache entry has a build in progress
+fn has_pending_temp_files(dir: &std::path::Path) -> bool {
+    let prefix = pending_temp_prefix();
+    std::fs::read_dir(dir)
+        .map(|entries| entries.flatten().any(|entry| entry.file_name().to_string_lossy().starts_with(&prefix)))
+        .unwrap_or(false)
+}
 
-    // Check if there are any audio streams
-    let audio_streams = json["streams"].as_array()
-        .map(|arr| arr.len())
-        .unwrap_or(0);
+// Helper function to total the size of every file in a directory tree
+fn directory_size(dir: &std::path::Path) -> u64 {
+    std::fs::read_dir(dir)
+        .map(|entries| {
+            entries.flatten()
+                .map(|entry| match entry.metadata() {
+                    Ok(m) if m.is_dir() => directory_size(&entry.path()),
+                    Ok(m) => m.len(),
+                    Err(_) => 0,
+                })
+                .sum()
+        })
+        .unwrap_or(0)
+}
 
-    if audio_streams == 0 {
-        log::info!("No audio stream found in video, returning silent waveform");
-        // Return silent waveform (all zeros)
-        return Ok(vec![0.0; num_samples]);
+impl MediaCache {
+    fn new(root: PathBuf) -> Self {
+        MediaCache {
+            root,
+            limit_bytes: MEDIA_CACHE_LIMIT_BYTES,
+            eviction_lock: Mutex::new(()),
+        }
     }
 
-    // Get the duration of the video
-    let probe_output = Command::new(&ffprobe)
-        .arg("-v").arg("error")
-        .arg("-show_entries").arg("format=duration")
-        .arg("-of").arg("default=noprint_wrappers=1:nokey=1")
-        .arg(&video_path)
-        .output()
-        .map_err(|e| format!("Failed to probe video: {}", e))?;
-
-    let duration_str = String::from_utf8_lossy(&probe_output.stdout);
-    let duration: f64 = duration_str.trim().parse().unwrap_or(0.0);
+    // Get (and create) the entry directory for a file, marking it as recently used
+    fn entry_dir(&self, key: &str) -> Result<PathBuf, String> {
+        let dir = self.root.join(key);
+        std::fs::create_dir_all(&dir)
+            .map_err(|e| format!("Failed to create cache directory: {}", e))?;
 
-    if duration <= 0.0 {
-        return Err("Could not determine video duration".to_string());
+        // The marker's mtime is the entry's last-used time for LRU eviction
+        let _ = std::fs::write(dir.join(".last_used"), b"");
+        Ok(dir)
     }
 
-    // Use FFmpeg to extract audio peaks at regular intervals
-    // We'll use the volumedetect filter to get volume levels
-    let ffmpeg = find_ffmpeg();
-
-    // Extract audio as raw PCM and get volume stats
-    let temp_audio = std::env::temp_dir().join(format!("waveform_{}.raw", std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_millis()));
-
-    let output = Command::new(&ffmpeg)
-        .arg("-i").arg(&video_path)
-        .arg("-vn") // No video
-        .arg("-acodec").arg("pcm_s16le") // PCM 16-bit
-        .arg("-ar").arg("8000") // 8kHz sample rate (enough for visualization)
-        .arg("-ac").arg("1") // Mono
-        .arg("-f").arg("s16le") // Raw PCM output
-        .arg("-y")
-        .arg(&temp_audio)
-        .output()
-        .map_err(|e| format!("Failed to extract audio: {}", e))?;
-
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        log::error!("FFmpeg audio extraction failed: {}", stderr);
-        return Err(format!("Failed to extract audio: {}", stderr));
+    // Path of a named artifact in an entry, if it has already been cached
+    fn cached_file(&self, key: &str, name: &str) -> Option<PathBuf> {
+        let path = self.root.join(key).join(name);
+        if path.exists() {
+            let _ = std::fs::write(self.root.join(key).join(".last_used"), b"");
+            Some(path)
+        } else {
+            None
+        }
     }
 
-    // Read the raw PCM data
-    let audio_data = std::fs::read(&temp_audio).map_err(|e| format!("Failed to read audio data: {}", e))?;
-    let _ = std::fs::remove_file(&temp_audio); // Clean up
+    // Scratch path to render an artifact into before publishing it with commit_file
+    // Unique per call, so an interrupted run never leaves a truncated file under the real name and
+    // concurrent builds of the same artifact don't write over each other. Keeps the extension so
+    // FFmpeg can still infer the output format.
+    // The file is created straight away, under the eviction lock, so evict sees the entry as
+    // being written from here until the file is committed or removed.
+    fn temp_file(&self, key: &str, name: &str) -> Result<PathBuf, String> {
+        let _guard = self.eviction_lock.lock().unwrap();
+        let unique = NEXT_CACHE_TEMP_FILE.fetch_add(1, Ordering::SeqCst);
+        let path = self.entry_dir(key)?.join(format!("{}{}-{}", pending_temp_prefix(), unique, name));
+        File::create(&path).map_err(|e| format!("Failed to create cache file: {}", e))?;
+        Ok(path)
+    }
 
-    // Convert bytes to i16 samples
-    let mut samples_i16: Vec<i16> = Vec::new();
-    for chunk in audio_data.chunks_exact(2) {
-        let sample = i16::from_le_bytes([chunk[0], chunk[1]]);
-        samples_i16.push(sample);
+    // Atomically move a finished scratch file into place as the named artifact
+    fn commit_file(&self, key: &str, temp_path: &std::path::Path, name: &str) -> Result<PathBuf, String> {
+        let path = self.root.join(key).join(name);
+        std::fs::rename(temp_path, &path).map_err(|e| {
+            let _ = std::fs::remove_file(temp_path);
+            format!("Failed to write cache entry: {}", e)
+        })?;
+        Ok(path)
     }
 
-    if samples_i16.is_empty() {
-        // Silent audio or no audio track
-        return Ok(vec![0.0; num_samples]);
+    fn read_json<T: serde::de::DeserializeOwned>(&self, key: &str, name: &str) -> Option<T> {
+        let path = self.cached_file(key, name)?;
+        let data = std::fs::read(&path).ok()?;
+        match serde_json::from_slice(&data) {
+            Ok(value) => Some(value),
+            Err(e) => {
+                // Stale format from an older version - drop it and recompute
+                log::warn!("Discarding unreadable cache file {:?}: {}", path, e);
+                let _ = std::fs::remove_file(&path);
+                None
+            }
+        }
     }
 
-    // Downsample to the requested number of samples by taking RMS of chunks
-    let chunk_size = samples_i16.len() / num_samples;
-    let mut waveform: Vec<f32> = Vec::new();
+    fn write_json<T: Serialize>(&self, key: &str, name: &str, value: &T) -> Result<(), String> {
+        let data = serde_json::to_vec(value)
+            .map_err(|e| format!("Failed to serialize cache entry: {}", e))?;
+        let temp_path = self.temp_file(key, name)?;
+        std::fs::write(&temp_path, data).map_err(|e| {
+            let _ = std::fs::remove_file(&temp_path);
+            format!("Failed to write cache entry: {}", e)
+        })?;
+        self.commit_file(key, &temp_path, name)?;
+        self.evict();
+        Ok(())
+    }
 
-    for i in 0..num_samples {
-        let start = i * chunk_size;
-        let end = ((i + 1) * chunk_size).min(samples_i16.len());
+    // Remove least-recently-used entries until the cache fits within its size limit
+    fn evict(&self) {
+        let _guard = self.eviction_lock.lock().unwrap();
+
+        let mut entries: Vec<(PathBuf, std::time::SystemTime, u64)> = match std::fs::read_dir(&self.root) {
+            Ok(read_dir) => read_dir
+                .flatten()
+                .filter(|entry| entry.path().is_dir())
+                .map(|entry| {
+                    let dir = entry.path();
+                    let last_used = std::fs::metadata(dir.join(".last_used"))
+                        .and_then(|m| m.modified())
+                        .unwrap_or(std::time::UNIX_EPOCH);
+                    let size = directory_size(&dir);
+                    (dir, last_used, size)
+                })
+                .collect(),
+            Err(_) => return,
+        };
 
-        if start >= samples_i16.len() {
-            waveform.push(0.0);
-            continue;
+        let mut total: u64 = entries.iter().map(|(_, _, size)| size).sum();
+        if total <= self.limit_bytes {
+            return;
         }
 
-        // Calculate RMS (root mean square) for this chunk
-        let chunk = &samples_i16[start..end];
-        let sum_squares: f64 = chunk.iter().map(|&s| (s as f64).powi(2)).sum();
-        let rms = (sum_squares / chunk.len() as f64).sqrt();
+        entries.sort_by_key(|(_, last_used, _)| *last_used);
 
-        // Normalize to 0.0-1.0 range (i16 max is 32767)
-        let normalized = (rms / 32767.0) as f32;
-        waveform.push(normalized);
-    }
+        // Never evict the most recently used entry, even when it alone is over the limit
+        // (a long source's proxy), or the artifact that was just written would disappear
+        entries.pop();
 
-    log::info!("Waveform generated with {} data points", waveform.len());
-    Ok(waveform)
+        for (dir, _, size) in entries {
+            if total <= self.limit_bytes {
+                break;
+            }
+            // A proxy encode or peaks build may still be writing here for minutes; committing
+            // its result would fail if the entry disappeared underneath it
+            if has_pending_temp_files(&dir) {
+                log::info!("Keeping media cache entry {:?} while it is being written", dir);
+                continue;
+            }
+            log::info!("Evicting media cache entry {:?} ({} bytes)", dir, size);
+            if std::fs::remove_dir_all(&dir).is_ok() {
+                total = total.saturating_sub(size);
+            }
+        }
+    }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct ExportOptions {
-    input_path: String,
-    output_path: String,
-    trim_start: Option<f64>,
-    trim_end: Option<f64>,
+#[tauri::command]
+fn get_media_cache_stats(cache: State<MediaCache>) -> Result<MediaCacheStats, String> {
+    let entries = std::fs::read_dir(&cache.root)
+        .map(|read_dir| read_dir.flatten().filter(|entry| entry.path().is_dir()).count())
+        .unwrap_or(0);
+
+    Ok(MediaCacheStats {
+        path: cache.root.to_string_lossy().to_string(),
+        entries,
+        size_bytes: directory_size(&cache.root),
+        limit_bytes: cache.limit_bytes,
+    })
 }
 
 #[tauri::command]
-fn export_video(options: ExportOptions) -> Result<String, String> {
-    log::info!("Starting export: {:?}", options);
+fn clear_media_cache(cache: State<MediaCache>) -> Result<(), String> {
+    log::info!("Clearing media cache at {:?}", cache.root);
 
-    // Check if input file exists
-    if !PathBuf::from(&options.input_path).exists() {
-        return Err("Input file does not exist".to_string());
+    let _guard = cache.eviction_lock.lock().unwrap();
+    if cache.root.exists() {
+        std::fs::remove_dir_all(&cache.root)
+            .map_err(|e| format!("Failed to clear media cache: {}", e))?;
     }
+    Ok(())
+}
 
-    // Check if input has an audio stream
-    let ffprobe = find_ffprobe();
-    let probe_output = Command::new(&ffprobe)
-        .args(&[
-            "-v", "quiet",
-            "-print_format", "json",
-            "-show_streams",
-            "-select_streams", "a",
-            &options.input_path
-        ])
-        .output()
-        .map_err(|e| format!("Failed to probe input: {}", e))?;
-
-    let json_str = String::from_utf8_lossy(&probe_output.stdout);
-    let json: serde_json::Value = serde_json::from_str(&json_str)
-        .unwrap_or(serde_json::json!({"streams": []}));
+// Default on-timeline duration for imported stills (PNG/JPEG/WebP), in seconds
+const DEFAULT_STILL_DURATION: f64 = 5.0;
 
-    let has_audio = json["streams"].as_array()
-        .map(|arr| !arr.is_empty())
-        .unwrap_or(false);
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AudioStreamInfo {
+    index: u32,                 // Absolute stream index in the container
+    codec: Option<String>,      // e.g., "aac", "opus", "pcm_s16le"
+    channels: Option<u32>,
+    sample_rate: Option<u32>,
+    language: Option<String>,   // ISO 639 tag if present, e.g., "eng"
+}
 
-    // Build FFmpeg command
-    let ffmpeg = find_ffmpeg();
-    let mut cmd = Command::new(&ffmpeg);
-    cmd.arg("-i").arg(&options.input_path);
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SubtitleStreamInfo {
+    index: u32,                 // Absolute stream index in the container
+    codec: Option<String>,      // e.g., "mov_text", "subrip", "ass", "hdmv_pgs_subtitle"
+    language: Option<String>,
+    title: Option<String>,
+    is_text: bool,              // False for image-based subtitles (PGS, VobSub), which can't be extracted as text
+}
 
-    // Add trim parameters if specified
-    if let (Some(start), Some(end)) = (options.trim_start, options.trim_end) {
-        let duration = end - start;
-        cmd.arg("-ss").arg(start.to_string());
-        cmd.arg("-t").arg(duration.to_string());
-    }
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChapterMarker {
+    start: f64,
+    end: f64,
+    title: Option<String>,
+}
 
-    // Output options
-    cmd.arg("-c:v").arg("libx264")
-        .arg("-preset").arg("fast")
-        .arg("-crf").arg("23");
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MediaProbe {
+    duration: f64,
+    has_video: bool,                 // False for audio-only files (mp3, wav, m4a)
+    width: Option<u32>,              // Coded width, before rotation is applied
+    height: Option<u32>,             // Coded height, before rotation is applied
+    display_width: Option<u32>,      // Width as shown to the user, after rotation
+    display_height: Option<u32>,     // Height as shown to the user, after rotation
+    container: Option<String>,       // ffprobe format_name, e.g., "mov,mp4,m4a,3gp,3g2,mj2"
+    video_codec: Option<String>,     // e.g., "h264", "hevc", "prores"
+    pixel_format: Option<String>,    // e.g., "yuv420p", "yuv422p10le"
+    avg_frame_rate: Option<f64>,     // Average frame rate over the whole stream
+    real_frame_rate: Option<f64>,    // r_frame_rate: lowest rate that represents all timestamps
+    is_vfr: bool,                    // Variable frame rate (avg and real rates disagree)
+    bit_rate: Option<u64>,           // Overall container bitrate in bits/s
+    rotation: i32,                   // Display rotation in degrees (0 if none)
+    mirrored: bool,                  // Display matrix flips the image (front camera footage)
+    color_space: Option<String>,     // e.g., "bt709", "bt2020nc"
+    audio_streams: Vec<AudioStreamInfo>,
+    subtitle_streams: Vec<SubtitleStreamInfo>,
+    chapters: Vec<ChapterMarker>,
+}
 
-    if has_audio {
-        cmd.arg("-c:a").arg("aac")
-            .arg("-b:a").arg("128k");
+#[derive(Debug, Serialize, Deserialize)]
+pub struct VideoMetadata {
+    path: String,
+    filename: String,
+    duration: Option<f64>,
+    width: Option<u32>,              // None for audio-only media
+    height: Option<u32>,             // None for audio-only media
+    thumbnail_path: Option<String>,
+    media_type: String,              // "video", "audio" or "image"
+    working_path: Option<String>,    // CFR-normalized copy to edit and export from, if normalize_frame_rate made one
+    proxy_path: Option<String>,      // Preview proxy from an earlier generate_proxy, if still cached
+    file_size: Option<u64>,          // Remembered so moved files can be relinked
+    fingerprint: Option<String>,     // Content fingerprint, see media_content_fingerprint
+    probe: MediaProbe,
+}
+
+#[tauri::command]
+fn import_video(path: String, cache: State<MediaCache>, app: tauri::AppHandle) -> Result<VideoMetadata, String> {
+    log::info!("import_video called with path: {}", path);
+    let metadata = import_media_file(&path, &cache)?;
+    if let Some(proxy_path) = &metadata.proxy_path {
+        register_proxy(&app, &path, proxy_path);
     }
+    Ok(metadata)
+}
 
-    cmd.arg("-y") // Overwrite output file
-        .arg(&options.output_path);
+// Helper function to probe and thumbnail a single media file
+fn import_media_file(path: &str, cache: &MediaCache) -> Result<VideoMetadata, String> {
+    let path_buf = PathBuf::from(path);
 
-    log::info!("Running FFmpeg command: {:?}", cmd);
+    if !path_buf.exists() {
+        log::error!("File does not exist: {}", path);
+        return Err("File does not exist".to_string());
+    }
 
-    // Execute FFmpeg
-    let output = cmd.output().map_err(|e| {
-        format!("Failed to execute FFmpeg. Make sure FFmpeg is installed. Error: {}", e)
-    })?;
+    let filename = path_buf
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or("unknown")
+        .to_string();
 
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        log::error!("FFmpeg failed: {}", stderr);
-        return Err(format!("FFmpeg export failed: {}", stderr));
-    }
+    // Identify the file by content so replaced files don't get stale cached results
+    let cache_key = match media_file_key(path) {
+        Ok(key) => Some(key),
+        Err(e) => {
+            log::warn!("Failed to compute media cache key, skipping cache: {}", e);
+            None
+        }
+    };
 
-    log::info!("Export successful: {}", options.output_path);
-    Ok(options.output_path)
-}
+    log::info!("Probing video metadata for: {}", filename);
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct TextOverlay {
-    text: String,
-    x_position: String,  // e.g., "10", "(w-text_w)/2", etc.
-    y_position: String,  // e.g., "10", "h-th-10", etc.
-    font_size: u32,
-    font_color: String,  // e.g., "white"
-    box_enabled: bool,
-    box_color: Option<String>,  // e.g., "black@0.5"
-    box_border_width: Option<u32>,
+    // Use FFmpeg to probe video metadata (or reuse a cached probe of the same file)
+    let cached_probe = cache_key.as_deref()
+        .and_then(|key| cache.read_json::<MediaProbe>(key, "probe.json"));
+
+    let probe = match cached_probe {
+        Some(probe) => {
+            log::info!("Using cached probe for: {}", filename);
+            probe
+        },
+        None => match probe_video_metadata(path) {
+            Ok(probe) => {
+                log::info!("Successfully probed metadata - Duration: {}s, Resolution: {:?}x{:?}, Codec: {:?}",
+                          probe.duration, probe.width, probe.height, probe.video_codec);
+                if let Some(key) = cache_key.as_deref() {
+                    if let Err(e) = cache.write_json(key, "probe.json", &probe) {
+                        log::warn!("Failed to cache probe result: {}", e);
+                    }
+                }
+                probe
+            },
+            Err(e) => {
+                log::error!("Failed to probe video metadata: {}", e);
+                return Err(e);
+            }
+        },
+    };
+
+    let media_type = media_type_for(&probe);
+
+    // Stills have no intrinsic duration; give them a default display duration on the timeline
+    let duration = if media_type == "image" { DEFAULT_STILL_DURATION } else { probe.duration };
+
+    // Generate thumbnail (non-fatal if it fails); audio-only media has no frames to grab
+    let thumbnail_path = if !probe.has_video {
+        log::info!("Audio-only media, skipping thumbnail");
+        None
+    } else {
+        match generate_thumbnail(path, probe.duration, cache, cache_key.as_deref()) {
+            Ok(thumb_path) => {
+                log::info!("Thumbnail generated: {}", thumb_path);
+                Some(thumb_path)
+            },
+            Err(e) => {
+                log::warn!("Failed to generate thumbnail: {}", e);
+                None
+            }
+        }
+    };
+
+    // Normalizing is a full re-encode, so import only picks up a CFR working copy that
+    // normalize_frame_rate already made; VFR sources are flagged in the probe for the frontend to offer it
+    let working_path = match cache_key.as_deref() {
+        Some(key) if probe.is_vfr => cache.cached_file(key, CFR_WORKING_COPY_NAME)
+            .map(|working_path| working_path.to_string_lossy().to_string()),
+        _ => None,
+    };
+
+    // Proxies are cached per content key, so one made in an earlier session is picked back up here
+    let proxy_path = cache_key.as_deref()
+        .and_then(|key| cache.cached_file(key, PROXY_CACHE_NAME))
+        .map(|proxy_path| proxy_path.to_string_lossy().to_string());
+
+    let file_size = std::fs::metadata(path).map(|m| m.len()).ok();
+    let fingerprint = media_content_fingerprint(path).ok();
+
+    // Report the displayed orientation so portrait phone clips are not shown as landscape
+    let result = VideoMetadata {
+        path: path.to_string(),
+        filename,
+        duration: Some(duration),
+        width: probe.display_width,
+        height: probe.display_height,
+        thumbnail_path,
+        media_type: media_type.to_string(),
+        working_path,
+        proxy_path,
+        file_size,
+        fingerprint,
+        probe,
+    };
+
+    log::info!("Returning metadata: {:?}", result);
+    Ok(result)
 }
 
+// File extensions accepted by batch import, whether passed explicitly or found in a folder
+const IMPORTABLE_EXTENSIONS: &[&str] = &[
+    // Video
+    "mp4", "mov", "m4v", "mkv", "webm", "avi", "mts", "m2ts", "ts", "flv", "wmv", "3gp",
+    // Audio
+    "mp3", "wav", "m4a", "aac", "flac", "ogg", "opus", "aif", "aiff",
+    // Images
+    "png", "jpg", "jpeg", "webp",
+];
+
 #[derive(Debug, Serialize, Deserialize)]
-pub struct ClipSegment {
-    input_path: String,
-    trim_start: Option<f64>,
-    trim_end: Option<f64>,
-    audio_trim_start: Option<f64>,
-    audio_trim_end: Option<f64>,
-    is_video_muted: Option<bool>,
-    is_audio_muted: Option<bool>,
-    is_audio_linked: Option<bool>,
-    audio_offset: Option<f64>,
-    text_overlay: Option<TextOverlay>,
+pub struct BatchImportError {
+    path: String,
+    error: String,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
-pub struct MultiClipExportOptions {
-    clips: Vec<ClipSegment>,
-    output_path: String,
+pub struct BatchImportResult {
+    imported: Vec<VideoMetadata>,
+    errors: Vec<BatchImportError>,
+    skipped: Vec<String>,  // Files (passed explicitly or found in the folder) that are not media
 }
 
 #[derive(Clone, serde::Serialize)]
-struct MergeProgress {
-    current: usize,
+struct ImportProgress {
+    path: String,
+    completed: usize,
     total: usize,
-    status: String,
+    status: String,  // "imported" or "failed"
+    error: Option<String>,
 }
 
-#[tauri::command]
-fn export_multi_clip(options: MultiClipExportOptions, window: tauri::Window) -> Result<String, String> {
-    log::info!("Starting multi-clip export with {} clips", options.clips.len());
+// Helper function to check a file extension against the importable media list
+fn is_importable_media(path: &std::path::Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| IMPORTABLE_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+        .unwrap_or(false)
+}
 
-    if options.clips.is_empty() {
-        return Err("No clips to export".to_string());
-    }
+// Helper function to list files in a folder, splitting them into media and non-media
+// A folder that can't be read is recorded in `errors` and the scan carries on with the rest.
+// Symlinked folders are not followed, so a link back up the tree can't recurse forever.
+fn collect_media_files(
+    dir: &std::path::Path,
+    recursive: bool,
+    media: &mut Vec<String>,
+    skipped: &mut Vec<String>,
+    errors: &mut Vec<BatchImportError>,
+) {
+    let read_dir = match std::fs::read_dir(dir) {
+        Ok(read_dir) => read_dir,
+        Err(e) => {
+            log::warn!("Failed to read directory {:?}: {}", dir, e);
+            errors.push(BatchImportError {
+                path: dir.to_string_lossy().to_string(),
+                error: format!("Failed to read directory: {}", e),
+            });
+            return;
+        }
+    };
 
-    // Emit initial progress
-    let _ = window.emit("merge-progress", MergeProgress {
-        current: 0,
-        total: options.clips.len(),
-        status: "Starting merge...".to_string(),
-    });
+    // DirEntry::file_type doesn't follow symlinks, unlike Path::is_dir
+    let mut entries: Vec<(PathBuf, Option<std::fs::FileType>)> = read_dir
+        .flatten()
+        .map(|entry| (entry.path(), entry.file_type().ok()))
+        .collect();
+    entries.sort_by(|a, b| a.0.cmp(&b.0));
 
-    // Process all clips through the same path to support text overlays and other features
-    // For multiple clips, we need to:
-    // 1. Export each trimmed clip to a temp file
-    // 2. Concat all temp files
-    // 3. Clean up temp files
+    for (entry, file_type) in entries {
+        let is_hidden = entry.file_name()
+            .and_then(|n| n.to_str())
+            .map(|n| n.starts_with('.'))
+            .unwrap_or(false);
+        if is_hidden {
+            continue;
+        }
 
-    let temp_dir = std::env::temp_dir();
-    let mut temp_files: Vec<PathBuf> = Vec::new();
+        let is_symlink = file_type.map(|t| t.is_symlink()).unwrap_or(false);
+        if file_type.map(|t| t.is_dir()).unwrap_or(false) {
+            if recursive {
+                collect_media_files(&entry, recursive, media, skipped, errors);
+            }
+        } else if is_symlink && entry.is_dir() {
+            log::info!("Not following symlinked directory {:?}", entry);
+        } else if is_importable_media(&entry) {
+            media.push(entry.to_string_lossy().to_string());
+        } else {
+            skipped.push(entry.to_string_lossy().to_string());
+        }
+    }
+}
 
-    // Step 1: Export each clip with trim applied and handle audio/video separation
-    for (i, clip) in options.clips.iter().enumerate() {
-        // Emit progress for current clip
-        let _ = window.emit("merge-progress", MergeProgress {
-            current: i + 1,
-            total: options.clips.len(),
-            status: format!("Processing clip {} of {}...", i + 1, options.clips.len()),
-        });
+// Helper function to gather the files a batch import should probe: explicit paths and the folder's
+// files pass the same extension filter, and duplicates (e.g., a file passed explicitly that is also
+// in the folder) are dropped. Returns the media files, the skipped non-media files and folder errors.
+fn gather_import_files(
+    paths: Vec<String>,
+    directory: Option<&str>,
+    recursive: bool,
+) -> (Vec<String>, Vec<String>, Vec<BatchImportError>) {
+    let (mut files, mut skipped): (Vec<String>, Vec<String>) = paths
+        .into_iter()
+        .partition(|path| is_importable_media(std::path::Path::new(path)));
+    let mut errors: Vec<BatchImportError> = Vec::new();
+
+    if let Some(dir) = directory {
+        log::info!("Scanning {} for media (recursive: {})", dir, recursive);
+        collect_media_files(std::path::Path::new(dir), recursive, &mut files, &mut skipped, &mut errors);
+    }
 
-        let temp_path = temp_dir.join(format!("clipforge_temp_{}.mp4", i));
+    let mut seen = std::collections::HashSet::new();
+    files.retain(|path| seen.insert(path.clone()));
+    let mut seen = std::collections::HashSet::new();
+    skipped.retain(|path| seen.insert(path.clone()));
 
-        log::info!("Exporting clip {} to temp file: {:?}", i, temp_path);
+    (files, skipped, errors)
+}
 
-        // Round trim values to 3 decimal places to avoid ffmpeg precision issues
-        let trim_start = clip.trim_start.map(round_to_millis);
-        let trim_end = clip.trim_end.map(round_to_millis);
-        let audio_trim_start = clip.audio_trim_start.or(trim_start).map(round_to_millis);
-        let audio_trim_end = clip.audio_trim_end.or(trim_end).map(round_to_millis);
+// Import many files at once (explicit paths and/or a folder), probing them in parallel.
+// One bad file doesn't fail the batch: successes and per-file errors are both returned.
+#[tauri::command]
+async fn import_media_batch(
+    paths: Option<Vec<String>>,
+    directory: Option<String>,
+    recursive: Option<bool>,
+    app: tauri::AppHandle,
+) -> Result<BatchImportResult, String> {
+    let (files, skipped, mut errors) = gather_import_files(paths.unwrap_or_default(), directory.as_deref(), recursive.unwrap_or(false));
+    log::info!("Batch importing {} files ({} non-media skipped)", files.len(), skipped.len());
+
+    let results = tauri::async_runtime::spawn_blocking(move || {
+        let cache = app.state::<MediaCache>();
+        let total = files.len();
+
+        // Each import runs ffprobe/ffmpeg processes, so keep the pool small
+        let workers = std::thread::available_parallelism()
+            .map(|n| n.get())
+            .unwrap_or(2)
+            .clamp(1, 4)
+            .min(total.max(1));
+
+        let next_index = std::sync::atomic::AtomicUsize::new(0);
+        let completed = std::sync::atomic::AtomicUsize::new(0);
+        let results: Mutex<Vec<Option<Result<VideoMetadata, String>>>> =
+            Mutex::new((0..total).map(|_| None).collect());
+
+        std::thread::scope(|scope| {
+            for _ in 0..workers {
+                scope.spawn(|| loop {
+                    let index = next_index.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                    if index >= total {
+                        break;
+                    }
 
-        // Check if input has an audio stream
-        let ffprobe = find_ffprobe();
-        let probe_output = Command::new(&ffprobe)
-            .args(&[
-                "-v", "quiet",
-                "-print_format", "json",
-                "-show_streams",
-                "-select_streams", "a",
-                &clip.input_path
-            ])
-            .output()
-            .map_err(|e| format!("Failed to probe clip {}: {}", i, e))?;
+                    let path = &files[index];
+                    let result = import_media_file(path, &cache);
+                    if let Some(proxy_path) = result.as_ref().ok().and_then(|metadata| metadata.proxy_path.as_ref()) {
+                        register_proxy(&app, path, proxy_path);
+                    }
+                    let done = completed.fetch_add(1, std::sync::atomic::Ordering::SeqCst) + 1;
+
+                    let _ = app.emit("import-progress", ImportProgress {
+                        path: path.clone(),
+                        completed: done,
+                        total,
+                        status: if result.is_ok() { "imported" } else { "failed" }.to_string(),
+                        error: result.as_ref().err().cloned(),
+                    });
+
+                    results.lock().unwrap()[index] = Some(result);
+                });
+            }
+        });
 
-        let json_str = String::from_utf8_lossy(&probe_output.stdout);
-        let json: serde_json::Value = serde_json::from_str(&json_str)
-            .unwrap_or(serde_json::json!({"streams": []}));
+        files.into_iter()
+            .zip(results.into_inner().unwrap())
+            .collect::<Vec<_>>()
+    })
+    .await
+    .map_err(|e| format!("Batch import task failed: {}", e))?;
 
-        let has_audio = json["streams"].as_array()
-            .map(|arr| !arr.is_empty())
-            .unwrap_or(false);
+    let mut imported = Vec::new();
 
-        log::info!("Clip {} has audio: {}", i, has_audio);
+    for (path, result) in results {
+        match result {
+            Some(Ok(metadata)) => imported.push(metadata),
+            Some(Err(error)) => {
+                log::warn!("Failed to import {}: {}", path, error);
+                errors.push(BatchImportError { path, error });
+            }
+            None => errors.push(BatchImportError { path, error: "File was not processed".to_string() }),
+        }
+    }
 
-        let ffmpeg = find_ffmpeg();
-        let mut cmd = Command::new(&ffmpeg);
-        cmd.arg("-i").arg(&clip.input_path);
+    log::info!("Batch import finished: {} imported, {} failed", imported.len(), errors.len());
+    Ok(BatchImportResult { imported, errors, skipped })
+}
 
-        let is_video_muted = clip.is_video_muted.unwrap_or(false);
-        let is_audio_muted = clip.is_audio_muted.unwrap_or(false);
-        let is_audio_linked = clip.is_audio_linked.unwrap_or(true);
-        let audio_offset = clip.audio_offset.unwrap_or(0.0);
+// Helper function to detect still images (PNG/JPEG/WebP) from the probed container
+// FFmpeg opens single images with the image2 demuxer or a "<codec>_pipe" demuxer
+fn is_still_image(probe: &MediaProbe) -> bool {
+    probe.has_video
+        && probe.container
+            .as_deref()
+            .map(|c| c == "image2" || c.ends_with("_pipe"))
+            .unwrap_or(false)
+}
 
-        // Check if audio trim is different from video trim
-        let has_independent_audio_trim = audio_trim_start != trim_start || audio_trim_end != trim_end;
+// Helper function to classify probed media for the frontend
+fn media_type_for(probe: &MediaProbe) -> &'static str {
+    if !probe.has_video {
+        "audio"
+    } else if is_still_image(probe) {
+        "image"
+    } else {
+        "video"
+    }
+}
 
-        // Add trim parameters for video (if audio trim is independent, we'll handle it with filters)
-        if !has_independent_audio_trim {
-            // Audio and video trim are the same, apply trim to entire file
-            if let (Some(start), Some(end)) = (trim_start, trim_end) {
-                let raw_duration = end - start;
-                // Round duration to avoid floating point precision issues
-                let duration = round_to_millis(raw_duration);
-                cmd.arg("-ss").arg(start.to_string());
-                cmd.arg("-t").arg(duration.to_string());
+// Helper function to parse ffprobe rational values like "30000/1001" into a float
+// Returns None for missing or degenerate values such as "0/0"
+fn parse_rational(value: &str) -> Option<f64> {
+    let parsed = match value.split_once('/') {
+        Some((num, den)) => {
+            let num: f64 = num.trim().parse().ok()?;
+            let den: f64 = den.trim().parse().ok()?;
+            if den == 0.0 {
+                return None;
             }
+            num / den
         }
+        None => value.trim().parse().ok()?,
+    };
 
-        // Calculate clip duration for generating placeholders (already rounded from trim values)
-        let duration = if let (Some(start), Some(end)) = (trim_start, trim_end) {
-            let raw_duration = end - start;
-            // Round duration to avoid floating point precision issues
-            let rounded = round_to_millis(raw_duration);
-            log::info!("Clip {} duration: raw={}, start={}, end={}, rounded={}", i, raw_duration, start, end, rounded);
-            rounded
-        } else {
-            // Use ffprobe to get duration if not trimmed
-            let ffprobe = find_ffprobe();
-            let probe_output = Command::new(&ffprobe)
-                .arg("-v").arg("error")
-                .arg("-show_entries").arg("format=duration")
-                .arg("-of").arg("default=noprint_wrappers=1:nokey=1")
-                .arg(&clip.input_path)
-                .output()
-                .map_err(|e| format!("Failed to probe clip {}: {}", i, e))?;
-
-            let parsed_duration = String::from_utf8_lossy(&probe_output.stdout)
-                .trim()
-                .parse::<f64>()
-                .unwrap_or(0.0);
-            let rounded = round_to_millis(parsed_duration);
-            log::info!("Clip {} duration from ffprobe: raw={}, rounded={}", i, parsed_duration, rounded);
-            rounded
-        };
+    if parsed.is_finite() && parsed > 0.0 {
+        Some(parsed)
+    } else {
+        None
+    }
+}
 
-        // Build filter complex for handling muted tracks, audio offset, and independent audio trim
-        let mut filter_parts = Vec::new();
-        let mut needs_filter = has_independent_audio_trim;
+// Helper function to detect variable frame rate from ffprobe's two frame rates
+// CFR streams report matching r_frame_rate and avg_frame_rate; allow 0.5% slack for
+// rounding in avg_frame_rate (frame count / duration) on short clips
+fn is_variable_frame_rate(avg_frame_rate: Option<f64>, real_frame_rate: Option<f64>) -> bool {
+    match (avg_frame_rate, real_frame_rate) {
+        (Some(avg), Some(real)) => ((real - avg).abs() / real) > 0.005,
+        _ => false,
+    }
+}
 
-        // Handle video
-        let mut video_filter = if is_video_muted {
-            needs_filter = true;
-            let duration_str = format!("{:.3}", duration);
-            format!("color=c=black:s=1920x1080:d={},fps=30", duration_str)
-        } else if has_independent_audio_trim {
-            // Apply video trim using filter
-            needs_filter = true;
-            if let (Some(start), Some(end)) = (trim_start, trim_end) {
-                format!("[0:v]trim=start={}:end={},setpts=PTS-STARTPTS", start, end)
-            } else {
-                "[0:v]null".to_string()
-            }
-        } else {
-            "[0:v]null".to_string()
-        };
+// Helper function to read an optional string field, treating ffprobe's "unknown" as missing
+fn probe_string(value: &serde_json::Value) -> Option<String> {
+    value
+        .as_str()
+        .filter(|s| !s.is_empty() && *s != "unknown")
+        .map(|s| s.to_string())
+}
 
-        // Add text overlay if present
-        if let Some(overlay) = &clip.text_overlay {
-            needs_filter = true;
-            let escaped_text = escape_ffmpeg_text(&overlay.text);
-
-            // Use system font (Helvetica on macOS)
-            let font_path = "/System/Library/Fonts/Supplemental/Arial.ttf";
-
-            let mut drawtext_params = format!(
-                "drawtext=text='{}':fontfile={}:fontsize={}:fontcolor={}:x={}:y={}",
-                escaped_text,
-                font_path,
-                overlay.font_size,
-                overlay.font_color,
-                overlay.x_position,
-                overlay.y_position
-            );
+// Helper function to read a numeric field that ffprobe may emit either as a number or a string
+fn probe_number(value: &serde_json::Value) -> Option<f64> {
+    value
+        .as_f64()
+        .or_else(|| value.as_str().and_then(|s| s.trim().parse::<f64>().ok()))
+}
 
-            // Add box if enabled
-            if overlay.box_enabled {
-                drawtext_params.push_str(":box=1");
-                if let Some(ref box_color) = overlay.box_color {
-                    drawtext_params.push_str(&format!(":boxcolor={}", box_color));
-                }
-                if let Some(border_width) = overlay.box_border_width {
-                    drawtext_params.push_str(&format!(":boxborderw={}", border_width));
-                }
-            }
+// Helper function to find the "Display Matrix" side data entry of a video stream
+fn display_matrix_side_data(stream: &serde_json::Value) -> Option<&serde_json::Value> {
+    stream["side_data_list"]
+        .as_array()
+        .and_then(|list| list.iter().find(|entry| entry["side_data_type"] == "Display Matrix"))
+}
 
-            video_filter.push_str(&format!(",{}", drawtext_params));
-        }
+// Helper function to read the display rotation of a video stream
+// Newer ffprobe reports it in the "Display Matrix" side data, older builds in the "rotate" tag
+fn parse_stream_rotation(stream: &serde_json::Value) -> i32 {
+    let side_data_rotation = display_matrix_side_data(stream)
+        .and_then(|entry| probe_number(&entry["rotation"]));
 
-        video_filter.push_str("[v]");
-        filter_parts.push(video_filter);
+    let rotation = side_data_rotation
+        .or_else(|| probe_number(&stream["tags"]["rotate"]))
+        .unwrap_or(0.0);
 
-        // Handle audio
-        let audio_filter = if !has_audio || is_audio_muted {
-            // No audio stream or audio is muted - generate silent audio
-            // Don't specify duration - let -shortest flag stop when video ends
-            needs_filter = true;
-            log::info!("Clip {} generating silent audio (infinite, will stop with -shortest)", i);
-            "anullsrc=channel_layout=stereo:sample_rate=44100[a]".to_string()
-        } else if has_independent_audio_trim {
-            // Apply audio-specific trim
-            needs_filter = true;
-            if let (Some(start), Some(end)) = (audio_trim_start, audio_trim_end) {
-                let mut audio_chain = format!("[0:a]atrim=start={}:end={},asetpts=PTS-STARTPTS", start, end);
-
-                // Add audio offset if needed
-                if !is_audio_linked && audio_offset != 0.0 {
-                    let delay_ms = (audio_offset * 1000.0).round() as i64;
-                    if delay_ms > 0 {
-                        audio_chain.push_str(&format!(",adelay={}|{}", delay_ms, delay_ms));
-                    }
-                }
+    // Normalize to 0..360 so callers only deal with 0/90/180/270
+    (rotation.round() as i32).rem_euclid(360)
+}
 
-                audio_chain.push_str("[a]");
-                audio_chain
-            } else {
-                // If no trim specified, still use audio but ensure we're using filter mode
-                needs_filter = true;
-                "[0:a]anull[a]".to_string()
-            }
-        } else if !is_audio_linked && audio_offset != 0.0 {
-            needs_filter = true;
-            let delay_ms = (audio_offset * 1000.0).round() as i64;
-            if delay_ms > 0 {
-                format!("[0:a]adelay={}|{}[a]", delay_ms, delay_ms)
-            } else if delay_ms < 0 {
-                format!("[0:a]atrim=start={}[a]", -audio_offset)
-            } else {
-                "[0:a]anull[a]".to_string()
-            }
-        } else if has_audio {
-            // Has audio but no special processing - use pass-through filter for consistency
-            needs_filter = true;
-            "[0:a]anull[a]".to_string()
-        } else {
-            // Fallback: generate silent audio
-            // Don't specify duration - let -shortest flag stop when video ends
-            needs_filter = true;
-            "anullsrc=channel_layout=stereo:sample_rate=44100[a]".to_string()
-        };
-        filter_parts.push(audio_filter);
-
-        // Add filter_complex and map outputs
-        if needs_filter || is_video_muted || is_audio_muted || (!is_audio_linked && audio_offset != 0.0) || !has_audio {
-            let filter_str = filter_parts.join(";");
-            cmd.arg("-filter_complex").arg(&filter_str);
-            cmd.arg("-map").arg("[v]");
-            cmd.arg("-map").arg("[a]");
-        } else {
-            // No special processing needed, use default mapping
-            cmd.arg("-map").arg("0:v");
-            if has_audio {
-                cmd.arg("-map").arg("0:a");
-            }
-        }
+// Helper function to detect a mirrored display matrix
+// ffprobe prints the 3x3 matrix as text rows like "00000000:  -65536  0  0"; a negative
+// determinant of the upper-left 2x2 block means the image is flipped
+fn parse_stream_mirrored(stream: &serde_json::Value) -> bool {
+    let matrix_text = match display_matrix_side_data(stream).and_then(|entry| entry["displaymatrix"].as_str()) {
+        Some(text) => text,
+        None => return false,
+    };
 
-        // Output options - re-encode to ensure compatibility
-        // Use ultrafast preset for temp files to speed up processing
-        cmd.arg("-c:v").arg("libx264")
-            .arg("-preset").arg("ultrafast")
-            .arg("-crf").arg("23")
-            .arg("-c:a").arg("aac")
-            .arg("-b:a").arg("128k")
-            .arg("-shortest")  // Stop when the shortest stream ends (prevents audio/video sync issues)
-            .arg("-y")
-            .arg(&temp_path);
+    let values: Vec<f64> = matrix_text
+        .lines()
+        .filter_map(|line| line.split_once(':').map(|(_, row)| row))
+        .flat_map(|row| row.split_whitespace().filter_map(|v| v.parse::<f64>().ok()))
+        .collect();
+
+    if values.len() < 5 {
+        return false;
+    }
+
+    let determinant = values[0] * values[4] - values[1] * values[3];
+    determinant < 0.0
+}
+
+// Helper function to get the dimensions a video is displayed at once rotation is applied
+fn displayed_dimensions(width: u32, height: u32, rotation: i32) -> (u32, u32) {
+    if rotation == 90 || rotation == 270 {
+        (height, width)
+    } else {
+        (width, height)
+    }
+}
+
+// Helper function to build a typed probe result from ffprobe's JSON output
+fn parse_media_probe(json: &serde_json::Value) -> Result<MediaProbe, String> {
+    // Extract duration from format section
+    let duration = probe_number(&json["format"]["duration"]).unwrap_or(0.0);
 
-        log::info!("FFmpeg command: {:?}", cmd);
+    log::info!("Extracted duration: {} seconds", duration);
 
-        let output = cmd.output().map_err(|e| {
-            format!("Failed to execute FFmpeg for clip {}: {}", i, e)
+    let streams = json["streams"].as_array()
+        .ok_or_else(|| {
+            log::error!("No streams array in ffprobe output");
+            "No streams found in video".to_string()
         })?;
 
-        if !output.status.success() {
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            log::error!("FFmpeg failed for clip {}: {}", i, stderr);
-
-            // Clean up temp files
-            for temp_file in &temp_files {
-                let _ = std::fs::remove_file(temp_file);
-            }
-
-            // Parse error and provide user-friendly message
-            let error_msg = if stderr.contains("Could not open encoder before EOF") || stderr.contains("Invalid argument") {
-                format!("Failed to process clip {} due to audio/video sync issues. This can happen with very short clips or corrupted files. Try adjusting the clip boundaries slightly.", i + 1)
-            } else if stderr.contains("No such file or directory") {
-                format!("Clip {} file not found. The source video may have been moved or deleted.", i + 1)
-            } else if stderr.contains("Invalid data found") {
-                format!("Clip {} appears to be corrupted or in an unsupported format.", i + 1)
-            } else if stderr.contains("Permission denied") {
-                format!("Permission denied while processing clip {}. Check file permissions.", i + 1)
-            } else {
-                // Extract just the key error lines instead of dumping everything
-                let key_errors: Vec<&str> = stderr.lines()
-                    .filter(|line| line.contains("Error") || line.contains("failed") || line.contains("Invalid"))
-                    .take(3)  // Only show first 3 error lines
-                    .collect();
+    log::info!("Found {} streams", streams.len());
 
-                if !key_errors.is_empty() {
-                    format!("Failed to process clip {}: {}", i + 1, key_errors.join("; "))
-                } else {
-                    format!("Failed to process clip {}. Check the logs for details.", i + 1)
-                }
-            };
+    let audio_streams: Vec<AudioStreamInfo> = streams.iter()
+        .filter(|s| s["codec_type"] == "audio")
+        .map(|s| AudioStreamInfo {
+            index: s["index"].as_u64().unwrap_or(0) as u32,
+            codec: probe_string(&s["codec_name"]),
+            channels: s["channels"].as_u64().map(|c| c as u32),
+            sample_rate: probe_number(&s["sample_rate"]).map(|r| r as u32),
+            language: probe_string(&s["tags"]["language"]),
+        })
+        .collect();
 
-            return Err(error_msg);
-        }
+    let subtitle_streams: Vec<SubtitleStreamInfo> = streams.iter()
+        .filter(|s| s["codec_type"] == "subtitle")
+        .map(|s| {
+            let codec = probe_string(&s["codec_name"]);
+            let is_text = !matches!(
+                codec.as_deref(),
+                Some("hdmv_pgs_subtitle") | Some("dvd_subtitle") | Some("dvb_subtitle") | Some("xsub")
+            );
+            SubtitleStreamInfo {
+                index: s["index"].as_u64().unwrap_or(0) as u32,
+                codec,
+                language: probe_string(&s["tags"]["language"]),
+                title: probe_string(&s["tags"]["title"]),
+                is_text,
+            }
+        })
+        .collect();
 
-        temp_files.push(temp_path);
+    let chapters: Vec<ChapterMarker> = json["chapters"].as_array()
+        .map(|list| {
+            list.iter()
+                .filter_map(|c| {
+                    Some(ChapterMarker {
+                        start: probe_number(&c["start_time"])?,
+                        end: probe_number(&c["end_time"])?,
+                        title: probe_string(&c["tags"]["title"]),
+                    })
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    // Attached pictures (cover art in mp3/m4a) show up as video streams but are not real video
+    let video_stream = streams.iter()
+        .find(|s| s["codec_type"] == "video" && s["disposition"]["attached_pic"] != 1);
+
+    if video_stream.is_none() && audio_streams.is_empty() {
+        log::error!("No video or audio stream found in {} streams", streams.len());
+        return Err("No video or audio stream found".to_string());
     }
 
-    // Emit progress for concatenation step
-    let _ = window.emit("merge-progress", MergeProgress {
-        current: options.clips.len(),
-        total: options.clips.len(),
-        status: "Merging clips together...".to_string(),
-    });
+    // Audio-only files have no video stream; indexing Null yields Null, so every video field is None
+    let has_video = video_stream.is_some();
+    let video_stream = video_stream.unwrap_or(&serde_json::Value::Null);
 
-    // Step 2: Probe all temp files to get their resolutions
-    let ffprobe = find_ffprobe();
-    let mut max_width = 0;
-    let mut max_height = 0;
+    let width = video_stream["width"].as_u64().map(|w| w as u32);
+    let height = video_stream["height"].as_u64().map(|h| h as u32);
 
-    for temp_file in &temp_files {
-        let probe_output = Command::new(&ffprobe)
-            .arg("-v").arg("error")
-            .arg("-select_streams").arg("v:0")
-            .arg("-show_entries").arg("stream=width,height")
-            .arg("-of").arg("csv=p=0")
-            .arg(temp_file)
-            .output()
-            .map_err(|e| format!("Failed to probe temp file: {}", e))?;
+    if has_video && (width.is_none() || height.is_none()) {
+        log::warn!("Video stream is missing dimensions: {:?}x{:?}", width, height);
+    }
 
-        let dimensions = String::from_utf8_lossy(&probe_output.stdout);
-        let parts: Vec<&str> = dimensions.trim().split(',').collect();
-        if parts.len() == 2 {
-            let width: u32 = parts[0].parse().unwrap_or(1920);
-            let height: u32 = parts[1].parse().unwrap_or(1080);
-            max_width = max_width.max(width);
-            max_height = max_height.max(height);
+    let rotation = parse_stream_rotation(video_stream);
+    let mirrored = parse_stream_mirrored(video_stream);
+
+    let (display_width, display_height) = match (width, height) {
+        (Some(w), Some(h)) => {
+            let (dw, dh) = displayed_dimensions(w, h, rotation);
+            (Some(dw), Some(dh))
         }
-    }
+        _ => (width, height),
+    };
 
-    log::info!("Target resolution for concat: {}x{}", max_width, max_height);
+    log::info!("Extracted resolution: {:?}x{:?} (rotation {}°, mirrored: {}, displayed as {:?}x{:?})",
+              width, height, rotation, mirrored, display_width, display_height);
 
-    // Step 3: Concat using filter_complex with scaling/padding for different resolutions
-    let ffmpeg = find_ffmpeg();
-    let mut concat_cmd = Command::new(&ffmpeg);
+    let avg_frame_rate = video_stream["avg_frame_rate"].as_str().and_then(parse_rational);
+    let real_frame_rate = video_stream["r_frame_rate"].as_str().and_then(parse_rational);
+    let is_vfr = is_variable_frame_rate(avg_frame_rate, real_frame_rate);
 
-    // Add all temp files as inputs
-    for temp_file in &temp_files {
-        concat_cmd.arg("-i").arg(temp_file);
+    if is_vfr {
+        log::warn!("Variable frame rate detected: avg={:?} fps, real={:?} fps", avg_frame_rate, real_frame_rate);
     }
 
-    // Build filter_complex for concatenation with scale and pad
-    let num_clips = temp_files.len();
-    let mut filter_str = String::new();
+    Ok(MediaProbe {
+        duration,
+        has_video,
+        width,
+        height,
+        display_width,
+        display_height,
+        container: probe_string(&json["format"]["format_name"]),
+        video_codec: probe_string(&video_stream["codec_name"]),
+        pixel_format: probe_string(&video_stream["pix_fmt"]),
+        avg_frame_rate,
+        real_frame_rate,
+        is_vfr,
+        bit_rate: probe_number(&json["format"]["bit_rate"]).map(|b| b as u64),
+        rotation,
+        mirrored,
+        color_space: probe_string(&video_stream["color_space"]),
+        audio_streams,
+        subtitle_streams,
+        chapters,
+    })
+}
 
-    // Scale and pad each input to the target resolution
-    for i in 0..num_clips {
-        filter_str.push_str(&format!(
-            "[{}:v]scale=w={}:h={}:force_original_aspect_ratio=decrease,pad={}:{}:(ow-iw)/2:(oh-ih)/2:black[v{}];",
-            i, max_width, max_height, max_width, max_height, i
-        ));
-    }
+// Helper function to probe video metadata using ffprobe
+fn probe_video_metadata(path: &str) -> Result<MediaProbe, String> {
+    log::info!("Running ffprobe on: {}", path);
 
-    // Concatenate the normalized videos with their audio
-    for i in 0..num_clips {
-        filter_str.push_str(&format!("[v{}][{}:a]", i, i));
+    let ffprobe = find_ffprobe();
+    let output = Command::new(&ffprobe)
+        .args(&[
+            "-v", "quiet",
+            "-print_format", "json",
+            "-show_format",
+            "-show_streams",
+            "-show_chapters",
+            path
+        ])
+        .output()
+        .map_err(|e| {
+            log::error!("Failed to execute ffprobe: {}", e);
+            format!("Failed to run ffprobe. Make sure FFmpeg is installed. Error: {}", e)
+        })?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        log::error!("ffprobe command failed. stderr: {}", stderr);
+        return Err(format!("ffprobe failed to read video metadata: {}", stderr));
     }
-    filter_str.push_str(&format!("concat=n={}:v=1:a=1[outv][outa]", num_clips));
 
-    concat_cmd
-        .arg("-filter_complex").arg(&filter_str)
-        .arg("-map").arg("[outv]")
-        .arg("-map").arg("[outa]")
-        // Re-encode to fix timestamp discontinuities between clips
-        .arg("-c:v").arg("libx264")
-        .arg("-preset").arg("medium")  // Better quality for final output
-        .arg("-crf").arg("18")  // High quality
-        .arg("-c:a").arg("aac")
-        .arg("-b:a").arg("192k")
-        .arg("-y")
-        .arg(&options.output_path);
+    let json_str = String::from_utf8_lossy(&output.stdout);
+    log::debug!("ffprobe JSON output: {}", json_str);
 
-    log::info!("Running concat command: {:?}", concat_cmd);
+    let json: serde_json::Value = serde_json::from_str(&json_str)
+        .map_err(|e| {
+            log::error!("Failed to parse ffprobe JSON: {}", e);
+            format!("Failed to parse ffprobe output: {}", e)
+        })?;
 
-    let concat_output = concat_cmd.output().map_err(|e| {
-        format!("Failed to execute FFmpeg concat: {}", e)
-    })?;
+    parse_media_probe(&json)
+}
 
-    // Clean up temp files
-    for temp_file in &temp_files {
-        let _ = std::fs::remove_file(temp_file);
-    }
+// Helper function to pick a constant frame rate for normalizing a VFR source
+// Snaps the average rate to a common broadcast rate when close, otherwise rounds it
+fn target_cfr_rate(avg_frame_rate: f64) -> String {
+    let standard_rates = [
+        ("24000/1001", 23.976), ("24", 24.0), ("25", 25.0), ("30000/1001", 29.97),
+        ("30", 30.0), ("50", 50.0), ("60000/1001", 59.94), ("60", 60.0),
+    ];
 
-    if !concat_output.status.success() {
-        let stderr = String::from_utf8_lossy(&concat_output.stderr);
-        log::error!("FFmpeg concat failed: {}", stderr);
-        return Err(format!("FFmpeg concat failed: {}", stderr));
+    for (rate_str, rate) in standard_rates.iter() {
+        if ((avg_frame_rate - rate).abs() / rate) < 0.01 {
+            return rate_str.to_string();
+        }
     }
 
-    log::info!("Multi-clip export successful: {}", options.output_path);
-    Ok(options.output_path)
+    (avg_frame_rate.round() as u32).max(1).to_string()
 }
 
-// Recording state management
-struct RecordingState {
-    process: Mutex<Option<Child>>,
+// CFR normalizations in progress by source path, for cancel_normalize_frame_rate
+// Reuses ExportJob for its cancellation flag, FFmpeg child and temp file tracking
+struct NormalizeState {
+    jobs: Mutex<HashMap<String, Arc<ExportJob>>>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct AudioDevice {
-    id: String,
-    name: String,
-    #[serde(rename = "type")]
-    device_type: String, // "input", "output", "virtual"
+#[derive(Clone, serde::Serialize)]
+struct NormalizeProgress {
+    path: String,
+    percent: f64,
+    status: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct AudioSettings {
-    microphone_enabled: bool,
-    microphone_device: String,
-    system_audio_enabled: bool,
-    system_audio_device: String,
-    audio_quality: String, // "voice", "standard", "high"
+// CFR working copies live in the media cache entry of their source, so a replaced source gets a
+// new working copy and old ones are evicted with the rest of the cache
+const CFR_WORKING_COPY_NAME: &str = "cfr.mp4";
+
+// Helper function to find the file an export should read for a source: the CFR working copy
+// normalize_frame_rate made for it, so trims land on the frames the user saw, or else the source
+fn export_source_path(path: &str, cache: &MediaCache) -> String {
+    match media_file_key(path).ok().and_then(|key| cache.cached_file(&key, CFR_WORKING_COPY_NAME)) {
+        Some(working_path) => {
+            log::info!("Exporting {} from its CFR working copy {:?}", path, working_path);
+            working_path.to_string_lossy().to_string()
+        }
+        None => path.to_string(),
+    }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct StartRecordingOptions {
-    mode: String, // "screen", "webcam", or "combo"
-    output_path: String,
-    audio_settings: Option<AudioSettings>,
+// Helper function to translate a source audio stream index to its index in the CFR working copy
+// The copy holds the first video stream followed by every audio stream of the source, in order
+fn cfr_copy_stream_index(source_audio: &[u32], index: u32) -> Option<u32> {
+    source_audio.iter().position(|&i| i == index).map(|n| n as u32 + 1)
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct TestSettings {
-    use_wallclock_as_timestamps: bool,
-    audio_filter: String,
-    rtbufsize: String,
-    thread_queue_size: i32,
+// Helper function to point an export clip at its source's CFR working copy, if there is one,
+// keeping any audio stream selection pointed at the same streams
+fn use_cfr_working_copy(clip: &mut ClipSegment, cache: &MediaCache) {
+    let working_path = export_source_path(&clip.input_path, cache);
+    if working_path == clip.input_path {
+        return;
+    }
+
+    let selects_streams = clip.audio_stream_index.is_some()
+        || clip.audio_tracks.as_ref().is_some_and(|tracks| !tracks.is_empty());
+    if selects_streams {
+        let source_audio: Vec<u32> = match probe_video_metadata(&clip.input_path) {
+            Ok(probe) => probe.audio_streams.iter().map(|s| s.index).collect(),
+            Err(e) => {
+                log::warn!("Exporting {} from the source, its audio streams could not be read: {}", clip.input_path, e);
+                return;
+            }
+        };
+        // Unknown indexes are left alone so the render reports them against the available streams
+        let translate = |index: u32| cfr_copy_stream_index(&source_audio, index).unwrap_or(index);
+        clip.audio_stream_index = clip.audio_stream_index.map(translate);
+        for track in clip.audio_tracks.iter_mut().flatten() {
+            track.stream_index = translate(track.stream_index);
+        }
+    }
+
+    clip.input_path = working_path;
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct StartTestRecordingOptions {
-    mode: String,
-    output_path: String,
-    audio_settings: Option<AudioSettings>,
-    test_settings: TestSettings,
+// Helper function to create a constant-frame-rate working copy of a VFR source
+// Encodes to a media cache scratch file, which the caller commits once the encode succeeds
+fn create_cfr_working_copy<F: FnMut(f64)>(video_path: &str, probe: &MediaProbe, temp_path: &std::path::Path, job: &ExportJob, on_progress: F) -> Result<(), String> {
+    let avg_frame_rate = probe.avg_frame_rate
+        .or(probe.real_frame_rate)
+        .ok_or_else(|| "Cannot normalize: source frame rate is unknown".to_string())?;
+    let frame_rate = target_cfr_rate(avg_frame_rate);
+
+    log::info!("Creating CFR working copy of {} at {} fps", video_path, frame_rate);
+
+    let mut cmd = Command::new(find_ffmpeg());
+    cmd.arg("-i").arg(video_path)
+        .arg("-map").arg("0:v:0")
+        .arg("-map").arg("0:a?")
+        // Duplicate/drop frames onto a fixed grid so every frame has a predictable timestamp
+        .arg("-vsync").arg("cfr")
+        .arg("-r").arg(&frame_rate)
+        .arg("-c:v").arg("libx264")
+        .arg("-preset").arg("fast")
+        .arg("-crf").arg("18")  // Near-lossless, this copy replaces the original for editing
+        .arg("-pix_fmt").arg("yuv420p")
+        // Stretch/squeeze audio to match so it stays in sync with the regenerated video timestamps
+        .arg("-af").arg("aresample=async=1:first_pts=0")
+        .arg("-c:a").arg("aac")
+        .arg("-b:a").arg("192k")
+        .arg("-movflags").arg("+faststart")
+        .arg("-y")
+        .arg(temp_path);
+
+    log::info!("Running CFR normalization: {:?}", cmd);
+
+    if let Err(stderr) = run_ffmpeg_for_job(&mut cmd, Some(job), on_progress) {
+        if !job.is_cancelled() {
+            log::error!("FFmpeg CFR normalization failed: {}", stderr);
+        }
+        return Err(stderr);
+    }
+
+    log::info!("CFR working copy encoded: {:?}", temp_path);
+    Ok(())
 }
 
+// Create a CFR working copy of a VFR source so trims land on the frames the user saw
+// Runs in the background with "normalize-progress" events; cancel with cancel_normalize_frame_rate
 #[tauri::command]
-fn list_audio_devices() -> Result<Vec<AudioDevice>, String> {
-    log::info!("Listing audio devices");
+async fn normalize_frame_rate(path: String, app: tauri::AppHandle) -> Result<String, String> {
+    log::info!("normalize_frame_rate called with path: {}", path);
 
-    #[cfg(target_os = "macos")]
-    {
-        // Use FFmpeg to list AVFoundation devices
-        let ffmpeg = find_ffmpeg();
-        let output = Command::new(&ffmpeg)
-            .arg("-f").arg("avfoundation")
-            .arg("-list_devices").arg("true")
-            .arg("-i").arg("")
-            .output()
-            .map_err(|e| format!("Failed to list devices: {}", e))?;
+    if !PathBuf::from(&path).exists() {
+        return Err("File does not exist".to_string());
+    }
 
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        let mut devices = Vec::new();
+    let probe = probe_video_metadata(&path)?;
+    if !probe.has_video || is_still_image(&probe) {
+        return Err("Only video files can be normalized to a constant frame rate".to_string());
+    }
 
-        // Parse FFmpeg output for audio devices
-        // Example: [AVFoundation indev @ ...] [1] Built-in Microphone
-        let mut in_audio_section = false;
-        for line in stderr.lines() {
-            if line.contains("AVFoundation audio devices:") {
-                in_audio_section = true;
-                continue;
-            }
-            if line.contains("AVFoundation video devices:") {
-                in_audio_section = false;
+    let key = media_file_key(&path)?;
+    let cache = app.state::<MediaCache>();
+
+    let working_path = match cache.cached_file(&key, CFR_WORKING_COPY_NAME) {
+        Some(cached) => {
+            log::info!("Reusing existing CFR working copy: {:?}", cached);
+            cached
+        }
+        None => {
+            let job = Arc::new(ExportJob::new());
+            {
+                let state = app.state::<NormalizeState>();
+                let mut jobs = state.jobs.lock().unwrap();
+                if jobs.contains_key(&path) {
+                    return Err("This file is already being normalized".to_string());
+                }
+                jobs.insert(path.clone(), job.clone());
             }
 
-            if in_audio_section {
-                // Look for lines with device format: [N] Device Name
-                // Skip lines that contain "AVFoundation" or "error"
-                if line.contains("AVFoundation") || line.to_lowercase().contains("error") {
-                    continue;
+            let temp_path = match cache.temp_file(&key, CFR_WORKING_COPY_NAME) {
+                Ok(temp_path) => temp_path,
+                Err(e) => {
+                    app.state::<NormalizeState>().jobs.lock().unwrap().remove(&path);
+                    return Err(e);
                 }
+            };
+            job.track_temp_file(temp_path.clone());
+
+            let duration = probe.duration;
+            let source = path.clone();
+            let output = temp_path.clone();
+            let worker_job = job.clone();
+            let progress_app = app.clone();
+
+            let result = tauri::async_runtime::spawn_blocking(move || {
+                create_cfr_working_copy(&source, &probe, &output, &worker_job, |seconds| {
+                    let percent = if duration > 0.0 { (seconds / duration * 100.0).clamp(0.0, 100.0) } else { 0.0 };
+                    let _ = progress_app.emit("normalize-progress", NormalizeProgress {
+                        path: source.clone(),
+                        percent,
+                        status: "Normalizing frame rate...".to_string(),
+                    });
+                })
+            })
+            .await
+            .map_err(|e| format!("Frame rate normalization task failed: {}", e))
+            .and_then(|result| result);
 
-                // Find the last '[' and ']' pair (device ID)
-                if let Some(bracket_start) = line.rfind('[') {
-                    if let Some(bracket_end) = line.rfind(']') {
-                        if bracket_end > bracket_start + 1 && bracket_start > 5 {
-                            let id = &line[bracket_start + 1..bracket_end];
-                            let name = line[bracket_end + 1..].trim();
+            app.state::<NormalizeState>().jobs.lock().unwrap().remove(&path);
 
-                            // Validate: ID should be a number, name should not be empty
-                            if id.parse::<u32>().is_ok() && !name.is_empty() {
-                                // Detect virtual audio devices
-                                let device_type = if name.to_lowercase().contains("blackhole")
-                                    || name.to_lowercase().contains("soundflower")
-                                    || name.to_lowercase().contains("loopback") {
-                                    "virtual"
-                                } else {
-                                    "input"
-                                };
+            // Publish the working copy before cleanup, which removes the scratch file if it is still there
+            let committed = result.and_then(|()| cache.commit_file(&key, &temp_path, CFR_WORKING_COPY_NAME));
+            job.remove_temp_files();
 
-                                devices.push(AudioDevice {
-                                    id: id.to_string(),
-                                    name: name.to_string(),
-                                    device_type: device_type.to_string(),
-                                });
-                            }
-                        }
-                    }
+            match committed {
+                Err(_) if job.is_cancelled() => {
+                    log::info!("Frame rate normalization cancelled for {}", path);
+                    return Err("Frame rate normalization cancelled".to_string());
+                }
+                Err(e) => return Err(format!("Failed to normalize frame rate: {}", e)),
+                Ok(working_path) => {
+                    cache.evict();
+                    working_path
                 }
             }
         }
+    };
+    let working_path_str = working_path.to_string_lossy().to_string();
 
-        // Always include a default device if none found
-        if devices.is_empty() {
-            devices.push(AudioDevice {
-                id: "0".to_string(),
-                name: "Default Microphone".to_string(),
-                device_type: "input".to_string(),
-            });
-        }
-
-        log::info!("Found {} audio devices", devices.len());
-        Ok(devices)
-    }
+    let _ = app.emit("normalize-progress", NormalizeProgress {
+        path: path.clone(),
+        percent: 100.0,
+        status: "Working copy ready".to_string(),
+    });
 
-    #[cfg(not(target_os = "macos"))]
-    {
-        // Fallback for other platforms
-        Ok(vec![
-            AudioDevice {
-                id: "0".to_string(),
-                name: "Default Microphone".to_string(),
-                device_type: "input".to_string(),
-            }
-        ])
-    }
+    Ok(working_path_str)
 }
 
+// Stop a running normalize_frame_rate; its partial working copy is removed
 #[tauri::command]
-fn start_recording(options: StartRecordingOptions, state: State<RecordingState>) -> Result<String, String> {
-    log::info!("Starting recording: {:?}", options);
+fn cancel_normalize_frame_rate(path: String, state: State<NormalizeState>) -> Result<(), String> {
+    log::info!("cancel_normalize_frame_rate called with path: {}", path);
+
+    let job = state.jobs.lock().unwrap()
+        .get(&path)
+        .cloned()
+        .ok_or_else(|| format!("No frame rate normalization running for {}", path))?;
+    job.cancel();
+    Ok(())
+}
 
-    let mut recording_process = state.process.lock().unwrap();
-    if recording_process.is_some() {
-        return Err("Recording already in progress".to_string());
-    }
+// Helper function to generate thumbnail for a video
+// Thumbnails are stored in the media cache entry for the file's content key
+fn generate_thumbnail(video_path: &str, duration: f64, cache: &MediaCache, cache_key: Option<&str>) -> Result<String, String> {
+    log::info!("Generating thumbnail for: {}", video_path);
 
-    // Build FFmpeg command based on platform and mode
-    let ffmpeg = find_ffmpeg();
-    let mut cmd = Command::new(&ffmpeg);
+    let key = match cache_key {
+        Some(key) => key.to_string(),
+        None => media_file_key(video_path)?,
+    };
 
-    // Add global sync and buffer parameters for all platforms
-    // These ensure audio/video stay synchronized during capture
-    cmd.arg("-loglevel").arg("verbose")  // Verbose logging for diagnostics
-        .arg("-rtbufsize").arg("200M");  // Larger realtime buffer to prevent audio drops
+    if let Some(cached) = cache.cached_file(&key, "thumbnail.jpg") {
+        log::info!("Using cached thumbnail: {:?}", cached);
+        return Ok(cached.to_string_lossy().to_string());
+    }
 
-    #[cfg(target_os = "macos")]
-    {
-        // Get audio settings or use defaults
-        let audio_settings = options.audio_settings.as_ref();
-        let mic_enabled = audio_settings.map(|s| s.microphone_enabled).unwrap_or(true);
-        let mic_device = audio_settings
-            .and_then(|s| {
-                if s.microphone_device == "default" {
-                    Some("0")
-                } else {
-                    Some(s.microphone_device.as_str())
-                }
-            })
-            .unwrap_or("0");
+    let thumbnail_path = cache.temp_file(&key, "thumbnail.jpg")?;
 
-        // Only enable system audio if enabled AND a valid device is selected
-        let sys_audio_enabled = audio_settings
-            .map(|s| {
-                s.system_audio_enabled
-                && !s.system_audio_device.is_empty()
-                && s.system_audio_device != "none"
-            })
-            .unwrap_or(false);
+    // Extract frame at 2 seconds or 10% of duration (whichever is smaller)
+    let timestamp = (duration * 0.1).min(2.0);
 
-        match options.mode.as_str() {
-            "screen" => {
-                cmd.arg("-f").arg("avfoundation")
-                    .arg("-capture_cursor").arg("1")
-                    .arg("-framerate").arg("15")
-                    // Use wallclock timestamps for consistent timing across inputs
-                    .arg("-use_wallclock_as_timestamps").arg("1");
+    log::info!("Extracting frame at {}s to: {:?}", timestamp, thumbnail_path);
 
-                // Handle dual audio (mic + system) vs single audio source
-                if mic_enabled && sys_audio_enabled {
-                    // Dual audio: Capture screen video + mic + system audio separately, then mix
-                    let sys_audio_device = audio_settings
-                        .and_then(|s| {
-                            if s.system_audio_device == "none" || s.system_audio_device.is_empty() {
-                                None
-                            } else {
-                                Some(s.system_audio_device.as_str())
-                            }
-                        })
-                        .unwrap_or("1"); // Default to device 1 for system audio
+    // Use FFmpeg to extract a single frame
+    let ffmpeg = find_ffmpeg();
+    let output = Command::new(&ffmpeg)
+        .args(&[
+            "-ss", &timestamp.to_string(),  // Seek to timestamp
+            "-i", video_path,                // Input video
+            "-vframes", "1",                 // Extract 1 frame
+            "-vf", "scale=320:-1",          // Scale to width 320, maintain aspect ratio
+            "-q:v", "2",                     // High quality JPEG
+            "-y",                            // Overwrite if exists
+            thumbnail_path.to_str().unwrap()
+        ])
+        .output()
+        .map_err(|e| {
+            log::error!("Failed to execute ffmpeg for thumbnail: {}", e);
+            format!("Failed to generate thumbnail: {}", e)
+        })?;
 
-                    // Screen video with no audio first
-                    cmd.arg("-i").arg("1:none");
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        log::error!("FFmpeg thumbnail generation failed: {}", stderr);
+        let _ = std::fs::remove_file(&thumbnail_path);
+        return Err(format!("Failed to generate thumbnail: {}", stderr));
+    }
 
-                    // Microphone audio input
-                    cmd.arg("-probesize").arg("100M")  // Large probe buffer for stable detection
-                        .arg("-rtbufsize").arg("200M")  // Large realtime buffer to prevent drops
-                        .arg("-f").arg("avfoundation")
-                        // Use device timestamps for audio (not wallclock - prevents choppiness)
-                        .arg("-thread_queue_size").arg("8192")  // Very large queue for smooth audio
-                        .arg("-i").arg(&format!(":{}", mic_device));
+    let thumbnail_path = cache.commit_file(&key, &thumbnail_path, "thumbnail.jpg")?;
+    let thumbnail_path_str = thumbnail_path.to_string_lossy().to_string();
+    log::info!("Thumbnail generated successfully: {}", thumbnail_path_str);
 
-                    // System audio input (BlackHole or similar)
-                    cmd.arg("-probesize").arg("100M")  // Large probe buffer for stable detection
-                        .arg("-rtbufsize").arg("200M")  // Large realtime buffer to prevent drops
-                        .arg("-f").arg("avfoundation")
-                        // Use device timestamps for audio (not wallclock - prevents choppiness)
-                        .arg("-thread_queue_size").arg("8192")  // Very large queue for smooth audio
-                        .arg("-i").arg(&format!(":{}", sys_audio_device));
-                } else if mic_enabled {
-                    // Just microphone
-                    cmd.arg("-i").arg(&format!("1:{}", mic_device));
-                } else if sys_audio_enabled {
-                    // Just system audio
-                    let sys_audio_device = audio_settings
-                        .and_then(|s| {
-                            if s.system_audio_device == "none" || s.system_audio_device.is_empty() {
-                                None
-                            } else {
-                                Some(s.system_audio_device.as_str())
-                            }
-                        })
-                        .unwrap_or("1");
-                    cmd.arg("-i").arg(&format!("1:{}", sys_audio_device));
-                } else {
-                    // No audio
-                    cmd.arg("-i").arg("1:none");
-                }
-            },
-            "webcam" => {
-                // For webcam, capture video device 0 with audio
-                if mic_enabled {
-                    // Capture webcam with microphone audio
-                    cmd.arg("-f").arg("avfoundation")
-                        .arg("-framerate").arg("30")
-                        // Use wallclock timestamps for consistent timing
-                        .arg("-use_wallclock_as_timestamps").arg("1")
-                        .arg("-i").arg(&format!("0:{}", mic_device));
+    cache.evict();
 
-                    // Explicitly map video and audio streams
-                    cmd.arg("-map").arg("0:v")  // Video from webcam
-                        .arg("-map").arg("0:a"); // Audio from microphone
-                } else {
-                    // Capture webcam without audio
-                    cmd.arg("-f").arg("avfoundation")
-                        .arg("-framerate").arg("30")
-                        // Use wallclock timestamps for consistent timing
-                        .arg("-use_wallclock_as_timestamps").arg("1")
-                        .arg("-i").arg("0:none");
-                }
-            },
-            "combo" => {
-                // For combo mode: capture screen + webcam, then overlay webcam on screen
+    Ok(thumbnail_path_str)
+}
 
-                // Input 0: Screen video (no audio attached to avoid conflicts)
-                // Increased thread_queue_size for better buffering and sync (was 512)
-                cmd.arg("-thread_queue_size").arg("2048")
-                    .arg("-f").arg("avfoundation")
-                    .arg("-capture_cursor").arg("1")
-                    .arg("-framerate").arg("30")
-                    // Use wallclock timestamps for consistent timing across all inputs
-                    .arg("-use_wallclock_as_timestamps").arg("1")
-                    .arg("-i").arg("1:none");
+// Maximum number of frames per sprite row
+const FILMSTRIP_MAX_COLUMNS: u32 = 10;
 
-                // Input 1: Webcam video (no audio, we'll handle audio separately)
-                // Increased thread_queue_size for smooth capture
-                cmd.arg("-thread_queue_size").arg("2048")
-                    .arg("-f").arg("avfoundation")
-                    .arg("-framerate").arg("30")
-                    // Use wallclock timestamps for consistent timing
-                    .arg("-use_wallclock_as_timestamps").arg("1")
-                    .arg("-i").arg("0:none");
+// JPEG can't encode images wider or taller than this; rows grow with the frame count, so
+// filmstrips that would exceed it are rejected rather than failing inside FFmpeg
+const FILMSTRIP_MAX_SPRITE_SIZE: u32 = 65535;
 
-                // Handle audio inputs separately (similar to screen mode dual audio)
-                if mic_enabled && sys_audio_enabled {
-                    let sys_audio_device = audio_settings
-                        .and_then(|s| {
-                            if s.system_audio_device == "none" || s.system_audio_device.is_empty() {
-                                None
-                            } else {
-                                Some(s.system_audio_device.as_str())
-                            }
-                        })
-                        .unwrap_or("1");
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FilmstripFrame {
+    time: f64,  // Source timestamp the frame represents, in seconds
+    x: u32,     // Offset of the frame within the sprite, in pixels
+    y: u32,
+}
 
-                    // Input 2: Microphone audio
-                    cmd.arg("-probesize").arg("100M")  // Large probe buffer for stable detection
-                        .arg("-rtbufsize").arg("200M")  // Large realtime buffer to prevent drops
-                        .arg("-thread_queue_size").arg("8192")
-                        .arg("-f").arg("avfoundation")
-                        // Use device timestamps for audio (not wallclock - prevents choppiness)
-                        .arg("-i").arg(&format!(":{}", mic_device));
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Filmstrip {
+    sprite_path: String,
+    frame_width: u32,
+    frame_height: u32,
+    columns: u32,
+    rows: u32,
+    frames: Vec<FilmstripFrame>,
+}
 
-                    // Input 3: System audio
-                    cmd.arg("-probesize").arg("100M")  // Large probe buffer for stable detection
-                        .arg("-rtbufsize").arg("200M")  // Large realtime buffer to prevent drops
-                        .arg("-thread_queue_size").arg("8192")
-                        .arg("-f").arg("avfoundation")
-                        // Use device timestamps for audio (not wallclock - prevents choppiness)
-                        .arg("-i").arg(&format!(":{}", sys_audio_device));
-                } else if mic_enabled {
-                    // Input 2: Just microphone
-                    cmd.arg("-probesize").arg("100M")  // Large probe buffer for stable detection
-                        .arg("-rtbufsize").arg("200M")  // Large realtime buffer to prevent drops
-                        .arg("-thread_queue_size").arg("8192")
-                        .arg("-f").arg("avfoundation")
-                        // Use device timestamps for audio (not wallclock - prevents choppiness)
-                        .arg("-i").arg(&format!(":{}", mic_device));
-                } else if sys_audio_enabled {
-                    // Input 2: Just system audio
-                    let sys_audio_device = audio_settings
-                        .and_then(|s| {
-                            if s.system_audio_device == "none" || s.system_audio_device.is_empty() {
-                                None
-                            } else {
-                                Some(s.system_audio_device.as_str())
-                            }
-                        })
-                        .unwrap_or("1");
-                    cmd.arg("-probesize").arg("100M")  // Large probe buffer for stable detection
-                        .arg("-rtbufsize").arg("200M")  // Large realtime buffer to prevent drops
-                        .arg("-thread_queue_size").arg("8192")
-                        .arg("-f").arg("avfoundation")
-                        // Use device timestamps for audio (not wallclock - prevents choppiness)
-                        .arg("-i").arg(&format!(":{}", sys_audio_device));
-                }
-            },
-            _ => return Err("Invalid recording mode".to_string()),
+// Helper function to compute where each evenly spaced frame sits in a filmstrip sprite
+fn filmstrip_layout(start: f64, end: f64, count: u32, frame_width: u32, frame_height: u32) -> Result<(u32, u32, Vec<FilmstripFrame>), String> {
+    let columns = count.clamp(1, FILMSTRIP_MAX_COLUMNS);
+    let rows = count.div_ceil(columns).max(1);
+
+    let sprite_width = columns as u64 * frame_width as u64;
+    let sprite_height = rows as u64 * frame_height as u64;
+    if sprite_width > FILMSTRIP_MAX_SPRITE_SIZE as u64 || sprite_height > FILMSTRIP_MAX_SPRITE_SIZE as u64 {
+        let max_rows = (FILMSTRIP_MAX_SPRITE_SIZE / frame_height.max(1)).max(1);
+        return Err(format!(
+            "Filmstrip of {} frames at {}x{} would be {}x{}px, over the {}px image limit - request at most {} frames at this height",
+            count, frame_width, frame_height, sprite_width, sprite_height, FILMSTRIP_MAX_SPRITE_SIZE, max_rows * columns
+        ));
+    }
+    let step = (end - start) / count.max(1) as f64;
+
+    let frames = (0..count)
+        .map(|i| FilmstripFrame {
+            time: round_to_millis(start + i as f64 * step),
+            x: (i % columns) * frame_width,
+            y: (i / columns) * frame_height,
+        })
+        .collect();
+
+    Ok((columns, rows, frames))
+}
+
+// Generate evenly spaced frames between start and end as a single sprite image
+// Uses one FFmpeg pass (fps + tile filters) instead of seeking once per frame
+// Long ranges with many frames take a while to decode, so the work runs off the main thread
+#[tauri::command]
+async fn generate_filmstrip(path: String, start: f64, end: f64, count: u32, height: u32, app: tauri::AppHandle) -> Result<Filmstrip, String> {
+    log::info!("Generating filmstrip for: {} ({}s-{}s, {} frames at {}px)", path, start, end, count, height);
+
+    if count == 0 || height == 0 {
+        return Err("Filmstrip needs at least one frame and a non-zero height".to_string());
+    }
+    if end <= start {
+        return Err("Filmstrip end must be after start".to_string());
+    }
+
+    tauri::async_runtime::spawn_blocking(move || {
+        render_filmstrip(&path, start, end, count, height, &app.state::<MediaCache>())
+    })
+    .await
+    .map_err(|e| format!("Filmstrip task failed: {}", e))?
+}
+
+// Helper function to render (or find in the media cache) the filmstrip sprite for a range
+fn render_filmstrip(path: &str, start: f64, end: f64, count: u32, height: u32, cache: &MediaCache) -> Result<Filmstrip, String> {
+    let probe = probe_video_metadata(path)?;
+    if !probe.has_video {
+        return Err("Audio-only media has no frames for a filmstrip".to_string());
+    }
+
+    // Frame size follows the displayed aspect ratio; libx264-style even sizes keep scale happy
+    let (display_width, display_height) = probe.display_width
+        .zip(probe.display_height)
+        .unwrap_or((1920, 1080));
+    let frame_height = height + height % 2;
+    let frame_width = ((frame_height as f64 * display_width as f64 / display_height.max(1) as f64).round() as u32).max(2);
+    let frame_width = frame_width + frame_width % 2;
+
+    let start = round_to_millis(start.max(0.0));
+    let end = round_to_millis(end);
+    let (columns, rows, frames) = filmstrip_layout(start, end, count, frame_width, frame_height)?;
+
+    let key = media_file_key(path)?;
+    let sprite_name = format!(
+        "filmstrip_{}_{}_{}_{}.jpg",
+        (start * 1000.0) as u64, (end * 1000.0) as u64, count, frame_height
+    );
+
+    let sprite_path = match cache.cached_file(&key, &sprite_name) {
+        Some(cached) => {
+            log::info!("Using cached filmstrip: {:?}", cached);
+            cached
+        }
+        None => {
+            let sprite_path = cache.temp_file(&key, &sprite_name)?;
+            let duration = end - start;
+
+            // fps picks `count` frames spread across the range, tile packs them into one image
+            let filter = format!(
+                "fps=fps={:.6},scale={}:{},tile={}x{}",
+                count as f64 / duration, frame_width, frame_height, columns, rows
+            );
+
+            let ffmpeg = find_ffmpeg();
+            let output = Command::new(&ffmpeg)
+                .arg("-ss").arg(start.to_string())
+                .arg("-t").arg(duration.to_string())
+                .arg("-i").arg(path)
+                .arg("-vf").arg(&filter)
+                .arg("-frames:v").arg("1")
+                .arg("-q:v").arg("3")
+                .arg("-y")
+                .arg(&sprite_path)
+                .output()
+                .map_err(|e| format!("Failed to generate filmstrip: {}", e))?;
+
+            if !output.status.success() {
+                let stderr = String::from_utf8_lossy(&output.stderr);
+                log::error!("FFmpeg filmstrip generation failed: {}", stderr);
+                let _ = std::fs::remove_file(&sprite_path);
+                return Err(format!("Failed to generate filmstrip: {}", stderr));
+            }
+
+            let sprite_path = cache.commit_file(&key, &sprite_path, &sprite_name)?;
+            cache.evict();
+            sprite_path
         }
+    };
+
+    Ok(Filmstrip {
+        sprite_path: sprite_path.to_string_lossy().to_string(),
+        frame_width,
+        frame_height,
+        columns,
+        rows,
+        frames,
+    })
+}
+
+// ===== WAVEFORM PEAKS =====
+
+// Waveform peaks are decoded once per media file at this rate (enough for visualization)
+const PEAKS_SAMPLE_RATE: u32 = 8000;
+
+// Zoom levels: the finest bucket is 128 samples (16 ms), each coarser level is 4x wider
+const PEAKS_BASE_BUCKET_SAMPLES: u32 = 128;
+const PEAKS_LEVEL_FACTOR: u32 = 4;
+const PEAKS_LEVEL_COUNT: usize = 5;
+
+// Bump when the on-disk peaks layout changes so stale files get rebuilt
+const PEAKS_FORMAT_VERSION: u32 = 1;
+
+// Each bucket is stored as min (i16), max (i16), rms (u16), little-endian
+const PEAKS_BUCKET_BYTES: usize = 6;
+
+// Partial results use the 64 ms level and are sent every 30 seconds of decoded audio
+const PEAKS_PARTIAL_LEVEL: usize = 1;
+const PEAKS_PARTIAL_INTERVAL_SECS: u64 = 30;
+
+#[derive(Debug, Clone, Copy, Default)]
+struct PeakBucket {
+    min: i16,
+    max: i16,
+    rms: u16,
+}
+
+// Running min/max/sum-of-squares for the bucket currently being filled
+#[derive(Debug, Clone, Copy)]
+struct PeakAccumulator {
+    min: i16,
+    max: i16,
+    sum_squares: f64,
+    count: u32,
+}
+
+impl PeakAccumulator {
+    fn new() -> Self {
+        PeakAccumulator { min: i16::MAX, max: i16::MIN, sum_squares: 0.0, count: 0 }
     }
 
-    #[cfg(target_os = "windows")]
-    {
-        match options.mode.as_str() {
-            "screen" => {
-                cmd.arg("-f").arg("gdigrab")
-                    .arg("-i").arg("desktop")
-                    .arg("-r").arg("30");
-            },
-            "webcam" => {
-                cmd.arg("-f").arg("dshow")
-                    .arg("-i").arg("video=") // User needs to specify device
-                    .arg("-r").arg("30");
-            },
-            _ => return Err("Invalid recording mode".to_string()),
+    fn push(&mut self, sample: i16) {
+        self.min = self.min.min(sample);
+        self.max = self.max.max(sample);
+        self.sum_squares += (sample as f64).powi(2);
+        self.count += 1;
+    }
+
+    fn finish(&mut self) -> PeakBucket {
+        let bucket = PeakBucket {
+            min: self.min,
+            max: self.max,
+            rms: (self.sum_squares / self.count as f64).sqrt().round().min(u16::MAX as f64) as u16,
+        };
+        *self = PeakAccumulator::new();
+        bucket
+    }
+}
+
+impl PeakBucket {
+    fn to_bytes(self) -> [u8; PEAKS_BUCKET_BYTES] {
+        let [min_lo, min_hi] = self.min.to_le_bytes();
+        let [max_lo, max_hi] = self.max.to_le_bytes();
+        let [rms_lo, rms_hi] = self.rms.to_le_bytes();
+        [min_lo, min_hi, max_lo, max_hi, rms_lo, rms_hi]
+    }
+
+    fn from_bytes(b: &[u8]) -> Self {
+        PeakBucket {
+            min: i16::from_le_bytes([b[0], b[1]]),
+            max: i16::from_le_bytes([b[2], b[3]]),
+            rms: u16::from_le_bytes([b[4], b[5]]),
+        }
+    }
+
+    fn to_peak(self) -> WaveformPeak {
+        WaveformPeak {
+            min: self.min as f32 / 32768.0,
+            max: self.max as f32 / 32767.0,
+            rms: (self.rms as f32 / 32767.0).min(1.0),
+        }
+    }
+}
+
+// One zoom level being built; completed buckets go straight to a scratch file
+struct PeakLevelWriter {
+    samples_per_bucket: u32,
+    accumulator: PeakAccumulator,
+    bucket_count: u64,
+    path: PathBuf,
+    writer: std::io::BufWriter<File>,
+}
+
+impl PeakLevelWriter {
+    fn write_bucket(&mut self) -> Result<PeakBucket, String> {
+        let bucket = self.accumulator.finish();
+        self.bucket_count += 1;
+        self.writer.write_all(&bucket.to_bytes())
+            .map_err(|e| format!("Failed to write waveform peaks: {}", e))?;
+        Ok(bucket)
+    }
+}
+
+// Builds every zoom level in a single pass over the decoded samples.
+// Memory use is constant: buckets are written to scratch files as they complete,
+// and only the not-yet-reported buckets of the partial-results level are kept.
+struct PeaksBuilder {
+    levels: Vec<PeakLevelWriter>,
+    total_samples: u64,
+    partial: Vec<PeakBucket>,
+    partial_start_bucket: u64,
+}
+
+impl PeaksBuilder {
+    fn new(bin_path: &std::path::Path) -> Result<Self, String> {
+        let mut levels = Vec::new();
+        for level in 0..PEAKS_LEVEL_COUNT {
+            let path = bin_path.with_extension(format!("L{}.part", level));
+            let file = File::create(&path)
+                .map_err(|e| format!("Failed to create waveform peaks file: {}", e))?;
+            levels.push(PeakLevelWriter {
+                samples_per_bucket: PEAKS_BASE_BUCKET_SAMPLES * PEAKS_LEVEL_FACTOR.pow(level as u32),
+                accumulator: PeakAccumulator::new(),
+                bucket_count: 0,
+                path,
+                writer: std::io::BufWriter::new(file),
+            });
         }
+        Ok(PeaksBuilder { levels, total_samples: 0, partial: Vec::new(), partial_start_bucket: 0 })
     }
 
-    #[cfg(target_os = "linux")]
-    {
-        match options.mode.as_str() {
-            "screen" => {
-                cmd.arg("-f").arg("x11grab")
-                    .arg("-i").arg(":0.0")
-                    .arg("-r").arg("30");
-            },
-            "webcam" => {
-                cmd.arg("-f").arg("v4l2")
-                    .arg("-i").arg("/dev/video0")
-                    .arg("-r").arg("30");
-            },
-            _ => return Err("Invalid recording mode".to_string()),
+    fn push(&mut self, sample: i16) -> Result<(), String> {
+        self.total_samples += 1;
+        for (level, writer) in self.levels.iter_mut().enumerate() {
+            writer.accumulator.push(sample);
+            if writer.accumulator.count == writer.samples_per_bucket {
+                let bucket = writer.write_bucket()?;
+                if level == PEAKS_PARTIAL_LEVEL {
+                    self.partial.push(bucket);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // Take the buckets completed since the last call, with the time the first one starts at
+    fn take_partial(&mut self) -> (f64, Vec<PeakBucket>) {
+        let samples_per_bucket = self.levels[PEAKS_PARTIAL_LEVEL].samples_per_bucket as u64;
+        let start = (self.partial_start_bucket * samples_per_bucket) as f64 / PEAKS_SAMPLE_RATE as f64;
+        let buckets = std::mem::take(&mut self.partial);
+        self.partial_start_bucket += buckets.len() as u64;
+        (start, buckets)
+    }
+
+    // Flush partially filled buckets and concatenate every level into the final peaks file
+    fn finish(mut self, bin_path: &std::path::Path) -> Result<PeaksIndex, String> {
+        let mut output = std::io::BufWriter::new(File::create(bin_path)
+            .map_err(|e| format!("Failed to write waveform peaks: {}", e))?);
+        let mut levels = Vec::new();
+        let mut byte_offset = 0;
+
+        for writer in self.levels.iter_mut() {
+            if writer.accumulator.count > 0 {
+                writer.write_bucket()?;
+            }
+            writer.writer.flush()
+                .map_err(|e| format!("Failed to write waveform peaks: {}", e))?;
+
+            let mut part = File::open(&writer.path)
+                .map_err(|e| format!("Failed to read waveform peaks: {}", e))?;
+            std::io::copy(&mut part, &mut output)
+                .map_err(|e| format!("Failed to write waveform peaks: {}", e))?;
+
+            levels.push(PeaksLevel {
+                samples_per_bucket: writer.samples_per_bucket,
+                bucket_count: writer.bucket_count,
+                byte_offset,
+            });
+            byte_offset += writer.bucket_count * PEAKS_BUCKET_BYTES as u64;
+        }
+
+        output.flush().map_err(|e| format!("Failed to write waveform peaks: {}", e))?;
+
+        Ok(PeaksIndex {
+            version: PEAKS_FORMAT_VERSION,
+            sample_rate: PEAKS_SAMPLE_RATE,
+            total_samples: self.total_samples,
+            levels,
+        })
+    }
+}
+
+impl Drop for PeaksBuilder {
+    // Scratch files are removed whether the build finished or failed part-way
+    fn drop(&mut self) {
+        for level in self.levels.drain(..) {
+            let path = level.path.clone();
+            drop(level);
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct PeaksLevel {
+    samples_per_bucket: u32,
+    bucket_count: u64,
+    byte_offset: u64,  // Where this level starts in the .bin file
+}
+
+// Sidecar index for a peaks .bin file; it is written last, so its presence means the .bin is complete
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct PeaksIndex {
+    version: u32,
+    sample_rate: u32,
+    total_samples: u64,
+    levels: Vec<PeaksLevel>,
+}
+
+impl PeaksIndex {
+    fn duration(&self) -> f64 {
+        self.total_samples as f64 / self.sample_rate as f64
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WaveformPeak {
+    min: f32,  // -1.0 to 1.0
+    max: f32,  // -1.0 to 1.0
+    rms: f32,  // 0.0 to 1.0
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WaveformRange {
+    start: f64,
+    end: f64,
+    bucket_duration: f64,  // Seconds covered by each returned peak
+    peaks: Vec<WaveformPeak>,
+}
+
+// Sent while a peaks file is being built so the timeline can draw the waveform progressively
+#[derive(Clone, serde::Serialize)]
+struct WaveformPartial {
+    path: String,
+    audio_stream: Option<u32>,
+    start: f64,            // Time of the first peak in this batch
+    bucket_duration: f64,  // Seconds covered by each peak
+    peaks: Vec<WaveformPeak>,
+    decoded_duration: f64, // How much of the file has been decoded so far
+}
+
+// Generate waveform data for audio visualization
+#[tauri::command]
+async fn generate_waveform(video_path: String, samples: Option<usize>, audio_stream: Option<u32>, app: tauri::AppHandle) -> Result<Vec<f32>, String> {
+    let num_samples = samples.unwrap_or(200);
+    log::info!("Generating waveform for: {} with {} samples (audio stream: {:?})", video_path, num_samples, audio_stream);
+
+    // Served from the persisted peaks file, so every resolution shares one decode
+    let range = tauri::async_runtime::spawn_blocking(move || {
+        let (peaks_path, index) = load_or_build_waveform_peaks(&video_path, audio_stream, &app)?;
+        slice_waveform_peaks(&peaks_path, &index, 0.0, index.duration(), num_samples)
+    })
+    .await
+    .map_err(|e| format!("Waveform task failed: {}", e))??;
+
+    log::info!("Waveform generated with {} data points", range.peaks.len());
+    Ok(range.peaks.iter().map(|peak| peak.rms).collect())
+}
+
+// Get min/max/RMS peaks for part of a file, e.g. the visible part of the timeline
+// The first call for a file builds its peaks file; later calls only read the buckets they need
+#[tauri::command]
+async fn get_waveform_range(
+    path: String,
+    start: f64,
+    end: f64,
+    buckets: usize,
+    audio_stream: Option<u32>,
+    app: tauri::AppHandle,
+) -> Result<WaveformRange, String> {
+    log::info!("get_waveform_range called for: {} ({}s - {}s, {} buckets)", path, start, end, buckets);
+
+    if buckets == 0 {
+        return Err("Bucket count must be greater than zero".to_string());
+    }
+    if end <= start || start < 0.0 {
+        return Err(format!("Invalid range: {}s - {}s", start, end));
+    }
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let (peaks_path, index) = load_or_build_waveform_peaks(&path, audio_stream, &app)?;
+        slice_waveform_peaks(&peaks_path, &index, start, end, buckets)
+    })
+    .await
+    .map_err(|e| format!("Waveform task failed: {}", e))?
+}
+
+// Helper function to find a file's peaks in the media cache, decoding the audio if they are missing
+// While decoding, partial peaks are emitted as "waveform-partial" events
+fn load_or_build_waveform_peaks(path: &str, audio_stream: Option<u32>, app: &tauri::AppHandle) -> Result<(PathBuf, PeaksIndex), String> {
+    let cache = app.state::<MediaCache>();
+    let key = media_file_key(path)?;
+    let base_name = match audio_stream {
+        Some(index) => format!("peaks_stream{}", index),
+        None => "peaks".to_string(),
+    };
+    let index_name = format!("{}.json", base_name);
+    let bin_name = format!("{}.bin", base_name);
+
+    if let Some(index) = cache.read_json::<PeaksIndex>(&key, &index_name) {
+        if index.version == PEAKS_FORMAT_VERSION {
+            if let Some(bin_path) = cache.cached_file(&key, &bin_name) {
+                log::info!("Using cached waveform peaks for: {}", path);
+                return Ok((bin_path, index));
+            }
+        }
+    }
+
+    // Each build writes to its own scratch files, so concurrent builds of the same peaks can't collide
+    let temp_path = cache.temp_file(&key, &bin_name)?;
+    let bucket_duration = (PEAKS_BASE_BUCKET_SAMPLES * PEAKS_LEVEL_FACTOR.pow(PEAKS_PARTIAL_LEVEL as u32)) as f64
+        / PEAKS_SAMPLE_RATE as f64;
+
+    let decoded = decode_waveform_peaks(path, audio_stream, &temp_path, |start, buckets, decoded_duration| {
+        let _ = app.emit("waveform-partial", WaveformPartial {
+            path: path.to_string(),
+            audio_stream,
+            start,
+            bucket_duration,
+            peaks: buckets.into_iter().map(PeakBucket::to_peak).collect(),
+            decoded_duration,
+        });
+    });
+    let index = decoded.inspect_err(|_| {
+        let _ = std::fs::remove_file(&temp_path);
+    })?;
+
+    // The .bin goes into place before its index, so a present index always points at a complete file
+    let bin_path = cache.commit_file(&key, &temp_path, &bin_name)?;
+    cache.write_json(&key, &index_name, &index)?;
+
+    log::info!("Built waveform peaks for {}: {:.1}s of audio", path, index.duration());
+    Ok((bin_path, index))
+}
+
+// Helper function to decode a file's audio to mono PCM and reduce it to peaks at every zoom level
+// on_partial receives (start time, new buckets, decoded seconds)
+fn decode_waveform_peaks<F: FnMut(f64, Vec<PeakBucket>, f64)>(
+    video_path: &str,
+    audio_stream: Option<u32>,
+    bin_path: &std::path::Path,
+    mut on_partial: F,
+) -> Result<PeaksIndex, String> {
+    // First, check if the video has an audio stream
+    let ffprobe = find_ffprobe();
+    let probe_output = Command::new(&ffprobe)
+        .args(&[
+            "-v", "quiet",
+            "-print_format", "json",
+            "-show_streams",
+            "-select_streams", "a",  // Only select audio streams
+            video_path
+        ])
+        .output()
+        .map_err(|e| format!("Failed to probe video: {}", e))?;
+
+    let json_str = String::from_utf8_lossy(&probe_output.stdout);
+    let json: serde_json::Value = serde_json::from_str(&json_str)
+        .map_err(|e| format!("Failed to parse ffprobe output: {}", e))?;
+
+    // Check if there are any audio streams
+    let audio_indexes: Vec<u64> = json["streams"].as_array()
+        .map(|arr| arr.iter().filter_map(|s| s["index"].as_u64()).collect())
+        .unwrap_or_default();
+
+    let mut builder = PeaksBuilder::new(bin_path)?;
+
+    if audio_indexes.is_empty() {
+        log::info!("No audio stream found in video, using silent waveform");
+        return builder.finish(bin_path);
+    }
+
+    if let Some(index) = audio_stream {
+        if !audio_indexes.contains(&(index as u64)) {
+            return Err(format!("Audio stream {} not found (available audio streams: {:?})", index, audio_indexes));
+        }
+    }
+
+    let partial_interval = PEAKS_PARTIAL_INTERVAL_SECS * PEAKS_SAMPLE_RATE as u64;
+    let mut next_partial = partial_interval;
+
+    stream_pcm_samples(video_path, audio_stream, Some(1), Some(PEAKS_SAMPLE_RATE), |sample| {
+        builder.push(sample)?;
+        if builder.total_samples >= next_partial {
+            next_partial += partial_interval;
+            let (start, buckets) = builder.take_partial();
+            on_partial(start, buckets, builder.total_samples as f64 / PEAKS_SAMPLE_RATE as f64);
+        }
+        Ok(())
+    })?;
+
+    let (start, buckets) = builder.take_partial();
+    if !buckets.is_empty() {
+        on_partial(start, buckets, builder.total_samples as f64 / PEAKS_SAMPLE_RATE as f64);
+    }
+
+    builder.finish(bin_path)
+}
+
+// Helper function to decode a file's audio as 16-bit PCM on FFmpeg's stdout and feed it sample by sample.
+// Samples are interleaved by channel. None for channels/sample_rate keeps the source's own layout and rate.
+// Nothing is buffered beyond one read, so memory use doesn't grow with the file's duration.
+fn stream_pcm_samples<F: FnMut(i16) -> Result<(), String>>(
+    path: &str,
+    audio_stream: Option<u32>,
+    channels: Option<u32>,
+    sample_rate: Option<u32>,
+    mut on_sample: F,
+) -> Result<(), String> {
+    use std::io::Read;
+
+    let ffmpeg = find_ffmpeg();
+    let mut cmd = Command::new(&ffmpeg);
+    cmd.arg("-i").arg(path);
+    if let Some(index) = audio_stream {
+        cmd.arg("-map").arg(format!("0:{}", index));
+    }
+    cmd.arg("-vn") // No video
+        .arg("-acodec").arg("pcm_s16le"); // PCM 16-bit
+    if let Some(rate) = sample_rate {
+        cmd.arg("-ar").arg(rate.to_string());
+    }
+    if let Some(count) = channels {
+        cmd.arg("-ac").arg(count.to_string());
+    }
+    let mut child = cmd
+        .arg("-f").arg("s16le") // Raw PCM output
+        .arg("pipe:1")
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to extract audio: {}", e))?;
+
+    // Drain stderr on a separate thread so a chatty FFmpeg can't block on a full pipe
+    let stderr_reader = child.stderr.take().map(|mut stderr| {
+        std::thread::spawn(move || {
+            let mut text = String::new();
+            let _ = stderr.read_to_string(&mut text);
+            text
+        })
+    });
+
+    let mut decode_result = Ok(());
+
+    if let Some(mut stdout) = child.stdout.take() {
+        let mut buffer = vec![0u8; 64 * 1024];
+        let mut carry: Option<u8> = None;
+
+        decode_result = (|| -> Result<(), String> {
+            loop {
+                let read = stdout.read(&mut buffer).map_err(|e| format!("Failed to read audio data: {}", e))?;
+                if read == 0 {
+                    return Ok(());
+                }
+
+                // A sample can straddle two reads; keep its low byte for the next one
+                let mut bytes = &buffer[..read];
+                if let Some(low) = carry.take() {
+                    on_sample(i16::from_le_bytes([low, bytes[0]]))?;
+                    bytes = &bytes[1..];
+                }
+                let mut chunks = bytes.chunks_exact(2);
+                for chunk in &mut chunks {
+                    on_sample(i16::from_le_bytes([chunk[0], chunk[1]]))?;
+                }
+                carry = chunks.remainder().first().copied();
+            }
+        })();
+    }
+
+    if decode_result.is_err() {
+        let _ = child.kill();
+    }
+    let status = child.wait().map_err(|e| format!("Failed to wait for FFmpeg: {}", e))?;
+    let stderr = stderr_reader
+        .and_then(|handle| handle.join().ok())
+        .unwrap_or_default();
+    decode_result?;
+
+    if !status.success() {
+        log::error!("FFmpeg audio extraction failed: {}", stderr);
+        return Err(format!("Failed to extract audio: {}", stderr));
+    }
+
+    Ok(())
+}
+
+// Helper function to read the peaks covering start..end from a peaks file and resample them to `buckets`
+fn slice_waveform_peaks(peaks_path: &PathBuf, index: &PeaksIndex, start: f64, end: f64, buckets: usize) -> Result<WaveformRange, String> {
+    use std::io::{Read, Seek, SeekFrom};
+
+    let bucket_duration = (end - start) / buckets as f64;
+    let silent = || WaveformRange {
+        start,
+        end,
+        bucket_duration,
+        peaks: vec![WaveformPeak { min: 0.0, max: 0.0, rms: 0.0 }; buckets],
+    };
+
+    if index.total_samples == 0 || index.levels.is_empty() {
+        return Ok(silent());
+    }
+
+    // Use the coarsest level that still has at least one stored bucket per requested bucket
+    let samples_per_output = bucket_duration * index.sample_rate as f64;
+    let level = index.levels
+        .iter()
+        .rfind(|level| level.samples_per_bucket as f64 <= samples_per_output)
+        .unwrap_or(&index.levels[0]);
+
+    let spb = level.samples_per_bucket as f64;
+    let first = ((start * index.sample_rate as f64) / spb).floor() as u64;
+    let last = (((end * index.sample_rate as f64) / spb).ceil() as u64).min(level.bucket_count);
+    if first >= last {
+        return Ok(silent());
+    }
+
+    let mut data = vec![0u8; (last - first) as usize * PEAKS_BUCKET_BYTES];
+    let mut file = File::open(peaks_path).map_err(|e| format!("Failed to open waveform peaks: {}", e))?;
+    file.seek(SeekFrom::Start(level.byte_offset + first * PEAKS_BUCKET_BYTES as u64))
+        .and_then(|_| file.read_exact(&mut data))
+        .map_err(|e| format!("Failed to read waveform peaks: {}", e))?;
+
+    let stored: Vec<PeakBucket> = data
+        .chunks_exact(PEAKS_BUCKET_BYTES)
+        .map(PeakBucket::from_bytes)
+        .collect();
+
+    // Map each output bucket to the stored buckets it overlaps; past the end of the audio is silence
+    let peaks = (0..buckets)
+        .map(|i| {
+            let out_start = (start + i as f64 * bucket_duration) * index.sample_rate as f64 / spb;
+            let out_end = (start + (i + 1) as f64 * bucket_duration) * index.sample_rate as f64 / spb;
+            let from = (out_start.floor() as u64).saturating_sub(first) as usize;
+            let to = ((out_end.ceil() as u64).saturating_sub(first) as usize).max(from + 1).min(stored.len());
+            if from >= to {
+                return WaveformPeak { min: 0.0, max: 0.0, rms: 0.0 };
+            }
+            let span = &stored[from..to];
+            let min = span.iter().map(|b| b.min).min().unwrap_or(0);
+            let max = span.iter().map(|b| b.max).max().unwrap_or(0);
+            let mean_square = span.iter().map(|b| (b.rms as f64).powi(2)).sum::<f64>() / span.len() as f64;
+            PeakBucket { min, max, rms: mean_square.sqrt().round().min(u16::MAX as f64) as u16 }.to_peak()
+        })
+        .collect();
+
+    Ok(WaveformRange { start, end, bucket_duration, peaks })
+}
+
+// Decoded samples at full scale; audio that hits these has almost certainly clipped
+const CLIPPING_SAMPLE_LEVEL: i32 = 32767;
+
+// Floor for dBFS values so digital silence doesn't serialize as -infinity (16-bit noise floor)
+const SILENCE_FLOOR_DB: f64 = -96.0;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChannelWaveform {
+    channel: u32,          // 0 = left, 1 = right, ...
+    waveform: Vec<f32>,    // RMS per bucket, 0.0 to 1.0
+    peak: f32,             // Highest absolute sample, 0.0 to 1.0
+    peak_db: f64,          // Peak level in dBFS
+    rms_db: f64,           // Overall RMS level in dBFS
+    clipped_samples: u64,  // Samples at full scale
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChannelWaveforms {
+    audio_stream: Option<u32>,  // Absolute index of the analyzed stream (None if the file has no audio)
+    sample_rate: u32,
+    channels: Vec<ChannelWaveform>,
+}
+
+// Helper function to convert a 0.0-1.0 amplitude to dBFS
+fn amplitude_to_db(amplitude: f64) -> f64 {
+    if amplitude <= 0.0 {
+        SILENCE_FLOOR_DB
+    } else {
+        (20.0 * amplitude.log10()).max(SILENCE_FLOOR_DB)
+    }
+}
+
+// Generate one waveform per channel plus a peak/clipping summary for each,
+// so problems like a mic recorded into only the left channel are visible
+#[tauri::command]
+async fn generate_channel_waveforms(
+    video_path: String,
+    samples: Option<usize>,
+    audio_stream: Option<u32>,
+    app: tauri::AppHandle,
+) -> Result<ChannelWaveforms, String> {
+    let num_samples = samples.unwrap_or(200).max(1);
+    log::info!("Generating per-channel waveforms for: {} with {} samples (audio stream: {:?})", video_path, num_samples, audio_stream);
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let cache = app.state::<MediaCache>();
+        let cache_key = media_file_key(&video_path).ok();
+        let cache_name = match audio_stream {
+            Some(index) => format!("channels_{}_stream{}.json", num_samples, index),
+            None => format!("channels_{}.json", num_samples),
+        };
+
+        if let Some(waveforms) = cache_key.as_deref().and_then(|key| cache.read_json::<ChannelWaveforms>(key, &cache_name)) {
+            log::info!("Using cached per-channel waveforms for: {}", video_path);
+            return Ok(waveforms);
+        }
+
+        let waveforms = compute_channel_waveforms(&video_path, num_samples, audio_stream)?;
+
+        if let Some(key) = cache_key.as_deref() {
+            if let Err(e) = cache.write_json(key, &cache_name, &waveforms) {
+                log::warn!("Failed to cache per-channel waveforms: {}", e);
+            }
+        }
+
+        Ok(waveforms)
+    })
+    .await
+    .map_err(|e| format!("Waveform task failed: {}", e))?
+}
+
+// Blocks of frames kept per output bucket while decoding (between this and twice this many)
+const CHANNEL_WAVEFORM_BLOCKS_PER_BUCKET: usize = 32;
+
+// Reduces interleaved PCM to per-channel RMS buckets, one sample at a time
+// The length of the audio is only known once decoding ends (container durations can be off,
+// e.g. when the video runs longer than the audio), so squares are first summed into blocks of
+// frames and the blocks are grouped into buckets at the end. Blocks double in size whenever
+// there are too many, so memory stays bounded however long the file is.
+struct ChannelWaveformBuilder {
+    channel_count: usize,
+    num_samples: usize,
+    block_frames: u64,
+    blocks: Vec<Vec<(f64, u64)>>,  // Per channel: (sum of squares, sample count) for each block
+    peaks: Vec<i32>,
+    total_squares: Vec<f64>,
+    clipped: Vec<u64>,
+    position: u64,
+}
+
+impl ChannelWaveformBuilder {
+    fn new(channel_count: usize, num_samples: usize) -> Self {
+        ChannelWaveformBuilder {
+            channel_count,
+            num_samples,
+            block_frames: 1,
+            blocks: vec![Vec::new(); channel_count],
+            peaks: vec![0; channel_count],
+            total_squares: vec![0.0; channel_count],
+            clipped: vec![0; channel_count],
+            position: 0,
+        }
+    }
+
+    fn push(&mut self, sample: i16) {
+        let channel = (self.position % self.channel_count as u64) as usize;
+        let frame = self.position / self.channel_count as u64;
+        self.position += 1;
+
+        // Every channel has filled the same blocks at the start of a frame, so they merge together
+        let max_blocks = 2 * CHANNEL_WAVEFORM_BLOCKS_PER_BUCKET * self.num_samples;
+        if channel == 0 && frame / self.block_frames >= max_blocks as u64 {
+            for blocks in &mut self.blocks {
+                *blocks = blocks.chunks(2)
+                    .map(|pair| pair.iter().fold((0.0, 0), |acc, block| (acc.0 + block.0, acc.1 + block.1)))
+                    .collect();
+            }
+            self.block_frames *= 2;
+        }
+
+        let block = (frame / self.block_frames) as usize;
+        let blocks = &mut self.blocks[channel];
+        if blocks.len() <= block {
+            blocks.resize(block + 1, (0.0, 0));
+        }
+        let square = (sample as f64).powi(2);
+        blocks[block].0 += square;
+        blocks[block].1 += 1;
+        self.total_squares[channel] += square;
+
+        let magnitude = (sample as i32).abs();
+        self.peaks[channel] = self.peaks[channel].max(magnitude);
+        if magnitude >= CLIPPING_SAMPLE_LEVEL {
+            self.clipped[channel] += 1;
+        }
+    }
+
+    // Group the blocks into buckets by where they start in the decoded audio, and return
+    // the waveforms along with the number of frames decoded
+    fn finish(self) -> (Vec<ChannelWaveform>, u64) {
+        let frames = self.position / self.channel_count as u64;
+        let channels = (0..self.channel_count)
+            .map(|channel| {
+                let mut buckets = vec![(0.0f64, 0u64); self.num_samples];
+                for (i, (squares, count)) in self.blocks[channel].iter().enumerate() {
+                    let block_start = (i as u64 * self.block_frames) as f64;
+                    let bucket = ((block_start / frames as f64 * self.num_samples as f64) as usize).min(self.num_samples - 1);
+                    buckets[bucket].0 += squares;
+                    buckets[bucket].1 += count;
+                }
+
+                let waveform = buckets
+                    .iter()
+                    .map(|(squares, count)| {
+                        if *count == 0 {
+                            0.0
+                        } else {
+                            ((squares / *count as f64).sqrt() / 32767.0).min(1.0) as f32
+                        }
+                    })
+                    .collect();
+                let peak = (self.peaks[channel] as f64 / 32767.0).min(1.0);
+                let rms = if frames > 0 { (self.total_squares[channel] / frames as f64).sqrt() / 32767.0 } else { 0.0 };
+
+                ChannelWaveform {
+                    channel: channel as u32,
+                    waveform,
+                    peak: peak as f32,
+                    peak_db: amplitude_to_db(peak),
+                    rms_db: amplitude_to_db(rms),
+                    clipped_samples: self.clipped[channel],
+                }
+            })
+            .collect();
+
+        (channels, frames)
+    }
+}
+
+// Helper function to decode a stream at its native layout and rate and reduce each channel to RMS buckets
+// Clipping is measured on the original samples, since resampling smooths away full-scale peaks
+fn compute_channel_waveforms(path: &str, num_samples: usize, audio_stream: Option<u32>) -> Result<ChannelWaveforms, String> {
+    let probe = probe_video_metadata(path)?;
+
+    let stream = match select_audio_stream(&probe, audio_stream)? {
+        Some(stream) => stream,
+        None => {
+            log::info!("No audio stream found in {}, returning no channels", path);
+            return Ok(ChannelWaveforms { audio_stream: None, sample_rate: 0, channels: Vec::new() });
+        }
+    };
+
+    let channel_count = stream.channels.unwrap_or(2).max(1) as usize;
+    let sample_rate = stream.sample_rate.unwrap_or(48000);
+
+    let mut builder = ChannelWaveformBuilder::new(channel_count, num_samples);
+    stream_pcm_samples(path, Some(stream.index), Some(channel_count as u32), Some(sample_rate), |sample| {
+        builder.push(sample);
+        Ok(())
+    })?;
+    let (channels, frames) = builder.finish();
+
+    for channel in channels.iter().filter(|channel| channel.clipped_samples > 0) {
+        log::warn!("Channel {} of {} has {} clipped samples", channel.channel, path, channel.clipped_samples);
+    }
+
+    log::info!("Per-channel waveforms generated for {} channel(s), {} frames", channels.len(), frames);
+    Ok(ChannelWaveforms { audio_stream: Some(stream.index), sample_rate, channels })
+}
+
+// Audio problem analysis runs on 50 ms windows of the native-rate PCM
+const AUDIO_ANALYSIS_WINDOW_SECS: f64 = 0.05;
+
+// A run of this many consecutive full-scale samples counts as clipping (single hits can be legitimate peaks)
+const CLIPPING_MIN_RUN_SAMPLES: u32 = 3;
+
+// Default level (peak dBFS) below which audio is too quiet to be usable, and how long it must stay there
+const DEFAULT_LOW_LEVEL_DB: f64 = -40.0;
+const LOW_LEVEL_MIN_SECS: f64 = 3.0;
+
+// A channel peaking below this while another channel carries signal is considered silent
+const SILENT_CHANNEL_DB: f64 = -60.0;
+const SILENT_CHANNEL_MIN_SECS: f64 = 1.0;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AudioProblem {
+    kind: String,          // "clipping", "low_level" or "silent_channel"
+    start: f64,
+    end: f64,
+    channel: Option<u32>,  // Affected channel (None when the problem spans all channels)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AudioProblemReport {
+    audio_stream: Option<u32>,  // Absolute index of the analyzed stream (None if the file has no audio)
+    channels: u32,
+    duration: f64,
+    problems: Vec<AudioProblem>,  // Sorted by start time
+}
+
+// Collects flagged analysis windows into time ranges
+// Flagged windows closer than merge_gap are joined; ranges shorter than min_duration are dropped
+struct ProblemRangeTracker {
+    current: Option<TimeRange>,
+    ranges: Vec<TimeRange>,
+    merge_gap: f64,
+    min_duration: f64,
+}
+
+impl ProblemRangeTracker {
+    fn new(merge_gap: f64, min_duration: f64) -> Self {
+        ProblemRangeTracker { current: None, ranges: Vec::new(), merge_gap, min_duration }
+    }
+
+    fn mark(&mut self, start: f64, end: f64) {
+        match &mut self.current {
+            Some(range) if start - range.end <= self.merge_gap => range.end = end,
+            _ => {
+                self.close();
+                self.current = Some(TimeRange { start, end });
+            }
+        }
+    }
+
+    fn close(&mut self) {
+        if let Some(range) = self.current.take() {
+            if range.end - range.start >= self.min_duration {
+                self.ranges.push(TimeRange { start: round_to_millis(range.start), end: round_to_millis(range.end) });
+            }
+        }
+    }
+
+    fn finish(mut self) -> Vec<TimeRange> {
+        self.close();
+        self.ranges
+    }
+}
+
+// Helper function to pick the audio stream to analyze: the requested one, or the first
+// Ok(None) means the file has no audio at all
+fn select_audio_stream(probe: &MediaProbe, audio_stream: Option<u32>) -> Result<Option<&AudioStreamInfo>, String> {
+    match audio_stream {
+        Some(index) => probe.audio_streams.iter()
+            .find(|s| s.index == index)
+            .map(Some)
+            .ok_or_else(|| format!("Audio stream {} not found", index)),
+        None => Ok(probe.audio_streams.first()),
+    }
+}
+
+// Scan a file's audio for clipping, unusably low levels and silent channels,
+// so bad takes can be flagged before export
+#[tauri::command]
+async fn analyze_audio_problems(
+    path: String,
+    audio_stream: Option<u32>,
+    low_level_db: Option<f64>,
+) -> Result<AudioProblemReport, String> {
+    let low_level_db = low_level_db.unwrap_or(DEFAULT_LOW_LEVEL_DB);
+    log::info!("Analyzing audio problems in: {} (audio stream: {:?}, low level: {} dB)", path, audio_stream, low_level_db);
+
+    if low_level_db >= 0.0 {
+        return Err("Low level threshold must be below 0 dB".to_string());
+    }
+
+    tauri::async_runtime::spawn_blocking(move || compute_audio_problems(&path, audio_stream, low_level_db))
+        .await
+        .map_err(|e| format!("Audio analysis task failed: {}", e))?
+}
+
+// Flags problem windows in interleaved PCM, one sample at a time, and turns them into time ranges
+struct AudioProblemScanner {
+    channel_count: usize,
+    sample_rate: u32,
+    window_frames: u64,
+    low_level: f64,     // Window peak below this (in sample units) is too quiet
+    silent_level: f64,  // Channel peak below this next to a live channel is silent
+    clipping: Vec<ProblemRangeTracker>,
+    silent_channel: Vec<ProblemRangeTracker>,
+    low_level_tracker: ProblemRangeTracker,
+    // Per-window state; clip runs carry across windows so a run split by a boundary still counts
+    window_peaks: Vec<i32>,
+    window_clipped: Vec<bool>,
+    clip_runs: Vec<u32>,
+    position: u64,
+}
+
+impl AudioProblemScanner {
+    fn new(channel_count: usize, sample_rate: u32, low_level_db: f64) -> Self {
+        AudioProblemScanner {
+            channel_count,
+            sample_rate,
+            window_frames: ((sample_rate as f64 * AUDIO_ANALYSIS_WINDOW_SECS) as u64).max(1),
+            low_level: 10f64.powf(low_level_db / 20.0) * 32767.0,
+            silent_level: 10f64.powf(SILENT_CHANNEL_DB / 20.0) * 32767.0,
+            clipping: (0..channel_count).map(|_| ProblemRangeTracker::new(0.25, 0.0)).collect(),
+            silent_channel: (0..channel_count).map(|_| ProblemRangeTracker::new(2.0, SILENT_CHANNEL_MIN_SECS)).collect(),
+            low_level_tracker: ProblemRangeTracker::new(0.0, LOW_LEVEL_MIN_SECS),
+            window_peaks: vec![0; channel_count],
+            window_clipped: vec![false; channel_count],
+            clip_runs: vec![0; channel_count],
+            position: 0,
+        }
+    }
+
+    fn push(&mut self, sample: i16) {
+        let channel = (self.position % self.channel_count as u64) as usize;
+        let frame = self.position / self.channel_count as u64;
+        self.position += 1;
+
+        let magnitude = (sample as i32).abs();
+        self.window_peaks[channel] = self.window_peaks[channel].max(magnitude);
+        if magnitude >= CLIPPING_SAMPLE_LEVEL {
+            self.clip_runs[channel] += 1;
+            if self.clip_runs[channel] >= CLIPPING_MIN_RUN_SAMPLES {
+                self.window_clipped[channel] = true;
+            }
+        } else {
+            self.clip_runs[channel] = 0;
+        }
+
+        // Last sample of the last channel in a window
+        if channel == self.channel_count - 1 && (frame + 1) % self.window_frames == 0 {
+            self.close_window(frame / self.window_frames);
+        }
+    }
+
+    fn close_window(&mut self, window: u64) {
+        let start = (window * self.window_frames) as f64 / self.sample_rate as f64;
+        let end = ((window + 1) * self.window_frames) as f64 / self.sample_rate as f64;
+        let loudest = self.window_peaks.iter().copied().max().unwrap_or(0) as f64;
+
+        for channel in 0..self.channel_count {
+            if self.window_clipped[channel] {
+                self.clipping[channel].mark(start, end);
+            }
+            // Only multi-channel audio can have one dead channel next to a live one
+            if self.channel_count > 1 && (self.window_peaks[channel] as f64) < self.silent_level && loudest >= self.low_level {
+                self.silent_channel[channel].mark(start, end);
+            }
+        }
+        if loudest < self.low_level {
+            self.low_level_tracker.mark(start, end);
+        }
+
+        self.window_peaks.iter_mut().for_each(|p| *p = 0);
+        self.window_clipped.iter_mut().for_each(|c| *c = false);
+    }
+
+    // Flush a trailing partial window and return the problems sorted by start time,
+    // along with the number of frames scanned
+    fn finish(mut self) -> (Vec<AudioProblem>, u64) {
+        let frames = self.position / self.channel_count as u64;
+        if frames % self.window_frames != 0 {
+            self.close_window(frames / self.window_frames);
+        }
+
+        let mut problems: Vec<AudioProblem> = Vec::new();
+        for (channel, tracker) in self.clipping.into_iter().enumerate() {
+            problems.extend(tracker.finish().into_iter().map(|range| AudioProblem {
+                kind: "clipping".to_string(),
+                start: range.start,
+                end: range.end,
+                channel: Some(channel as u32),
+            }));
+        }
+        for (channel, tracker) in self.silent_channel.into_iter().enumerate() {
+            problems.extend(tracker.finish().into_iter().map(|range| AudioProblem {
+                kind: "silent_channel".to_string(),
+                start: range.start,
+                end: range.end,
+                channel: Some(channel as u32),
+            }));
+        }
+        problems.extend(self.low_level_tracker.finish().into_iter().map(|range| AudioProblem {
+            kind: "low_level".to_string(),
+            start: range.start,
+            end: range.end,
+            channel: None,
+        }));
+        problems.sort_by(|a, b| a.start.partial_cmp(&b.start).unwrap());
+
+        (problems, frames)
+    }
+}
+
+// Helper function to decode a stream at its native layout and rate and flag problem windows
+fn compute_audio_problems(path: &str, audio_stream: Option<u32>, low_level_db: f64) -> Result<AudioProblemReport, String> {
+    let probe = probe_video_metadata(path)?;
+    let stream = match select_audio_stream(&probe, audio_stream)? {
+        Some(stream) => stream,
+        None => {
+            log::info!("No audio stream found in {}, nothing to analyze", path);
+            return Ok(AudioProblemReport { audio_stream: None, channels: 0, duration: probe.duration, problems: Vec::new() });
+        }
+    };
+
+    let channel_count = stream.channels.unwrap_or(2).max(1) as usize;
+    let sample_rate = stream.sample_rate.unwrap_or(48000);
+
+    let mut scanner = AudioProblemScanner::new(channel_count, sample_rate, low_level_db);
+    stream_pcm_samples(path, Some(stream.index), Some(channel_count as u32), Some(sample_rate), |sample| {
+        scanner.push(sample);
+        Ok(())
+    })?;
+    let (problems, frames) = scanner.finish();
+
+    log::info!("Found {} audio problems in {}", problems.len(), path);
+    Ok(AudioProblemReport {
+        audio_stream: Some(stream.index),
+        channels: channel_count as u32,
+        duration: frames as f64 / sample_rate as f64,
+        problems,
+    })
+}
+
+// Largest spectrogram showspectrumpic should be asked to render, per side
+const SPECTROGRAM_MAX_SIZE: u32 = 8192;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Spectrogram {
+    image_path: String,
+    width: u32,
+    height: u32,
+    start: f64,
+    end: f64,
+    max_frequency: f64,  // Frequency at the top edge (Nyquist of the source), bottom edge is 0 Hz
+}
+
+// Render a spectrogram PNG for part of a file, to inspect hum, noise and crackling visually
+// Time runs left to right, frequency (linear) bottom to top, brightness is log-scaled magnitude
+// Decoding the range can take a while for long spans, so the work runs off the main thread
+#[tauri::command]
+async fn generate_spectrogram(
+    path: String,
+    start: f64,
+    end: f64,
+    width: u32,
+    height: u32,
+    audio_stream: Option<u32>,
+    app: tauri::AppHandle,
+) -> Result<Spectrogram, String> {
+    log::info!("Generating spectrogram for: {} ({}s-{}s at {}x{})", path, start, end, width, height);
+
+    if width < 2 || height < 2 || width > SPECTROGRAM_MAX_SIZE || height > SPECTROGRAM_MAX_SIZE {
+        return Err(format!("Spectrogram size must be between 2 and {} pixels per side", SPECTROGRAM_MAX_SIZE));
+    }
+    if end <= start {
+        return Err("Spectrogram end must be after start".to_string());
+    }
+
+    tauri::async_runtime::spawn_blocking(move || {
+        render_spectrogram(&path, start, end, width, height, audio_stream, &app.state::<MediaCache>())
+    })
+    .await
+    .map_err(|e| format!("Spectrogram task failed: {}", e))?
+}
+
+// Helper function to render (or find in the media cache) the spectrogram PNG for a range
+fn render_spectrogram(
+    path: &str,
+    start: f64,
+    end: f64,
+    width: u32,
+    height: u32,
+    audio_stream: Option<u32>,
+    cache: &MediaCache,
+) -> Result<Spectrogram, String> {
+    let probe = probe_video_metadata(path)?;
+    let stream = select_audio_stream(&probe, audio_stream)?
+        .ok_or("This file has no audio track")?;
+    let max_frequency = stream.sample_rate.unwrap_or(48000) as f64 / 2.0;
+
+    let start = round_to_millis(start.max(0.0));
+    let end = round_to_millis(end);
+
+    let key = media_file_key(path)?;
+    let image_name = format!(
+        "spectrogram_{}_{}_{}x{}_stream{}.png",
+        (start * 1000.0) as u64, (end * 1000.0) as u64, width, height, stream.index
+    );
+
+    let image_path = match cache.cached_file(&key, &image_name) {
+        Some(cached) => {
+            log::info!("Using cached spectrogram: {:?}", cached);
+            cached
+        }
+        None => {
+            let image_path = cache.temp_file(&key, &image_name)?;
+
+            // legend=0 keeps the image exactly width x height so the UI can line it up with the timeline
+            let filter = format!(
+                "[0:{}]showspectrumpic=s={}x{}:legend=0:mode=combined:color=intensity:scale=log:fscale=lin",
+                stream.index, width, height
+            );
+
+            let ffmpeg = find_ffmpeg();
+            let output = Command::new(&ffmpeg)
+                .arg("-ss").arg(start.to_string())
+                .arg("-t").arg(round_to_millis(end - start).to_string())
+                .arg("-i").arg(path)
+                .arg("-filter_complex").arg(&filter)
+                .arg("-frames:v").arg("1")
+                .arg("-y")
+                .arg(&image_path)
+                .output()
+                .map_err(|e| format!("Failed to generate spectrogram: {}", e))?;
+
+            if !output.status.success() {
+                let stderr = String::from_utf8_lossy(&output.stderr);
+                log::error!("FFmpeg spectrogram generation failed: {}", stderr);
+                let _ = std::fs::remove_file(&image_path);
+                return Err(format!("Failed to generate spectrogram: {}", stderr));
+            }
+
+            let image_path = cache.commit_file(&key, &image_path, &image_name)?;
+            cache.evict();
+            image_path
+        }
+    };
+
+    Ok(Spectrogram {
+        image_path: image_path.to_string_lossy().to_string(),
+        width,
+        height,
+        start,
+        end,
+        max_frequency,
+    })
+}
+
+// ===== EXPORT PRESETS =====
+
+// Built-in presets used when an export doesn't name one; they match the settings these exports always used
+const DEFAULT_EXPORT_PRESET: &str = "H.264 Standard";
+const DEFAULT_MERGE_PRESET: &str = "H.264 High Quality";
+
+// Largest output size a preset may request, per side
+const EXPORT_PRESET_MAX_SIZE: u32 = 8192;
+
+const X26X_SPEED_PRESETS: &[&str] = &[
+    "ultrafast", "superfast", "veryfast", "faster", "fast", "medium", "slow", "slower", "veryslow", "placebo",
+];
+
+// ProRes profiles by name, as numbered by prores_ks
+const PRORES_PROFILES: &[(&str, u32)] = &[
+    ("proxy", 0), ("lt", 1), ("standard", 2), ("hq", 3), ("4444", 4), ("4444xq", 5),
+];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportPreset {
+    name: String,
+    video_codec: String,              // "x264", "x265", "vp9", "av1", "prores" or "ffv1"
+    container: String,                // "mp4", "mov", "mkv" or "webm"
+    crf: Option<u32>,                 // Constant-quality mode (used when no bitrate is set)
+    video_bitrate_kbps: Option<u32>,  // Average-bitrate mode
+    preset: Option<String>,           // x264/x265: "fast", "medium"...; VP9: cpu-used 0-8; AV1: 0-13; ProRes: profile ("hq", "4444"...)
+    audio_codec: String,              // "aac", "opus", "flac" or "pcm"
+    audio_bitrate_kbps: Option<u32>,  // For aac/opus (default: 192)
+    width: Option<u32>,               // Output size; setting only one side keeps the aspect ratio
+    height: Option<u32>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ExportPresetList {
+    builtin: Vec<ExportPreset>,
+    user: Vec<ExportPreset>,
+}
+
+// User-saved export presets, persisted as a JSON array in the app config dir
+struct ExportPresetStore {
+    path: PathBuf,
+    lock: Mutex<()>,
+}
+
+impl ExportPresetStore {
+    fn new(path: PathBuf) -> Self {
+        ExportPresetStore { path, lock: Mutex::new(()) }
+    }
+
+    fn load(&self) -> Result<Vec<ExportPreset>, String> {
+        match std::fs::read(&self.path) {
+            Ok(data) => serde_json::from_slice(&data)
+                .map_err(|e| format!("Failed to parse saved export presets: {}", e)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+            Err(e) => Err(format!("Failed to read saved export presets: {}", e)),
+        }
+    }
+
+    fn save(&self, presets: &[ExportPreset]) -> Result<(), String> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create config directory: {}", e))?;
+        }
+        let data = serde_json::to_vec_pretty(presets)
+            .map_err(|e| format!("Failed to serialize export presets: {}", e))?;
+        std::fs::write(&self.path, data)
+            .map_err(|e| format!("Failed to save export presets: {}", e))
+    }
+
+    fn list(&self) -> Result<ExportPresetList, String> {
+        Ok(ExportPresetList { builtin: builtin_export_presets(), user: self.load()? })
+    }
+}
+
+fn builtin_export_presets() -> Vec<ExportPreset> {
+    vec![
+        ExportPreset {
+            name: DEFAULT_EXPORT_PRESET.to_string(),
+            video_codec: "x264".to_string(),
+            container: "mp4".to_string(),
+            crf: Some(23),
+            video_bitrate_kbps: None,
+            preset: Some("fast".to_string()),
+            audio_codec: "aac".to_string(),
+            audio_bitrate_kbps: Some(128),
+            width: None,
+            height: None,
+        },
+        ExportPreset {
+            name: DEFAULT_MERGE_PRESET.to_string(),
+            video_codec: "x264".to_string(),
+            container: "mp4".to_string(),
+            crf: Some(18),
+            video_bitrate_kbps: None,
+            preset: Some("medium".to_string()),
+            audio_codec: "aac".to_string(),
+            audio_bitrate_kbps: Some(192),
+            width: None,
+            height: None,
+        },
+        ExportPreset {
+            name: "H.264 1080p Web".to_string(),
+            video_codec: "x264".to_string(),
+            container: "mp4".to_string(),
+            crf: None,
+            video_bitrate_kbps: Some(8000),
+            preset: Some("medium".to_string()),
+            audio_codec: "aac".to_string(),
+            audio_bitrate_kbps: Some(192),
+            width: None,
+            height: Some(1080),
+        },
+        ExportPreset {
+            name: "HEVC (H.265)".to_string(),
+            video_codec: "x265".to_string(),
+            container: "mp4".to_string(),
+            crf: Some(26),
+            video_bitrate_kbps: None,
+            preset: Some("medium".to_string()),
+            audio_codec: "aac".to_string(),
+            audio_bitrate_kbps: Some(192),
+            width: None,
+            height: None,
+        },
+        ExportPreset {
+            name: "VP9 WebM".to_string(),
+            video_codec: "vp9".to_string(),
+            container: "webm".to_string(),
+            crf: Some(32),
+            video_bitrate_kbps: None,
+            preset: Some("2".to_string()),
+            audio_codec: "opus".to_string(),
+            audio_bitrate_kbps: Some(160),
+            width: None,
+            height: None,
+        },
+        ExportPreset {
+            name: "AV1".to_string(),
+            video_codec: "av1".to_string(),
+            container: "mkv".to_string(),
+            crf: Some(32),
+            video_bitrate_kbps: None,
+            preset: Some("8".to_string()),
+            audio_codec: "opus".to_string(),
+            audio_bitrate_kbps: Some(160),
+            width: None,
+            height: None,
+        },
+        ExportPreset {
+            name: "ProRes 422 HQ".to_string(),
+            video_codec: "prores".to_string(),
+            container: "mov".to_string(),
+            crf: None,
+            video_bitrate_kbps: None,
+            preset: Some("hq".to_string()),
+            audio_codec: "pcm".to_string(),
+            audio_bitrate_kbps: None,
+            width: None,
+            height: None,
+        },
+        ExportPreset {
+            name: "FFV1 Lossless".to_string(),
+            video_codec: "ffv1".to_string(),
+            container: "mkv".to_string(),
+            crf: None,
+            video_bitrate_kbps: None,
+            preset: None,
+            audio_codec: "flac".to_string(),
+            audio_bitrate_kbps: None,
+            width: None,
+            height: None,
+        },
+    ]
+}
+
+// Helper function to check that a preset only combines codecs, containers and settings FFmpeg can produce
+fn validate_export_preset(preset: &ExportPreset) -> Result<(), String> {
+    let video = preset.video_codec.as_str();
+    let audio = preset.audio_codec.as_str();
+
+    if preset.name.trim().is_empty() {
+        return Err("Export preset needs a name".to_string());
+    }
+    if !["x264", "x265", "vp9", "av1", "prores", "ffv1"].contains(&video) {
+        return Err(format!("Unsupported video codec: {}", video));
+    }
+    if !["aac", "opus", "flac", "pcm"].contains(&audio) {
+        return Err(format!("Unsupported audio codec: {}", audio));
+    }
+
+    let (allowed_video, allowed_audio): (&[&str], &[&str]) = match preset.container.as_str() {
+        "mp4" => (&["x264", "x265", "vp9", "av1"], &["aac", "opus"]),
+        "mov" => (&["x264", "x265", "prores"], &["aac", "pcm"]),
+        "webm" => (&["vp9", "av1"], &["opus"]),
+        "mkv" => (&["x264", "x265", "vp9", "av1", "prores", "ffv1"], &["aac", "opus", "flac", "pcm"]),
+        other => return Err(format!("Unsupported container: {}", other)),
+    };
+    if !allowed_video.contains(&video) || !allowed_audio.contains(&audio) {
+        return Err(format!("A {} file can't hold {} video with {} audio", preset.container, video, audio));
+    }
+
+    let max_crf = match video {
+        "x264" | "x265" => Some(51),
+        "vp9" | "av1" => Some(63),
+        _ => None,  // ProRes and FFV1 have no quality/bitrate setting
+    };
+    if let (Some(max_crf), Some(crf)) = (max_crf, preset.crf) {
+        if crf > max_crf {
+            return Err(format!("CRF for {} must be between 0 and {}", video, max_crf));
+        }
+    }
+    if max_crf.is_some() && preset.crf.is_none() && preset.video_bitrate_kbps.is_none() {
+        return Err(format!("{} needs either a CRF or a video bitrate", video));
+    }
+    if preset.video_bitrate_kbps == Some(0) || preset.audio_bitrate_kbps == Some(0) {
+        return Err("Bitrates must be greater than zero".to_string());
+    }
+
+    if let Some(speed) = preset.preset.as_deref() {
+        let valid = match video {
+            "x264" | "x265" => X26X_SPEED_PRESETS.contains(&speed),
+            "vp9" => speed.parse::<u32>().map(|n| n <= 8).unwrap_or(false),
+            "av1" => speed.parse::<u32>().map(|n| n <= 13).unwrap_or(false),
+            "prores" => PRORES_PROFILES.iter().any(|(name, _)| *name == speed),
+            _ => true,
+        };
+        if !valid {
+            return Err(format!("Invalid preset '{}' for {}", speed, video));
+        }
+    }
+
+    for side in [preset.width, preset.height].into_iter().flatten() {
+        if !(2..=EXPORT_PRESET_MAX_SIZE).contains(&side) || side % 2 != 0 {
+            return Err(format!("Output size must be even and between 2 and {} pixels", EXPORT_PRESET_MAX_SIZE));
+        }
+    }
+
+    Ok(())
+}
+
+// Helper function to pick the preset for an export: the one given, or the named built-in default
+fn resolve_export_preset(preset: Option<ExportPreset>, default_name: &str) -> Result<ExportPreset, String> {
+    let preset = match preset {
+        Some(preset) => preset,
+        None => builtin_export_presets()
+            .into_iter()
+            .find(|p| p.name == default_name)
+            .ok_or_else(|| format!("Unknown built-in export preset: {}", default_name))?,
+    };
+    validate_export_preset(&preset)?;
+    Ok(preset)
+}
+
+// Helper function to build the FFmpeg video encoder arguments for a preset
+fn preset_video_args(preset: &ExportPreset) -> Vec<String> {
+    let mut args: Vec<String> = Vec::new();
+    let mut push = |values: &[&str]| args.extend(values.iter().map(|v| v.to_string()));
+
+    let bitrate = preset.video_bitrate_kbps.map(|kbps| format!("{}k", kbps));
+    let speed = preset.preset.as_deref();
+
+    match preset.video_codec.as_str() {
+        "x264" | "x265" => {
+            let encoder = if preset.video_codec == "x264" { "libx264" } else { "libx265" };
+            push(&["-c:v", encoder, "-preset", speed.unwrap_or("medium")]);
+            match &bitrate {
+                Some(bitrate) => push(&["-b:v", bitrate]),
+                None => push(&["-crf", &preset.crf.unwrap_or(23).to_string()]),
+            }
+            push(&["-pix_fmt", "yuv420p"]);
+            if preset.video_codec == "x265" {
+                // Apple players only recognize HEVC tagged as hvc1
+                push(&["-tag:v", "hvc1"]);
+            }
+        }
+        "vp9" => {
+            push(&["-c:v", "libvpx-vp9", "-cpu-used", speed.unwrap_or("2"), "-row-mt", "1"]);
+            match &bitrate {
+                Some(bitrate) => push(&["-b:v", bitrate]),
+                // -b:v 0 puts libvpx in constant-quality mode
+                None => push(&["-crf", &preset.crf.unwrap_or(32).to_string(), "-b:v", "0"]),
+            }
+            push(&["-pix_fmt", "yuv420p"]);
+        }
+        "av1" => {
+            push(&["-c:v", "libsvtav1", "-preset", speed.unwrap_or("8")]);
+            match &bitrate {
+                Some(bitrate) => push(&["-b:v", bitrate]),
+                None => push(&["-crf", &preset.crf.unwrap_or(32).to_string()]),
+            }
+            push(&["-pix_fmt", "yuv420p"]);
+        }
+        "prores" => {
+            let profile = speed.unwrap_or("hq");
+            let number = PRORES_PROFILES.iter()
+                .find(|(name, _)| *name == profile)
+                .map(|(_, number)| *number)
+                .unwrap_or(3);
+            let pixel_format = if number >= 4 { "yuva444p10le" } else { "yuv422p10le" };
+            push(&["-c:v", "prores_ks", "-profile:v", &number.to_string(), "-pix_fmt", pixel_format]);
+        }
+        _ => push(&["-c:v", "ffv1", "-level", "3"]),
+    }
+
+    args
+}
+
+// Helper function to build the FFmpeg audio encoder arguments for a preset
+fn preset_audio_args(preset: &ExportPreset) -> Vec<String> {
+    let bitrate = format!("{}k", preset.audio_bitrate_kbps.unwrap_or(192));
+    let args: Vec<&str> = match preset.audio_codec.as_str() {
+        "aac" => vec!["-c:a", "aac", "-b:a", &bitrate],
+        "opus" => vec!["-c:a", "libopus", "-b:a", &bitrate],
+        "flac" => vec!["-c:a", "flac"],
+        _ => vec!["-c:a", "pcm_s16le"],
+    };
+    args.into_iter().map(|a| a.to_string()).collect()
+}
+
+// Helper function to build the muxer arguments, so the container doesn't depend on the output file extension
+fn preset_container_args(preset: &ExportPreset) -> Vec<String> {
+    let args: Vec<&str> = match preset.container.as_str() {
+        "mp4" => vec!["-f", "mp4", "-movflags", "+faststart"],
+        "mov" => vec!["-f", "mov", "-movflags", "+faststart"],
+        "webm" => vec!["-f", "webm"],
+        _ => vec!["-f", "matroska"],
+    };
+    args.into_iter().map(|a| a.to_string()).collect()
+}
+
+// Helper function to pick the file extension and encoder arguments for multi-clip temp files,
+// which the concat encodes a second time. Lossless presets get lossless intermediates so the
+// export isn't limited by a lossy first pass; everything else favors encoding speed.
+fn temp_clip_format(preset: &ExportPreset) -> (&'static str, Vec<String>) {
+    let (extension, args): (&str, Vec<&str>) = match preset.video_codec.as_str() {
+        "prores" | "ffv1" => ("mkv", vec!["-c:v", "ffv1", "-level", "3", "-c:a", "flac", "-f", "matroska"]),
+        _ => ("mp4", vec!["-c:v", "libx264", "-preset", "ultrafast", "-crf", "23", "-c:a", "aac", "-b:a", "128k", "-f", "mp4"]),
+    };
+    (extension, args.into_iter().map(|a| a.to_string()).collect())
+}
+
+// Helper function to build a scale filter for a preset's output size (None keeps the source size)
+// With both sides set the picture is fit inside and letterboxed, like the multi-clip canvas
+fn preset_scale_filter(preset: &ExportPreset) -> Option<String> {
+    match (preset.width, preset.height) {
+        (Some(width), Some(height)) => Some(format!(
+            "scale=w={}:h={}:force_original_aspect_ratio=decrease,pad={}:{}:(ow-iw)/2:(oh-ih)/2:black",
+            width, height, width, height
+        )),
+        (Some(width), None) => Some(format!("scale={}:-2", width)),
+        (None, Some(height)) => Some(format!("scale=-2:{}", height)),
+        (None, None) => None,
+    }
+}
+
+// Helper function to apply a preset's output size to the multi-clip canvas
+fn preset_canvas_size(preset: &ExportPreset, canvas: (u32, u32)) -> (u32, u32) {
+    let (canvas_width, canvas_height) = canvas;
+    let even = |value: f64| ((value / 2.0).round() as u32 * 2).max(2);
+    match (preset.width, preset.height) {
+        (Some(width), Some(height)) => (width, height),
+        (Some(width), None) => (width, even(width as f64 * canvas_height as f64 / canvas_width.max(1) as f64)),
+        (None, Some(height)) => (even(height as f64 * canvas_width as f64 / canvas_height.max(1) as f64), height),
+        (None, None) => canvas,
+    }
+}
+
+// Helper function to pick the MIME type for an exported file
+fn container_mime_type(container: &str) -> &'static str {
+    match container {
+        "mov" => "video/quicktime",
+        "webm" => "video/webm",
+        "mkv" => "video/x-matroska",
+        _ => "video/mp4",
+    }
+}
+
+// List built-in and user-saved export presets
+#[tauri::command]
+fn list_export_presets(store: State<ExportPresetStore>) -> Result<ExportPresetList, String> {
+    store.list()
+}
+
+// Save a user preset, replacing any user preset with the same name
+#[tauri::command]
+fn save_export_preset(preset: ExportPreset, store: State<ExportPresetStore>) -> Result<ExportPresetList, String> {
+    log::info!("Saving export preset: {:?}", preset);
+    validate_export_preset(&preset)?;
+
+    if builtin_export_presets().iter().any(|p| p.name == preset.name) {
+        return Err(format!("'{}' is a built-in preset - save it under a different name", preset.name));
+    }
+
+    let _guard = store.lock.lock().unwrap();
+    let mut presets = store.load()?;
+    match presets.iter_mut().find(|p| p.name == preset.name) {
+        Some(existing) => *existing = preset,
+        None => presets.push(preset),
+    }
+    store.save(&presets)?;
+    store.list()
+}
+
+#[tauri::command]
+fn delete_export_preset(name: String, store: State<ExportPresetStore>) -> Result<ExportPresetList, String> {
+    log::info!("Deleting export preset: {}", name);
+
+    let _guard = store.lock.lock().unwrap();
+    let mut presets = store.load()?;
+    let before = presets.len();
+    presets.retain(|p| p.name != name);
+    if presets.len() == before {
+        return Err(format!("No saved preset named '{}'", name));
+    }
+    store.save(&presets)?;
+    store.list()
+}
+
+// ===== EXPORT JOBS =====
+
+// Error returned by every export step once its job has been cancelled
+const EXPORT_CANCELLED_ERROR: &str = "Export cancelled";
+
+static NEXT_EXPORT_JOB: AtomicU64 = AtomicU64::new(1);
+
+// Shared state of one export: its cancellation flag, the FFmpeg children currently running
+// (keyed by process ID, as clips may render in parallel), and the temp files that must not outlive it
+struct ExportJob {
+    id: String,
+    cancelled: AtomicBool,
+    aborted: AtomicBool,  // A step failed; remaining FFmpeg processes are stopped, but the job isn't "cancelled"
+    children: Mutex<HashMap<u32, Child>>,
+    temp_files: Mutex<Vec<PathBuf>>,
+}
+
+// Helper function to make an export job ID that stays unique across app restarts
+fn next_export_job_id() -> String {
+    let millis = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or(0);
+    format!("export-{}-{}", millis, NEXT_EXPORT_JOB.fetch_add(1, Ordering::SeqCst))
+}
+
+impl ExportJob {
+    fn new() -> Self {
+        ExportJob::with_id(next_export_job_id())
+    }
+
+    fn with_id(id: String) -> Self {
+        ExportJob {
+            id,
+            cancelled: AtomicBool::new(false),
+            aborted: AtomicBool::new(false),
+            children: Mutex::new(HashMap::new()),
+            temp_files: Mutex::new(Vec::new()),
+        }
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+
+    fn is_aborted(&self) -> bool {
+        self.aborted.load(Ordering::SeqCst)
+    }
+
+    fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+        for child in self.children.lock().unwrap().values_mut() {
+            let _ = child.kill();
+        }
+    }
+
+    // Kill the running FFmpeg processes after one of them failed, so the export fails fast
+    // instead of waiting for work whose output will be thrown away
+    fn abort(&self) {
+        self.aborted.store(true, Ordering::SeqCst);
+        for child in self.children.lock().unwrap().values_mut() {
+            let _ = child.kill();
+        }
+    }
+
+    // Hand a running FFmpeg process to the job; kill it straight away if cancel or abort raced the spawn
+    fn attach_child(&self, mut child: Child) {
+        let mut children = self.children.lock().unwrap();
+        if self.is_cancelled() || self.is_aborted() {
+            let _ = child.kill();
+        }
+        children.insert(child.id(), child);
+    }
+
+    fn detach_child(&self, pid: u32) -> Option<Child> {
+        self.children.lock().unwrap().remove(&pid)
+    }
+
+    fn track_temp_file(&self, path: PathBuf) {
+        self.temp_files.lock().unwrap().push(path);
+    }
+
+    fn remove_temp_files(&self) {
+        for temp_file in self.temp_files.lock().unwrap().drain(..) {
+            let _ = std::fs::remove_file(temp_file);
+        }
+    }
+}
+
+// Background exports by job ID, for cancel_export
+struct ExportJobs {
+    jobs: Mutex<HashMap<String, Arc<ExportJob>>>,
+}
+
+#[derive(Clone, serde::Serialize)]
+struct ExportJobEvent {
+    job_id: String,
+    status: String,  // "completed", "failed" or "cancelled"
+    output_path: Option<String>,
+    error: Option<String>,
+}
+
+// Helper function to unregister a finished background export and report how it ended
+fn finish_export_job(window: &tauri::Window, job: &ExportJob, result: Result<String, String>) {
+    // A retried queue job reuses its ID, so only unregister the entry if it is still this job
+    {
+        let state = window.state::<ExportJobs>();
+        let mut jobs = state.jobs.lock().unwrap();
+        if jobs.get(&job.id).is_some_and(|registered| std::ptr::eq(registered.as_ref(), job)) {
+            jobs.remove(&job.id);
+        }
+    }
+
+    let event = match result {
+        Ok(output_path) => {
+            log::info!("Export job {} completed: {}", job.id, output_path);
+            ExportJobEvent { job_id: job.id.clone(), status: "completed".to_string(), output_path: Some(output_path), error: None }
+        }
+        Err(_) if job.is_cancelled() => {
+            log::info!("Export job {} cancelled", job.id);
+            ExportJobEvent { job_id: job.id.clone(), status: "cancelled".to_string(), output_path: None, error: None }
+        }
+        Err(e) => {
+            log::error!("Export job {} failed: {}", job.id, e);
+            ExportJobEvent { job_id: job.id.clone(), status: "failed".to_string(), output_path: None, error: Some(e) }
+        }
+    };
+
+    let _ = window.emit("export-job", event);
+}
+
+// Helper function to register a background export and run it on its own thread
+fn spawn_export_job<F>(window: tauri::Window, jobs: &ExportJobs, run: F) -> String
+where
+    F: FnOnce(&tauri::Window, &ExportJob) -> Result<String, String> + Send + 'static,
+{
+    spawn_export_job_with(window, jobs, Arc::new(ExportJob::new()), run)
+}
+
+fn spawn_export_job_with<F>(window: tauri::Window, jobs: &ExportJobs, job: Arc<ExportJob>, run: F) -> String
+where
+    F: FnOnce(&tauri::Window, &ExportJob) -> Result<String, String> + Send + 'static,
+{
+    let job_id = job.id.clone();
+    jobs.jobs.lock().unwrap().insert(job_id.clone(), job.clone());
+
+    std::thread::spawn(move || {
+        let result = run(&window, &job);
+        finish_export_job(&window, &job, result);
+    });
+
+    job_id
+}
+
+// Start a single-file export in the background; returns its job ID right away
+// The outcome arrives as an "export-job" event
+#[tauri::command]
+fn start_export(options: ExportOptions, window: tauri::Window, jobs: State<ExportJobs>) -> Result<String, String> {
+    let job_id = spawn_export_job(window, &jobs, move |window, job| export_video_job(options, window, job));
+    log::info!("Started export job {}", job_id);
+    Ok(job_id)
+}
+
+// Start a multi-clip export in the background; returns its job ID right away
+#[tauri::command]
+fn start_multi_clip_export(options: MultiClipExportOptions, window: tauri::Window, jobs: State<ExportJobs>) -> Result<String, String> {
+    if options.clips.is_empty() {
+        return Err("No clips to export".to_string());
+    }
+    let job_id = spawn_export_job(window, &jobs, move |window, job| export_multi_clip_job(options, window, job));
+    log::info!("Started multi-clip export job {}", job_id);
+    Ok(job_id)
+}
+
+// Cancel a background export: kills its FFmpeg process and removes its temp files
+// and partial output; the job then reports "cancelled" through the "export-job" event
+#[tauri::command]
+fn cancel_export(job_id: String, jobs: State<ExportJobs>) -> Result<(), String> {
+    log::info!("cancel_export called for job: {}", job_id);
+
+    let job = jobs.jobs.lock().unwrap()
+        .get(&job_id)
+        .cloned()
+        .ok_or_else(|| format!("No running export with ID {}", job_id))?;
+    job.cancel();
+    Ok(())
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportOptions {
+    input_path: String,
+    output_path: String,
+    trim_start: Option<f64>,
+    trim_end: Option<f64>,
+    target_loudness: Option<f64>,  // Normalize audio to this integrated loudness in LUFS, e.g. -14.0
+    preset: Option<ExportPreset>,  // Encoding settings (default: the "H.264 Standard" built-in)
+}
+
+#[tauri::command]
+fn export_video(options: ExportOptions, window: tauri::Window) -> Result<String, String> {
+    export_video_job(options, &window, &ExportJob::new())
+}
+
+// Run a single-file export as an export job (cancellable when started through start_export)
+// Progress goes out through the same "merge-progress" event as multi-clip exports
+fn export_video_job(mut options: ExportOptions, window: &tauri::Window, job: &ExportJob) -> Result<String, String> {
+    log::info!("Starting export {}: {:?}", job.id, options);
+
+    // Check if input file exists
+    if !PathBuf::from(&options.input_path).exists() {
+        return Err("Input file does not exist".to_string());
+    }
+
+    // A VFR source is exported from its CFR working copy, which shares the source's timeline
+    options.input_path = export_source_path(&options.input_path, &window.state::<MediaCache>());
+
+    let preset = resolve_export_preset(options.preset.clone(), DEFAULT_EXPORT_PRESET)?;
+    log::info!("Using export preset: {}", preset.name);
+
+    // Check if input has an audio stream
+    let ffprobe = find_ffprobe();
+    let probe_output = Command::new(&ffprobe)
+        .args(&[
+            "-v", "quiet",
+            "-print_format", "json",
+            "-show_streams",
+            "-select_streams", "a",
+            &options.input_path
+        ])
+        .output()
+        .map_err(|e| format!("Failed to probe input: {}", e))?;
+
+    let json_str = String::from_utf8_lossy(&probe_output.stdout);
+    let json: serde_json::Value = serde_json::from_str(&json_str)
+        .unwrap_or(serde_json::json!({"streams": []}));
+
+    let has_audio = json["streams"].as_array()
+        .map(|arr| !arr.is_empty())
+        .unwrap_or(false);
+
+    // First loudnorm pass: measure the exported range so the second pass can apply an exact gain
+    let loudness_filter = match options.target_loudness {
+        Some(target) if has_audio => {
+            validate_target_loudness(target)?;
+            let range = match (options.trim_start, options.trim_end) {
+                (Some(start), Some(end)) => Some(TimeRange { start, end }),
+                _ => None,
+            };
+            let measured = analyze_loudness(&options.input_path, range.as_ref(), Some(target), Some(job))?;
+            log::info!("Measured loudness before export: {:?}", measured);
+            loudnorm_filter(target, &measured)
+        }
+        _ => None,
+    };
+
+    // Build FFmpeg command
+    let ffmpeg = find_ffmpeg();
+    let mut cmd = Command::new(&ffmpeg);
+    cmd.arg("-i").arg(&options.input_path);
+
+    // Add trim parameters if specified
+    if let (Some(start), Some(end)) = (options.trim_start, options.trim_end) {
+        let duration = end - start;
+        cmd.arg("-ss").arg(start.to_string());
+        cmd.arg("-t").arg(duration.to_string());
+    }
+
+    // Output options
+    if let Some(filter) = preset_scale_filter(&preset) {
+        cmd.arg("-vf").arg(filter);
+    }
+    cmd.args(preset_video_args(&preset));
+
+    if has_audio {
+        if let Some(filter) = &loudness_filter {
+            cmd.arg("-af").arg(filter);
+        }
+        cmd.args(preset_audio_args(&preset));
+    }
+
+    cmd.args(preset_container_args(&preset));
+    cmd.arg("-y") // Overwrite output file
+        .arg(&options.output_path);
+
+    log::info!("Running FFmpeg command: {:?}", cmd);
+
+    let output_duration = match (options.trim_start, options.trim_end) {
+        (Some(start), Some(end)) if end > start => end - start,
+        _ => probe_video_metadata(&options.input_path).map(|probe| probe.duration).unwrap_or(0.0),
+    };
+    let progress = ExportProgressTracker::new(output_duration);
+    let _ = window.emit("merge-progress", progress.report(1, 1, "Exporting...".to_string(), 0.0));
+
+    // Execute FFmpeg
+    let export_result = run_ffmpeg_for_job(&mut cmd, Some(job), |position| {
+        let _ = window.emit("merge-progress", progress.report(1, 1, "Exporting...".to_string(), position));
+    });
+
+    if let Err(stderr) = export_result {
+        if job.is_cancelled() {
+            let _ = std::fs::remove_file(&options.output_path);
+            return Err(stderr);
+        }
+        log::error!("FFmpeg failed: {}", stderr);
+        return Err(format!("FFmpeg export failed: {}", stderr));
+    }
+
+    log::info!("Export successful: {}", options.output_path);
+    Ok(options.output_path)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TextOverlay {
+    text: String,
+    x_position: String,  // e.g., "10", "(w-text_w)/2", etc.
+    y_position: String,  // e.g., "10", "h-th-10", etc.
+    font_size: u32,
+    font_color: String,  // e.g., "white"
+    box_enabled: bool,
+    box_color: Option<String>,  // e.g., "black@0.5"
+    box_border_width: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClipSegment {
+    input_path: String,
+    trim_start: Option<f64>,
+    trim_end: Option<f64>,
+    audio_trim_start: Option<f64>,
+    audio_trim_end: Option<f64>,
+    is_video_muted: Option<bool>,
+    is_audio_muted: Option<bool>,
+    is_audio_linked: Option<bool>,
+    audio_offset: Option<f64>,
+    text_overlay: Option<TextOverlay>,
+    display_duration: Option<f64>,  // How long a still image is shown, in seconds
+    audio_stream_index: Option<u32>,          // Absolute index of the source audio stream to use (default: first)
+    audio_tracks: Option<Vec<AudioTrackMix>>, // Mix several source audio streams; overrides audio_stream_index
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AudioTrackMix {
+    stream_index: u32,     // Absolute stream index, as reported in AudioStreamInfo
+    gain_db: Option<f64>,  // Gain applied before mixing (default: 0 dB)
+}
+
+// Helper function to build a plain trimmed segment of a source, with no mutes or overlays
+fn clip_segment_for_range(input_path: &str, start: f64, end: f64) -> ClipSegment {
+    ClipSegment {
+        input_path: input_path.to_string(),
+        trim_start: Some(start),
+        trim_end: Some(end),
+        audio_trim_start: None,
+        audio_trim_end: None,
+        is_video_muted: None,
+        is_audio_muted: None,
+        is_audio_linked: None,
+        audio_offset: None,
+        text_overlay: None,
+        display_duration: None,
+        audio_stream_index: None,
+        audio_tracks: None,
+    }
+}
+
+// Helper function to resolve which source audio a clip uses
+// Returns the filter chain that prepares it (if any) and the label to read audio from
+fn clip_audio_source(clip: &ClipSegment, available: &[u32]) -> Result<(Option<String>, String), String> {
+    let requested: Vec<u32> = match (&clip.audio_tracks, clip.audio_stream_index) {
+        (Some(tracks), _) if !tracks.is_empty() => tracks.iter().map(|t| t.stream_index).collect(),
+        (_, Some(index)) => vec![index],
+        _ => return Ok((None, "[0:a]".to_string())),
+    };
+
+    if let Some(missing) = requested.iter().find(|index| !available.contains(index)) {
+        return Err(format!(
+            "Audio stream {} not found in {} (available audio streams: {:?})",
+            missing, clip.input_path, available
+        ));
+    }
+
+    let tracks = match &clip.audio_tracks {
+        Some(tracks) if !tracks.is_empty() => tracks,
+        _ => return Ok((None, format!("[0:{}]", requested[0]))),
+    };
+
+    // Apply per-track gain, then mix without amix's automatic 1/N attenuation
+    let mut chains: Vec<String> = tracks
+        .iter()
+        .enumerate()
+        .map(|(n, track)| format!("[0:{}]volume={}dB[srca{}]", track.stream_index, track.gain_db.unwrap_or(0.0), n))
+        .collect();
+
+    if tracks.len() == 1 {
+        return Ok((Some(chains.remove(0)), "[srca0]".to_string()));
+    }
+
+    let inputs: String = (0..tracks.len()).map(|n| format!("[srca{}]", n)).collect();
+    chains.push(format!("{}amix=inputs={}:duration=longest:normalize=0[srcmix]", inputs, tracks.len()));
+    Ok((Some(chains.join(";")), "[srcmix]".to_string()))
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MultiClipExportOptions {
+    clips: Vec<ClipSegment>,
+    output_path: String,
+    target_loudness: Option<f64>,  // Normalize each clip to this integrated loudness in LUFS, e.g. -14.0
+    preset: Option<ExportPreset>,  // Final encoding settings (default: the "H.264 High Quality" built-in)
+}
+
+// Most clips a multi-clip export renders at once, however many cores are available
+const MAX_PARALLEL_CLIP_RENDERS: usize = 8;
+
+// Helper function to decide how many clips render at once and how many threads each encode gets.
+// Each encode is itself multi-threaded, so the pool gets about half the cores (never more
+// workers than clips) and the cores are split between the workers
+fn clip_render_split(cores: usize, clips: usize) -> (usize, usize) {
+    let workers = (cores / 2).clamp(1, MAX_PARALLEL_CLIP_RENDERS).min(clips).max(1);
+    let threads_per_clip = (cores / workers).max(1);
+    (workers, threads_per_clip)
+}
+
+// Helper function to run `render` for clips 0..total on a pool of `workers` threads, handing out
+// clips in order. The first failure aborts the job, which kills the clips still rendering, and
+// stops further clips from starting; their errors are only the result of being killed, so just
+// the first one is returned
+fn render_clips_fail_fast<F>(total: usize, workers: usize, job: &ExportJob, render: F) -> Option<String>
+where
+    F: Fn(usize) -> Result<(), String> + Sync,
+{
+    let next_index = std::sync::atomic::AtomicUsize::new(0);
+    let failed = AtomicBool::new(false);
+    let failure: Mutex<Option<String>> = Mutex::new(None);
+
+    std::thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| loop {
+                if failed.load(Ordering::SeqCst) {
+                    break;
+                }
+                let i = next_index.fetch_add(1, Ordering::SeqCst);
+                if i >= total {
+                    break;
+                }
+
+                if let Err(e) = render(i) {
+                    if !failed.swap(true, Ordering::SeqCst) {
+                        *failure.lock().unwrap() = Some(e);
+                        job.abort();
+                    }
+                }
+            });
+        }
+    });
+
+    failure.into_inner().unwrap()
+}
+
+#[derive(Clone, serde::Serialize)]
+struct MergeProgress {
+    current: usize,
+    total: usize,
+    status: String,
+    percent: Option<f64>,      // Overall progress 0-100, once the output duration is known
+    speed: Option<f64>,        // Encode speed as a multiple of realtime
+    eta_seconds: Option<f64>,  // Estimated time remaining
+}
+
+// Turns FFmpeg output positions into overall percentage, speed and ETA for an export.
+// Work is counted in seconds of media encoded, summed over every FFmpeg pass of the export.
+struct ExportProgressTracker {
+    total_work: f64,
+    completed_work: f64,
+    started: std::time::Instant,
+}
+
+impl ExportProgressTracker {
+    fn new(total_work: f64) -> Self {
+        ExportProgressTracker { total_work, completed_work: 0.0, started: std::time::Instant::now() }
+    }
+
+    // Record a finished FFmpeg pass that encoded `seconds` of media
+    fn complete(&mut self, seconds: f64) {
+        self.completed_work += seconds;
+    }
+
+    // Build a progress event for the pass currently at `position` seconds of its output
+    fn report(&self, current: usize, total: usize, status: String, position: f64) -> MergeProgress {
+        if self.total_work <= 0.0 {
+            return MergeProgress { current, total, status, percent: None, speed: None, eta_seconds: None };
+        }
+
+        let done = (self.completed_work + position.max(0.0)).min(self.total_work);
+        let elapsed = self.started.elapsed().as_secs_f64();
+
+        // Speed and ETA need a little history before they mean anything
+        let speed = if elapsed >= 1.0 && done > 0.0 { Some(done / elapsed) } else { None };
+        let eta_seconds = speed.map(|speed| ((self.total_work - done) / speed).round());
+
+        MergeProgress {
+            current,
+            total,
+            status,
+            percent: Some(((done / self.total_work * 1000.0).round() / 10.0).min(100.0)),
+            speed: speed.map(|speed| (speed * 100.0).round() / 100.0),
+            eta_seconds,
+        }
+    }
+}
+
+// Helper function to estimate how long a clip will be once rendered, before rendering it
+// Follows the same rules as the render: stills use their display duration, trims win over the full file
+fn estimate_clip_duration(clip: &ClipSegment) -> f64 {
+    let trimmed = match (clip.trim_start, clip.trim_end) {
+        (Some(start), Some(end)) if end > start => Some(end - start),
+        _ => None,
+    };
+    let probe = probe_video_metadata(&clip.input_path).ok();
+
+    if probe.as_ref().map(is_still_image).unwrap_or(false) {
+        return clip.display_duration.or(trimmed).unwrap_or(DEFAULT_STILL_DURATION);
+    }
+    trimmed.or(probe.map(|probe| probe.duration)).unwrap_or(0.0)
+}
+
+#[tauri::command]
+fn export_multi_clip(options: MultiClipExportOptions, window: tauri::Window) -> Result<String, String> {
+    export_multi_clip_job(options, &window, &ExportJob::new())
+}
+
+// Run a multi-clip export as an export job (cancellable when started through start_multi_clip_export)
+// Temp clips are removed however the export ends, and a cancelled export also removes its partial output
+fn export_multi_clip_job(options: MultiClipExportOptions, window: &tauri::Window, job: &ExportJob) -> Result<String, String> {
+    let output_path = options.output_path.clone();
+    let result = render_multi_clip(options, window, job);
+
+    job.remove_temp_files();
+    if result.is_err() && job.is_cancelled() {
+        let _ = std::fs::remove_file(&output_path);
+    }
+
+    result
+}
+
+fn render_multi_clip(mut options: MultiClipExportOptions, window: &tauri::Window, job: &ExportJob) -> Result<String, String> {
+    log::info!("Starting multi-clip export {} with {} clips", job.id, options.clips.len());
+
+    if options.clips.is_empty() {
+        return Err("No clips to export".to_string());
+    }
+
+    // Preflight: report every missing source up front instead of failing partway through encoding
+    let missing = find_missing_clip_files(&options.clips);
+    if !missing.is_empty() {
+        let listing: Vec<String> = missing.iter()
+            .map(|m| format!("Clip {}: {}", m.clip_index + 1, m.path))
+            .collect();
+        log::error!("Export aborted, missing source files: {:?}", listing);
+        return Err(format!(
+            "{} source file(s) could not be found. The source videos may have been moved or deleted - relink them before exporting.\n{}",
+            missing.len(),
+            listing.join("\n")
+        ));
+    }
+
+    // Render VFR sources from their CFR working copies, which share the source's timeline
+    let cache = window.state::<MediaCache>();
+    for clip in options.clips.iter_mut() {
+        use_cfr_working_copy(clip, &cache);
+    }
+
+    if let Some(target) = options.target_loudness {
+        validate_target_loudness(target)?;
+    }
+
+    let preset = resolve_export_preset(options.preset.clone(), DEFAULT_MERGE_PRESET)?;
+    log::info!("Using export preset: {}", preset.name);
+
+    // Every clip is encoded twice: once to its temp file, then again by the concat
+    let clip_durations: Vec<f64> = options.clips.iter().map(estimate_clip_duration).collect();
+    let output_duration: f64 = clip_durations.iter().sum();
+    let progress = ExportProgressTracker::new(output_duration * 2.0);
+    log::info!("Estimated output duration: {:.3}s", output_duration);
+
+    // Emit initial progress
+    let _ = window.emit("merge-progress", progress.report(0, options.clips.len(), "Starting merge...".to_string(), 0.0));
+
+    // Process all clips through the same path to support text overlays and other features
+    // For multiple clips, we need to:
+    // 1. Export each trimmed clip to a temp file
+    // 2. Concat all temp files
+    // 3. Clean up temp files
+
+    let temp_dir = std::env::temp_dir();
+
+    // Step 1: Export each clip with trim applied and handle audio/video separation
+    // Clips are independent, so they render in parallel
+    let total = options.clips.len();
+    let cores = std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1);
+    let (workers, threads_per_clip) = clip_render_split(cores, total);
+    log::info!("Rendering {} clips with {} workers, {} threads each", total, workers, threads_per_clip);
+
+    // Temp names include the job ID so concurrent exports don't overwrite each other's clips
+    let (temp_extension, mut temp_codec_args) = temp_clip_format(&preset);
+    temp_codec_args.extend(["-threads".to_string(), threads_per_clip.to_string()]);
+    let temp_files: Vec<PathBuf> = (0..total)
+        .map(|i| temp_dir.join(format!("clipforge_temp_{}_{}.{}", job.id, i, temp_extension)))
+        .collect();
+    for temp_path in &temp_files {
+        job.track_temp_file(temp_path.clone());
+    }
+
+    // Progress counts finished clips plus how far each in-flight clip has got.
+    // Both locks are always taken in this order, and finishing a clip moves its work from
+    // clip_positions to the tracker under both, so progress never jumps back or double counts.
+    let progress = Mutex::new(progress);
+    let clip_positions = Mutex::new(vec![0.0; total]);
+    let finished = std::sync::atomic::AtomicUsize::new(0);
+    let emit_clip_progress = |i: usize, position: f64| {
+        let progress = progress.lock().unwrap();
+        let mut clip_positions = clip_positions.lock().unwrap();
+        clip_positions[i] = position;
+        let in_flight: f64 = clip_positions.iter().sum();
+        let status = format!("Processing clip {} of {}...", i + 1, total);
+        let _ = window.emit("merge-progress", progress.report(finished.load(Ordering::SeqCst), total, status, in_flight));
+    };
+
+    let failure = render_clips_fail_fast(total, workers, job, |i| {
+        emit_clip_progress(i, 0.0);
+        render_temp_clip(&options.clips[i], i, &temp_files[i], &temp_codec_args, job, |position| {
+            emit_clip_progress(i, position);
+        })?;
+
+        let mut progress = progress.lock().unwrap();
+        let mut clip_positions = clip_positions.lock().unwrap();
+        progress.complete(clip_durations[i]);
+        clip_positions[i] = 0.0;
+        finished.fetch_add(1, Ordering::SeqCst);
+        Ok(())
+    });
+
+    if let Some(e) = failure {
+        if job.is_cancelled() {
+            return Err(EXPORT_CANCELLED_ERROR.to_string());
+        }
+        for temp_file in &temp_files {
+            let _ = std::fs::remove_file(temp_file);
+        }
+        return Err(e);
+    }
+
+    let progress = progress.into_inner().unwrap();
+
+    // Emit progress for concatenation step
+    let _ = window.emit("merge-progress", progress.report(options.clips.len(), options.clips.len(), "Merging clips together...".to_string(), 0.0));
+
+    // Measure each rendered clip so the concat can bring them all to the same loudness
+    let mut clip_loudness_filters: Vec<Option<String>> = Vec::new();
+    if let Some(target) = options.target_loudness {
+        let _ = window.emit("merge-progress", progress.report(options.clips.len(), options.clips.len(), "Measuring loudness...".to_string(), 0.0));
+
+        for (i, temp_file) in temp_files.iter().enumerate() {
+            let measured = match analyze_loudness(&temp_file.to_string_lossy(), None, Some(target), Some(job)) {
+                Ok(measured) => measured,
+                Err(e) if job.is_cancelled() => return Err(e),
+                Err(e) => {
+                    for temp_file in &temp_files {
+                        let _ = std::fs::remove_file(temp_file);
+                    }
+                    return Err(format!("Failed to measure loudness of clip {}: {}", i + 1, e));
+                }
+            };
+            log::info!("Clip {} loudness: {:?}", i, measured);
+            clip_loudness_filters.push(loudnorm_filter(target, &measured));
+        }
+    }
+
+    // Step 2: Probe all temp files to get their displayed resolutions
+    let mut clip_dimensions: Vec<(u32, u32)> = Vec::new();
+
+    for temp_file in &temp_files {
+        match probe_video_metadata(&temp_file.to_string_lossy()) {
+            Ok(probe) => {
+                if let Some(dimensions) = probe.display_width.zip(probe.display_height) {
+                    clip_dimensions.push(dimensions);
+                }
+            }
+            Err(e) => log::warn!("Failed to probe temp file {:?}: {}", temp_file, e),
+        }
+    }
+
+    let (max_width, max_height) = preset_canvas_size(&preset, concat_canvas_size(&clip_dimensions));
+
+    log::info!("Target resolution for concat: {}x{}", max_width, max_height);
+
+    // Step 3: Concat using filter_complex with scaling/padding for different resolutions
+    let ffmpeg = find_ffmpeg();
+    let mut concat_cmd = Command::new(&ffmpeg);
+
+    // Add all temp files as inputs
+    for temp_file in &temp_files {
+        concat_cmd.arg("-i").arg(temp_file);
+    }
+
+    // Build filter_complex for concatenation with scale and pad
+    let num_clips = temp_files.len();
+    let mut filter_str = String::new();
+
+    // Scale and pad each input to the target resolution
+    for i in 0..num_clips {
+        filter_str.push_str(&format!(
+            "[{}:v]scale=w={}:h={}:force_original_aspect_ratio=decrease,pad={}:{}:(ow-iw)/2:(oh-ih)/2:black[v{}];",
+            i, max_width, max_height, max_width, max_height, i
+        ));
+    }
+
+    // Normalize each clip's loudness (second loudnorm pass); silent clips pass through
+    for (i, loudness_filter) in clip_loudness_filters.iter().enumerate() {
+        match loudness_filter {
+            Some(filter) => filter_str.push_str(&format!("[{}:a]{}[a{}];", i, filter, i)),
+            None => filter_str.push_str(&format!("[{}:a]aresample={}[a{}];", i, LOUDNORM_OUTPUT_SAMPLE_RATE, i)),
+        }
+    }
+
+    // Concatenate the normalized videos with their audio
+    for i in 0..num_clips {
+        if clip_loudness_filters.is_empty() {
+            filter_str.push_str(&format!("[v{}][{}:a]", i, i));
+        } else {
+            filter_str.push_str(&format!("[v{}][a{}]", i, i));
+        }
+    }
+    filter_str.push_str(&format!("concat=n={}:v=1:a=1[outv][outa]", num_clips));
+
+    concat_cmd
+        .arg("-filter_complex").arg(&filter_str)
+        .arg("-map").arg("[outv]")
+        .arg("-map").arg("[outa]")
+        // Re-encode to fix timestamp discontinuities between clips
+        .args(preset_video_args(&preset))
+        .args(preset_audio_args(&preset))
+        .args(preset_container_args(&preset))
+        .arg("-y")
+        .arg(&options.output_path);
+
+    log::info!("Running concat command: {:?}", concat_cmd);
+
+    let concat_result = run_ffmpeg_for_job(&mut concat_cmd, Some(job), |position| {
+        let _ = window.emit("merge-progress", progress.report(num_clips, num_clips, "Merging clips together...".to_string(), position));
+    });
+
+    // Clean up temp files
+    for temp_file in &temp_files {
+        let _ = std::fs::remove_file(temp_file);
+    }
+
+    if let Err(stderr) = concat_result {
+        if job.is_cancelled() {
+            return Err(stderr);
+        }
+        log::error!("FFmpeg concat failed: {}", stderr);
+        return Err(format!("FFmpeg concat failed: {}", stderr));
+    }
+
+    log::info!("Multi-clip export successful: {}", options.output_path);
+    Ok(options.output_path)
+}
+
+// Helper function to render one clip of a multi-clip export to its temp file, with trims, mutes,
+// audio offset and text overlay applied. Errors are already worded for the user.
+fn render_temp_clip<F: FnMut(f64)>(
+    clip: &ClipSegment,
+    i: usize,
+    temp_path: &std::path::Path,
+    codec_args: &[String],
+    job: &ExportJob,
+    on_progress: F,
+) -> Result<(), String> {
+    log::info!("Exporting clip {} to temp file: {:?}", i, temp_path);
+
+    // Round trim values to 3 decimal places to avoid ffmpeg precision issues
+    let trim_start = clip.trim_start.map(round_to_millis);
+    let trim_end = clip.trim_end.map(round_to_millis);
+    let audio_trim_start = clip.audio_trim_start.or(trim_start).map(round_to_millis);
+    let audio_trim_end = clip.audio_trim_end.or(trim_end).map(round_to_millis);
+
+    // Check if input has an audio stream
+    let ffprobe = find_ffprobe();
+    let probe_output = Command::new(&ffprobe)
+        .args(&[
+            "-v", "quiet",
+            "-print_format", "json",
+            "-show_streams",
+            "-select_streams", "a",
+            &clip.input_path
+        ])
+        .output()
+        .map_err(|e| format!("Failed to probe clip {}: {}", i, e))?;
+
+    let json_str = String::from_utf8_lossy(&probe_output.stdout);
+    let json: serde_json::Value = serde_json::from_str(&json_str)
+        .unwrap_or(serde_json::json!({"streams": []}));
+
+    let audio_indexes: Vec<u32> = json["streams"].as_array()
+        .map(|arr| arr.iter().filter_map(|s| s["index"].as_u64()).map(|idx| idx as u32).collect())
+        .unwrap_or_default();
+    let has_audio = !audio_indexes.is_empty();
+
+    log::info!("Clip {} has audio: {} (streams: {:?})", i, has_audio, audio_indexes);
+
+    // Pick the source audio stream(s); a mix gets its own chain ahead of the [a] chain
+    let (audio_source_chain, audio_in) = if has_audio {
+        clip_audio_source(clip, &audio_indexes).map_err(|e| format!("Clip {}: {}", i, e))?
+    } else {
+        (None, "[0:a]".to_string())
+    };
+    if audio_source_chain.is_some() || clip.audio_stream_index.is_some() {
+        log::info!("Clip {} using source audio {}", i, audio_in);
+    }
+
+    // Check if input has a video stream; audio-only clips (voiceovers, music) get a black
+    // video rendered under them. If probing fails, assume video and let FFmpeg report errors.
+    let source_probe = probe_video_metadata(&clip.input_path).ok();
+    let has_video = source_probe.as_ref().map(|probe| probe.has_video).unwrap_or(true);
+
+    log::info!("Clip {} has video: {}", i, has_video);
+
+    // Stills are looped for their display duration instead of being trimmed
+    let is_still = source_probe.as_ref().map(is_still_image).unwrap_or(false);
+    let still_duration = if is_still {
+        let from_trim = match (trim_start, trim_end) {
+            (Some(start), Some(end)) if end > start => Some(end - start),
+            _ => None,
+        };
+        let seconds = clip.display_duration.or(from_trim).unwrap_or(DEFAULT_STILL_DURATION);
+        log::info!("Clip {} is a still image, showing for {}s", i, seconds);
+        Some(round_to_millis(seconds))
+    } else {
+        None
+    };
+
+    let ffmpeg = find_ffmpeg();
+    let mut cmd = Command::new(&ffmpeg);
+    if let Some(seconds) = still_duration {
+        cmd.arg("-loop").arg("1")
+            .arg("-framerate").arg("30")
+            .arg("-t").arg(seconds.to_string());
+    }
+    cmd.arg("-i").arg(&clip.input_path);
+
+    let is_video_muted = clip.is_video_muted.unwrap_or(false);
+    let is_audio_muted = clip.is_audio_muted.unwrap_or(false);
+    let is_audio_linked = clip.is_audio_linked.unwrap_or(true);
+    let audio_offset = clip.audio_offset.unwrap_or(0.0);
+
+    // Check if audio trim is different from video trim (stills have no audio to trim)
+    let has_independent_audio_trim = !is_still && (audio_trim_start != trim_start || audio_trim_end != trim_end);
+
+    // Add trim parameters for video (if audio trim is independent, we'll handle it with filters)
+    if !has_independent_audio_trim && !is_still {
+        // Audio and video trim are the same, apply trim to entire file
+        if let (Some(start), Some(end)) = (trim_start, trim_end) {
+            let raw_duration = end - start;
+            // Round duration to avoid floating point precision issues
+            let duration = round_to_millis(raw_duration);
+            cmd.arg("-ss").arg(start.to_string());
+            cmd.arg("-t").arg(duration.to_string());
+        }
+    }
+
+    // Calculate clip duration for generating placeholders (already rounded from trim values)
+    let duration = if let Some(seconds) = still_duration {
+        seconds
+    } else if let (Some(start), Some(end)) = (trim_start, trim_end) {
+        let raw_duration = end - start;
+        // Round duration to avoid floating point precision issues
+        let rounded = round_to_millis(raw_duration);
+        log::info!("Clip {} duration: raw={}, start={}, end={}, rounded={}", i, raw_duration, start, end, rounded);
+        rounded
+    } else {
+        // Use ffprobe to get duration if not trimmed
+        let ffprobe = find_ffprobe();
+        let probe_output = Command::new(&ffprobe)
+            .arg("-v").arg("error")
+            .arg("-show_entries").arg("format=duration")
+            .arg("-of").arg("default=noprint_wrappers=1:nokey=1")
+            .arg(&clip.input_path)
+            .output()
+            .map_err(|e| format!("Failed to probe clip {}: {}", i, e))?;
+
+        let parsed_duration = String::from_utf8_lossy(&probe_output.stdout)
+            .trim()
+            .parse::<f64>()
+            .unwrap_or(0.0);
+        let rounded = round_to_millis(parsed_duration);
+        log::info!("Clip {} duration from ffprobe: raw={}, rounded={}", i, parsed_duration, rounded);
+        rounded
+    };
+
+    // Build filter complex for handling muted tracks, audio offset, and independent audio trim
+    let mut filter_parts = Vec::new();
+    let mut needs_filter = has_independent_audio_trim;
+
+    // Handle video
+    // FFmpeg autorotates inputs while decoding, so [0:v] is already in displayed orientation.
+    // Placeholders for muted video must match that orientation, not the coded one.
+    let mut video_filter = if is_video_muted || !has_video {
+        needs_filter = true;
+        let (placeholder_width, placeholder_height) = source_probe
+            .as_ref()
+            .and_then(|probe| probe.display_width.zip(probe.display_height))
+            .unwrap_or((1920, 1080));
+        // Round up to even dimensions for yuv420p (stills can have odd sizes)
+        let placeholder_width = placeholder_width + placeholder_width % 2;
+        let placeholder_height = placeholder_height + placeholder_height % 2;
+        let duration_str = format!("{:.3}", duration);
+        format!("color=c=black:s={}x{}:d={},fps=30", placeholder_width, placeholder_height, duration_str)
+    } else if is_still {
+        // Looped stills may be RGBA or odd-sized; normalize so libx264 accepts them
+        needs_filter = true;
+        "[0:v]scale=trunc(iw/2)*2:trunc(ih/2)*2,format=yuv420p".to_string()
+    } else if has_independent_audio_trim {
+        // Apply video trim using filter
+        needs_filter = true;
+        if let (Some(start), Some(end)) = (trim_start, trim_end) {
+            format!("[0:v]trim=start={}:end={},setpts=PTS-STARTPTS", start, end)
+        } else {
+            "[0:v]null".to_string()
+        }
+    } else {
+        "[0:v]null".to_string()
+    };
+
+    // Add text overlay if present
+    if let Some(overlay) = &clip.text_overlay {
+        needs_filter = true;
+        let escaped_text = escape_ffmpeg_text(&overlay.text);
+
+        // Use system font (Helvetica on macOS)
+        let font_path = "/System/Library/Fonts/Supplemental/Arial.ttf";
+
+        let mut drawtext_params = format!(
+            "drawtext=text='{}':fontfile={}:fontsize={}:fontcolor={}:x={}:y={}",
+            escaped_text,
+            font_path,
+            overlay.font_size,
+            overlay.font_color,
+            overlay.x_position,
+            overlay.y_position
+        );
+
+        // Add box if enabled
+        if overlay.box_enabled {
+            drawtext_params.push_str(":box=1");
+            if let Some(ref box_color) = overlay.box_color {
+                drawtext_params.push_str(&format!(":boxcolor={}", box_color));
+            }
+            if let Some(border_width) = overlay.box_border_width {
+                drawtext_params.push_str(&format!(":boxborderw={}", border_width));
+            }
+        }
+
+        video_filter.push_str(&format!(",{}", drawtext_params));
+    }
+
+    video_filter.push_str("[v]");
+    filter_parts.push(video_filter);
+
+    // Handle audio
+    let audio_filter = if !has_audio || is_audio_muted {
+        // No audio stream or audio is muted - generate silent audio
+        // Don't specify duration - let -shortest flag stop when video ends
+        needs_filter = true;
+        log::info!("Clip {} generating silent audio (infinite, will stop with -shortest)", i);
+        "anullsrc=channel_layout=stereo:sample_rate=44100[a]".to_string()
+    } else if has_independent_audio_trim {
+        // Apply audio-specific trim
+        needs_filter = true;
+        if let (Some(start), Some(end)) = (audio_trim_start, audio_trim_end) {
+            let mut audio_chain = format!("{}atrim=start={}:end={},asetpts=PTS-STARTPTS", audio_in, start, end);
+
+            // Add audio offset if needed
+            if !is_audio_linked && audio_offset != 0.0 {
+                let delay_ms = (audio_offset * 1000.0).round() as i64;
+                if delay_ms > 0 {
+                    audio_chain.push_str(&format!(",adelay={}|{}", delay_ms, delay_ms));
+                }
+            }
+
+            audio_chain.push_str("[a]");
+            audio_chain
+        } else {
+            // If no trim specified, still use audio but ensure we're using filter mode
+            needs_filter = true;
+            format!("{}anull[a]", audio_in)
+        }
+    } else if !is_audio_linked && audio_offset != 0.0 {
+        needs_filter = true;
+        let delay_ms = (audio_offset * 1000.0).round() as i64;
+        if delay_ms > 0 {
+            format!("{}adelay={}|{}[a]", audio_in, delay_ms, delay_ms)
+        } else if delay_ms < 0 {
+            format!("{}atrim=start={}[a]", audio_in, -audio_offset)
+        } else {
+            format!("{}anull[a]", audio_in)
+        }
+    } else if has_audio {
+        // Has audio but no special processing - use pass-through filter for consistency
+        needs_filter = true;
+        format!("{}anull[a]", audio_in)
+    } else {
+        // Fallback: generate silent audio
+        // Don't specify duration - let -shortest flag stop when video ends
+        needs_filter = true;
+        "anullsrc=channel_layout=stereo:sample_rate=44100[a]".to_string()
+    };
+    if let Some(chain) = audio_source_chain.filter(|_| has_audio && !is_audio_muted) {
+        needs_filter = true;
+        filter_parts.push(chain);
+    }
+    filter_parts.push(audio_filter);
+
+    // Add filter_complex and map outputs
+    if needs_filter || is_video_muted || !has_video || is_audio_muted || (!is_audio_linked && audio_offset != 0.0) || !has_audio {
+        let filter_str = filter_parts.join(";");
+        cmd.arg("-filter_complex").arg(&filter_str);
+        cmd.arg("-map").arg("[v]");
+        cmd.arg("-map").arg("[a]");
+    } else {
+        // No special processing needed, use default mapping
+        cmd.arg("-map").arg("0:v");
+        if has_audio {
+            let audio_map = clip.audio_stream_index.map(|idx| format!("0:{}", idx));
+            cmd.arg("-map").arg(audio_map.as_deref().unwrap_or("0:a"));
+        }
+    }
+
+    // Output options - re-encode to ensure compatibility (see temp_clip_format)
+    cmd.args(codec_args)
+        .arg("-shortest")  // Stop when the shortest stream ends (prevents audio/video sync issues)
+        .arg("-y")
+        .arg(temp_path);
+
+    log::info!("FFmpeg command: {:?}", cmd);
+
+    let clip_result = run_ffmpeg_for_job(&mut cmd, Some(job), on_progress);
+
+    if let Err(stderr) = clip_result {
+        // Killed because the export was cancelled or another clip failed; nothing to explain
+        if job.is_cancelled() || job.is_aborted() {
+            return Err(stderr);
+        }
+        log::error!("FFmpeg failed for clip {}: {}", i, stderr);
+
+        // Parse error and provide user-friendly message
+        let error_msg = if stderr.contains("Could not open encoder before EOF") || stderr.contains("Invalid argument") {
+            format!("Failed to process clip {} due to audio/video sync issues. This can happen with very short clips or corrupted files. Try adjusting the clip boundaries slightly.", i + 1)
+        } else if stderr.contains("No such file or directory") {
+            format!("Clip {} file not found. The source video may have been moved or deleted.", i + 1)
+        } else if stderr.contains("Invalid data found") {
+            format!("Clip {} appears to be corrupted or in an unsupported format.", i + 1)
+        } else if stderr.contains("Permission denied") {
+            format!("Permission denied while processing clip {}. Check file permissions.", i + 1)
+        } else {
+            // Extract just the key error lines instead of dumping everything
+            let key_errors: Vec<&str> = stderr.lines()
+                .filter(|line| line.contains("Error") || line.contains("failed") || line.contains("Invalid"))
+                .take(3)  // Only show first 3 error lines
+                .collect();
+
+            if !key_errors.is_empty() {
+                format!("Failed to process clip {}: {}", i + 1, key_errors.join("; "))
+            } else {
+                format!("Failed to process clip {}. Check the logs for details.", i + 1)
+            }
+        };
+
+        return Err(error_msg);
+    }
+
+    Ok(())
+}
+
+// Helper function to pick the concat canvas for clips of mixed size and orientation
+// The canvas follows the first clip's orientation and is large enough for every clip's long
+// and short edge, so portrait phone clips are not padded into a square canvas
+fn concat_canvas_size(dimensions: &[(u32, u32)]) -> (u32, u32) {
+    let (first_width, first_height) = match dimensions.first() {
+        Some(first) => *first,
+        None => return (1920, 1080),
+    };
+
+    let long_edge = dimensions.iter().map(|(w, h)| *w.max(h)).max().unwrap_or(1920);
+    let short_edge = dimensions.iter().map(|(w, h)| *w.min(h)).max().unwrap_or(1080);
+
+    // libx264 with yuv420p needs even dimensions
+    let long_edge = long_edge + long_edge % 2;
+    let short_edge = short_edge + short_edge % 2;
+
+    if first_height > first_width {
+        (short_edge, long_edge)
+    } else {
+        (long_edge, short_edge)
+    }
+}
+
+// Recording state management
+struct RecordingState {
+    process: Mutex<Option<Child>>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AudioDevice {
+    id: String,
+    name: String,
+    #[serde(rename = "type")]
+    device_type: String, // "input", "output", "virtual"
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AudioSettings {
+    microphone_enabled: bool,
+    microphone_device: String,
+    system_audio_enabled: bool,
+    system_audio_device: String,
+    audio_quality: String, // "voice", "standard", "high"
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StartRecordingOptions {
+    mode: String, // "screen", "webcam", or "combo"
+    output_path: String,
+    audio_settings: Option<AudioSettings>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TestSettings {
+    use_wallclock_as_timestamps: bool,
+    audio_filter: String,
+    rtbufsize: String,
+    thread_queue_size: i32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StartTestRecordingOptions {
+    mode: String,
+    output_path: String,
+    audio_settings: Option<AudioSettings>,
+    test_settings: TestSettings,
+}
+
+#[tauri::command]
+fn list_audio_devices() -> Result<Vec<AudioDevice>, String> {
+    log::info!("Listing audio devices");
+
+    #[cfg(target_os = "macos")]
+    {
+        // Use FFmpeg to list AVFoundation devices
+        let ffmpeg = find_ffmpeg();
+        let output = Command::new(&ffmpeg)
+            .arg("-f").arg("avfoundation")
+            .arg("-list_devices").arg("true")
+            .arg("-i").arg("")
+            .output()
+            .map_err(|e| format!("Failed to list devices: {}", e))?;
+
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let mut devices = Vec::new();
+
+        // Parse FFmpeg output for audio devices
+        // Example: [AVFoundation indev @ ...] [1] Built-in Microphone
+        let mut in_audio_section = false;
+        for line in stderr.lines() {
+            if line.contains("AVFoundation audio devices:") {
+                in_audio_section = true;
+                continue;
+            }
+            if line.contains("AVFoundation video devices:") {
+                in_audio_section = false;
+            }
+
+            if in_audio_section {
+                // Look for lines with device format: [N] Device Name
+                // Skip lines that contain "AVFoundation" or "error"
+                if line.contains("AVFoundation") || line.to_lowercase().contains("error") {
+                    continue;
+                }
+
+                // Find the last '[' and ']' pair (device ID)
+                if let Some(bracket_start) = line.rfind('[') {
+                    if let Some(bracket_end) = line.rfind(']') {
+                        if bracket_end > bracket_start + 1 && bracket_start > 5 {
+                            let id = &line[bracket_start + 1..bracket_end];
+                            let name = line[bracket_end + 1..].trim();
+
+                            // Validate: ID should be a number, name should not be empty
+                            if id.parse::<u32>().is_ok() && !name.is_empty() {
+                                // Detect virtual audio devices
+                                let device_type = if name.to_lowercase().contains("blackhole")
+                                    || name.to_lowercase().contains("soundflower")
+                                    || name.to_lowercase().contains("loopback") {
+                                    "virtual"
+                                } else {
+                                    "input"
+                                };
+
+                                devices.push(AudioDevice {
+                                    id: id.to_string(),
+                                    name: name.to_string(),
+                                    device_type: device_type.to_string(),
+                                });
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        // Always include a default device if none found
+        if devices.is_empty() {
+            devices.push(AudioDevice {
+                id: "0".to_string(),
+                name: "Default Microphone".to_string(),
+                device_type: "input".to_string(),
+            });
+        }
+
+        log::info!("Found {} audio devices", devices.len());
+        Ok(devices)
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    {
+        // Fallback for other platforms
+        Ok(vec![
+            AudioDevice {
+                id: "0".to_string(),
+                name: "Default Microphone".to_string(),
+                device_type: "input".to_string(),
+            }
+        ])
+    }
+}
+
+#[tauri::command]
+fn start_recording(options: StartRecordingOptions, state: State<RecordingState>) -> Result<String, String> {
+    log::info!("Starting recording: {:?}", options);
+
+    let mut recording_process = state.process.lock().unwrap();
+    if recording_process.is_some() {
+        return Err("Recording already in progress".to_string());
+    }
+
+    // Build FFmpeg command based on platform and mode
+    let ffmpeg = find_ffmpeg();
+    let mut cmd = Command::new(&ffmpeg);
+
+    // Add global sync and buffer parameters for all platforms
+    // These ensure audio/video stay synchronized during capture
+    cmd.arg("-loglevel").arg("verbose")  // Verbose logging for diagnostics
+        .arg("-rtbufsize").arg("200M");  // Larger realtime buffer to prevent audio drops
+
+    #[cfg(target_os = "macos")]
+    {
+        // Get audio settings or use defaults
+        let audio_settings = options.audio_settings.as_ref();
+        let mic_enabled = audio_settings.map(|s| s.microphone_enabled).unwrap_or(true);
+        let mic_device = audio_settings
+            .and_then(|s| {
+                if s.microphone_device == "default" {
+                    Some("0")
+                } else {
+                    Some(s.microphone_device.as_str())
+                }
+            })
+            .unwrap_or("0");
+
+        // Only enable system audio if enabled AND a valid device is selected
+        let sys_audio_enabled = audio_settings
+            .map(|s| {
+                s.system_audio_enabled
+                && !s.system_audio_device.is_empty()
+                && s.system_audio_device != "none"
+            })
+            .unwrap_or(false);
+
+        match options.mode.as_str() {
+            "screen" => {
+                cmd.arg("-f").arg("avfoundation")
+                    .arg("-capture_cursor").arg("1")
+                    .arg("-framerate").arg("15")
+                    // Use wallclock timestamps for consistent timing across inputs
+                    .arg("-use_wallclock_as_timestamps").arg("1");
+
+                // Handle dual audio (mic + system) vs single audio source
+                if mic_enabled && sys_audio_enabled {
+                    // Dual audio: Capture screen video + mic + system audio separately, then mix
+                    let sys_audio_device = audio_settings
+                        .and_then(|s| {
+                            if s.system_audio_device == "none" || s.system_audio_device.is_empty() {
+                                None
+                            } else {
+                                Some(s.system_audio_device.as_str())
+                            }
+                        })
+                        .unwrap_or("1"); // Default to device 1 for system audio
+
+                    // Screen video with no audio first
+                    cmd.arg("-i").arg("1:none");
+
+                    // Microphone audio input
+                    cmd.arg("-probesize").arg("100M")  // Large probe buffer for stable detection
+                        .arg("-rtbufsize").arg("200M")  // Large realtime buffer to prevent drops
+                        .arg("-f").arg("avfoundation")
+                        // Use device timestamps for audio (not wallclock - prevents choppiness)
+                        .arg("-thread_queue_size").arg("8192")  // Very large queue for smooth audio
+                        .arg("-i").arg(&format!(":{}", mic_device));
+
+                    // System audio input (BlackHole or similar)
+                    cmd.arg("-probesize").arg("100M")  // Large probe buffer for stable detection
+                        .arg("-rtbufsize").arg("200M")  // Large realtime buffer to prevent drops
+                        .arg("-f").arg("avfoundation")
+                        // Use device timestamps for audio (not wallclock - prevents choppiness)
+                        .arg("-thread_queue_size").arg("8192")  // Very large queue for smooth audio
+                        .arg("-i").arg(&format!(":{}", sys_audio_device));
+                } else if mic_enabled {
+                    // Just microphone
+                    cmd.arg("-i").arg(&format!("1:{}", mic_device));
+                } else if sys_audio_enabled {
+                    // Just system audio
+                    let sys_audio_device = audio_settings
+                        .and_then(|s| {
+                            if s.system_audio_device == "none" || s.system_audio_device.is_empty() {
+                                None
+                            } else {
+                                Some(s.system_audio_device.as_str())
+                            }
+                        })
+                        .unwrap_or("1");
+                    cmd.arg("-i").arg(&format!("1:{}", sys_audio_device));
+                } else {
+                    // No audio
+                    cmd.arg("-i").arg("1:none");
+                }
+            },
+            "webcam" => {
+                // For webcam, capture video device 0 with audio
+                if mic_enabled {
+                    // Capture webcam with microphone audio
+                    cmd.arg("-f").arg("avfoundation")
+                        .arg("-framerate").arg("30")
+                        // Use wallclock timestamps for consistent timing
+                        .arg("-use_wallclock_as_timestamps").arg("1")
+                        .arg("-i").arg(&format!("0:{}", mic_device));
+
+                    // Explicitly map video and audio streams
+                    cmd.arg("-map").arg("0:v")  // Video from webcam
+                        .arg("-map").arg("0:a"); // Audio from microphone
+                } else {
+                    // Capture webcam without audio
+                    cmd.arg("-f").arg("avfoundation")
+                        .arg("-framerate").arg("30")
+                        // Use wallclock timestamps for consistent timing
+                        .arg("-use_wallclock_as_timestamps").arg("1")
+                        .arg("-i").arg("0:none");
+                }
+            },
+            "combo" => {
+                // For combo mode: capture screen + webcam, then overlay webcam on screen
+
+                // Input 0: Screen video (no audio attached to avoid conflicts)
+                // Increased thread_queue_size for better buffering and sync (was 512)
+                cmd.arg("-thread_queue_size").arg("2048")
+                    .arg("-f").arg("avfoundation")
+                    .arg("-capture_cursor").arg("1")
+                    .arg("-framerate").arg("30")
+                    // Use wallclock timestamps for consistent timing across all inputs
+                    .arg("-use_wallclock_as_timestamps").arg("1")
+                    .arg("-i").arg("1:none");
+
+                // Input 1: Webcam video (no audio, we'll handle audio separately)
+                // Increased thread_queue_size for smooth capture
+                cmd.arg("-thread_queue_size").arg("2048")
+                    .arg("-f").arg("avfoundation")
+                    .arg("-framerate").arg("30")
+                    // Use wallclock timestamps for consistent timing
+                    .arg("-use_wallclock_as_timestamps").arg("1")
+                    .arg("-i").arg("0:none");
+
+                // Handle audio inputs separately (similar to screen mode dual audio)
+                if mic_enabled && sys_audio_enabled {
+                    let sys_audio_device = audio_settings
+                        .and_then(|s| {
+                            if s.system_audio_device == "none" || s.system_audio_device.is_empty() {
+                                None
+                            } else {
+                                Some(s.system_audio_device.as_str())
+                            }
+                        })
+                        .unwrap_or("1");
+
+                    // Input 2: Microphone audio
+                    cmd.arg("-probesize").arg("100M")  // Large probe buffer for stable detection
+                        .arg("-rtbufsize").arg("200M")  // Large realtime buffer to prevent drops
+                        .arg("-thread_queue_size").arg("8192")
+                        .arg("-f").arg("avfoundation")
+                        // Use device timestamps for audio (not wallclock - prevents choppiness)
+                        .arg("-i").arg(&format!(":{}", mic_device));
+
+                    // Input 3: System audio
+                    cmd.arg("-probesize").arg("100M")  // Large probe buffer for stable detection
+                        .arg("-rtbufsize").arg("200M")  // Large realtime buffer to prevent drops
+                        .arg("-thread_queue_size").arg("8192")
+                        .arg("-f").arg("avfoundation")
+                        // Use device timestamps for audio (not wallclock - prevents choppiness)
+                        .arg("-i").arg(&format!(":{}", sys_audio_device));
+                } else if mic_enabled {
+                    // Input 2: Just microphone
+                    cmd.arg("-probesize").arg("100M")  // Large probe buffer for stable detection
+                        .arg("-rtbufsize").arg("200M")  // Large realtime buffer to prevent drops
+                        .arg("-thread_queue_size").arg("8192")
+                        .arg("-f").arg("avfoundation")
+                        // Use device timestamps for audio (not wallclock - prevents choppiness)
+                        .arg("-i").arg(&format!(":{}", mic_device));
+                } else if sys_audio_enabled {
+                    // Input 2: Just system audio
+                    let sys_audio_device = audio_settings
+                        .and_then(|s| {
+                            if s.system_audio_device == "none" || s.system_audio_device.is_empty() {
+                                None
+                            } else {
+                                Some(s.system_audio_device.as_str())
+                            }
+                        })
+                        .unwrap_or("1");
+                    cmd.arg("-probesize").arg("100M")  // Large probe buffer for stable detection
+                        .arg("-rtbufsize").arg("200M")  // Large realtime buffer to prevent drops
+                        .arg("-thread_queue_size").arg("8192")
+                        .arg("-f").arg("avfoundation")
+                        // Use device timestamps for audio (not wallclock - prevents choppiness)
+                        .arg("-i").arg(&format!(":{}", sys_audio_device));
+                }
+            },
+            _ => return Err("Invalid recording mode".to_string()),
+        }
+    }
+
+    #[cfg(target_os = "windows")]
+    {
+        match options.mode.as_str() {
+            "screen" => {
+                cmd.arg("-f").arg("gdigrab")
+                    .arg("-i").arg("desktop")
+                    .arg("-r").arg("30");
+            },
+            "webcam" => {
+                cmd.arg("-f").arg("dshow")
+                    .arg("-i").arg("video=") // User needs to specify device
+                    .arg("-r").arg("30");
+            },
+            _ => return Err("Invalid recording mode".to_string()),
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        match options.mode.as_str() {
+            "screen" => {
+                cmd.arg("-f").arg("x11grab")
+                    .arg("-i").arg(":0.0")
+                    .arg("-r").arg("30");
+            },
+            "webcam" => {
+                cmd.arg("-f").arg("v4l2")
+                    .arg("-i").arg("/dev/video0")
+                    .arg("-r").arg("30");
+            },
+            _ => return Err("Invalid recording mode".to_string()),
+        }
+    }
+
+    // Output settings: H.264/MP4 optimized for smooth browser playback
+
+    // Video filtering depends on mode:
+    // - For screen/webcam: simple scale filter
+    // - For combo: overlay webcam on screen (picture-in-picture)
+    #[cfg(target_os = "macos")]
+    let is_combo_mode = options.mode == "combo";
+
+    #[cfg(not(target_os = "macos"))]
+    let is_combo_mode = false;
+
+    if !is_combo_mode {
+        // Simple scale for screen and webcam modes - 720p to reduce CPU load
+        cmd.arg("-vf").arg("scale=1280:720:force_original_aspect_ratio=decrease:force_divisible_by=2");
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        // Use hardware encoding on macOS (VideoToolbox) - offloads video to GPU
+        cmd.arg("-c:v").arg("h264_videotoolbox")
+            .arg("-b:v").arg("5000k") // 5 Mbps bitrate for good quality
+            .arg("-allow_sw").arg("1") // Fallback to software if hardware unavailable
+            .arg("-require_sw").arg("0"); // Prefer hardware encoding
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    {
+        // Software encoding for non-macOS platforms
+        cmd.arg("-c:v").arg("libx264")
+            .arg("-preset").arg("ultrafast")
+            .arg("-crf").arg("23");
+    }
+
+    cmd
+        .arg("-pix_fmt").arg("yuv420p") // Standard pixel format
+        // CRITICAL: Force constant frame rate at 15fps
+        .arg("-r").arg("15")
+        .arg("-vsync").arg("cfr")
+        .arg("-g").arg("15") // GOP size = framerate for consistent keyframes
+        .arg("-bf").arg("0") // No B-frames for simpler decode
+        // Web-optimized MP4 container
+        .arg("-movflags").arg("+faststart") // Move moov atom to beginning for web streaming
+        .arg("-video_track_timescale").arg("90000"); // Standard MPEG timescale
+
+    // Add audio encoding based on audio settings
+    let audio_settings = options.audio_settings.as_ref();
+    let mic_enabled = audio_settings.map(|s| s.microphone_enabled).unwrap_or(true);
+
+    // Only enable system audio if enabled AND a valid device is selected
+    let sys_audio_enabled = audio_settings
+        .map(|s| {
+            s.system_audio_enabled
+            && !s.system_audio_device.is_empty()
+            && s.system_audio_device != "none"
+        })
+        .unwrap_or(false);
+
+    let has_audio = mic_enabled || sys_audio_enabled;
+
+    if has_audio {
+        let audio_quality = audio_settings
+            .map(|s| s.audio_quality.as_str())
+            .unwrap_or("standard");
+
+        // Set bitrate based on quality (increased for better audio quality and less crackling)
+        let bitrate = match audio_quality {
+            "voice" => "96k",     // Increased from 64k
+            "standard" => "192k", // Increased from 128k (test 4 winner!)
+            "high" => "256k",     // Unchanged
+            _ => "192k",          // Default to standard
+        };
+
+        // Check if we're mixing dual audio (mic + system) or doing video overlay (combo mode)
+        #[cfg(target_os = "macos")]
+        if options.mode == "combo" {
+            // Combo mode: overlay webcam on screen + handle audio
+            // Inputs: 0=screen video, 1=webcam video, 2=mic audio (optional), 3=system audio (optional)
+
+            // Create picture-in-picture effect: webcam in bottom-right corner
+            // Use fps filter and setpts to ensure smooth, synchronized playback
+            // Scale screen to 1280x720 (720p), scale webcam to 213x120, overlay at bottom-right with 20px margin
+            let video_filter = "[0:v]fps=15,setpts=PTS-STARTPTS,scale=1280:720:force_original_aspect_ratio=decrease:force_divisible_by=2[screen];\
+                               [1:v]fps=15,setpts=PTS-STARTPTS,scale=213:120:force_original_aspect_ratio=decrease:force_divisible_by=2[webcam];\
+                               [screen][webcam]overlay=W-w-20:H-h-20:shortest=1[vout]";
+
+            if mic_enabled && sys_audio_enabled {
+                // Both audio sources: mix them with simple timestamp normalization
+                // Using asetpts instead of aresample to avoid resampling overhead
+                let filter = format!("{};[2:a]asetpts=PTS-STARTPTS[a1];[3:a]asetpts=PTS-STARTPTS[a2];[a1][a2]amix=inputs=2:duration=first:dropout_transition=2[aout]", video_filter);
+                cmd.arg("-filter_complex").arg(&filter)
+                    .arg("-map").arg("[vout]")    // Overlayed video
+                    .arg("-map").arg("[aout]");   // Mixed audio
+            } else if mic_enabled || sys_audio_enabled {
+                // Single audio source - simple timestamp normalization
+                let filter = format!("{};[2:a]asetpts=PTS-STARTPTS[aout]", video_filter);
+                cmd.arg("-filter_complex").arg(&filter)
+                    .arg("-map").arg("[vout]")    // Overlayed video
+                    .arg("-map").arg("[aout]");   // Synced audio
+            } else {
+                // No audio
+                cmd.arg("-filter_complex").arg(video_filter)
+                    .arg("-map").arg("[vout]");   // Overlayed video only
+            }
+        } else if options.mode == "screen" && mic_enabled && sys_audio_enabled {
+            // Screen mode with dual audio mixing: mix mic (input 1) + system (input 2)
+            // Using asetpts instead of aresample to avoid resampling overhead
+            cmd.arg("-filter_complex")
+                .arg("[1:a]asetpts=PTS-STARTPTS[a1];[2:a]asetpts=PTS-STARTPTS[a2];[a1][a2]amix=inputs=2:duration=first:dropout_transition=2[aout]")
+                .arg("-map").arg("0:v")  // Video from input 0 (screen)
+                .arg("-map").arg("[aout]"); // Mixed audio output
+        }
+
+        // Audio encoding settings (optimized for quality and reduced crackling)
+        cmd.arg("-c:a").arg("aac")
+            .arg("-b:a").arg(bitrate)
+            .arg("-profile:a").arg("aac_low")  // AAC-LC profile for better quality
+            .arg("-ar").arg("48000")
+            .arg("-ac").arg("2"); // Stereo output
+
+        // For simple captures (no filter_complex), add simple timestamp normalization
+        // Using asetpts instead of aresample to avoid resampling overhead
+        #[cfg(target_os = "macos")]
+        if options.mode != "combo" && !(options.mode == "screen" && mic_enabled && sys_audio_enabled) {
+            // Only add audio filter if we're not already using filter_complex
+            cmd.arg("-af").arg("asetpts=PTS-STARTPTS");
+        }
+    }
+
+    cmd.arg("-y")
+        .arg(&options.output_path)
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped()); // Capture stderr for diagnostics
+
+    log::info!("Starting FFmpeg process: {:?}", cmd);
+
+    let mut child = cmd.spawn().map_err(|e| {
+        format!("Failed to start FFmpeg recording: {}. Make sure FFmpeg is installed.", e)
+    })?;
+
+    // Capture stderr for continuous monitoring
+    if let Some(stderr) = child.stderr.take() {
+        // Spawn a thread to read and log FFmpeg output
+        std::thread::spawn(move || {
+            use std::io::{BufRead, BufReader};
+            let reader = BufReader::new(stderr);
+            for line in reader.lines() {
+                if let Ok(line) = line {
+                    // Log important messages
+                    if line.contains("drop") || line.contains("discontinuity") ||
+                       line.contains("buffer") || line.contains("queue") ||
+                       line.contains("audio") || line.contains("overrun") ||
+                       line.contains("underrun") || line.contains("pts") {
+                        log::warn!("[FFmpeg Audio] {}", line);
+                    }
+                    // Also log at debug level for full capture
+                    log::debug!("[FFmpeg] {}", line);
+                }
+            }
+        });
+    }
+
+    // Give FFmpeg a moment to start and validate inputs
+    std::thread::sleep(std::time::Duration::from_millis(500));
+
+    // Check if the process is still running (didn't immediately fail)
+    match child.try_wait() {
+        Ok(Some(status)) => {
+            return Err(format!("FFmpeg exited immediately with status: {}", status));
+        }
+        Ok(None) => {
+            // Process is still running - good!
+            log::info!("FFmpeg process started successfully");
+        }
+        Err(e) => {
+            log::warn!("Could not check FFmpeg status: {}", e);
+        }
+    }
+
+    *recording_process = Some(child);
+    Ok("Recording started".to_string())
+}
+
+#[tauri::command]
+fn stop_recording(state: State<RecordingState>) -> Result<String, String> {
+    log::info!("Stopping recording");
+
+    let mut recording_process = state.process.lock().unwrap();
+
+    if let Some(mut child) = recording_process.take() {
+        // Send SIGINT to FFmpeg to gracefully stop
+        #[cfg(unix)]
+        {
+            unsafe {
+                libc::kill(child.id() as i32, libc::SIGINT);
+            }
+        }
+
+        #[cfg(not(unix))]
+        {
+            let _ = child.kill();
+        }
+
+        // Wait for process to finish
+        match child.wait() {
+            Ok(status) => {
+                log::info!("Recording stopped with status: {:?}", status);
+
+                // FFmpeg exits with 255 when stopped via SIGINT - this is NORMAL and expected
+                // Only check for errors if the exit code indicates a real failure
+                #[cfg(unix)]
+                let is_normal_exit = {
+                    use std::os::unix::process::ExitStatusExt;
+                    status.code() == Some(255) || status.signal() == Some(2) || status.success()
+                };
+
+                #[cfg(not(unix))]
+                let is_normal_exit = status.success();
+
+                if !is_normal_exit {
+                    if let Some(mut stderr) = child.stderr.take() {
+                        use std::io::Read;
+                        let mut error_msg = String::new();
+                        if stderr.read_to_string(&mut error_msg).is_ok() {
+                            log::error!("FFmpeg stderr: {}", error_msg);
+
+                            // Check for common permission errors
+                            if error_msg.contains("not permitted") || error_msg.contains("Operation not permitted") {
+                                return Err("Recording failed: Screen recording permission denied. Please grant permission in System Settings > Privacy & Security > Screen Recording".to_string());
+                            }
+                            if error_msg.contains("Invalid device") {
+                                return Err("Recording failed: Invalid audio device selected".to_string());
+                            }
+                            if error_msg.contains("Error") && !error_msg.contains("Exiting normally") {
+                                return Err(format!("Recording failed: {}", error_msg.lines().find(|l| l.contains("Error")).unwrap_or("Unknown error")));
+                            }
+                        }
+                    }
+                    return Err(format!("Recording failed with status: {}. Check the logs for details.", status));
+                }
+
+                // Give FFmpeg a moment to flush and close the file
+                std::thread::sleep(std::time::Duration::from_millis(500));
+
+                log::info!("Recording completed successfully");
+                Ok("Recording stopped".to_string())
+            }
+            Err(e) => {
+                log::error!("Error waiting for FFmpeg process: {}", e);
+                Err(format!("Failed to stop recording cleanly: {}", e))
+            }
+        }
+    } else {
+        Err("No active recording".to_string())
+    }
+}
+
+#[tauri::command]
+fn start_test_recording(options: StartTestRecordingOptions, state: State<RecordingState>) -> Result<String, String> {
+    log::info!("Starting test recording with custom settings: {:?}", options);
+
+    let mut recording_process = state.process.lock().unwrap();
+    if recording_process.is_some() {
+        return Err("Recording already in progress".to_string());
+    }
+
+    // Build FFmpeg command with test settings
+    let ffmpeg = find_ffmpeg();
+    let mut cmd = Command::new(&ffmpeg);
+
+    // Apply test settings for buffer and logging
+    cmd.arg("-loglevel").arg("verbose")
+        .arg("-rtbufsize").arg(&options.test_settings.rtbufsize);
+
+    #[cfg(target_os = "macos")]
+    {
+        // Get audio settings
+        let audio_settings = options.audio_settings.as_ref();
+        let mic_enabled = audio_settings.map(|s| s.microphone_enabled).unwrap_or(true);
+        let mic_device = audio_settings
+            .and_then(|s| {
+                if s.microphone_device == "default" {
+                    Some("0")
+                } else {
+                    Some(s.microphone_device.as_str())
+                }
+            })
+            .unwrap_or("0");
+
+        // Screen capture with test settings
+        if options.mode == "screen" {
+            cmd.arg("-f").arg("avfoundation")
+                .arg("-capture_cursor").arg("1")
+                .arg("-framerate").arg("30");
+
+            // Apply wallclock timestamp test setting
+            if options.test_settings.use_wallclock_as_timestamps {
+                cmd.arg("-use_wallclock_as_timestamps").arg("1");
+            }
+
+            // Input
+            if mic_enabled {
+                cmd.arg("-i").arg(&format!("1:{}", mic_device));
+            } else {
+                cmd.arg("-i").arg("1:none");
+            }
+        }
+
+        // Thread queue size
+        cmd.arg("-thread_queue_size").arg(options.test_settings.thread_queue_size.to_string());
+
+        // Video encoding
+        cmd.arg("-c:v").arg("libx264")
+            .arg("-preset").arg("ultrafast")
+            .arg("-pix_fmt").arg("yuv420p");
+
+        // Audio encoding
+        cmd.arg("-c:a").arg("aac")
+            .arg("-b:a").arg("128k")
+            .arg("-ar").arg("48000")
+            .arg("-ac").arg("2");
+
+        // Apply audio filter test setting if provided
+        if !options.test_settings.audio_filter.is_empty() && mic_enabled {
+            cmd.arg("-af").arg(&options.test_settings.audio_filter);
+        }
+    }
+
+    cmd.arg("-y")
+        .arg(&options.output_path)
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped());
+
+    log::info!("Starting test recording FFmpeg process: {:?}", cmd);
+
+    let mut child = cmd.spawn().map_err(|e| {
+        format!("Failed to start test recording: {}. Make sure FFmpeg is installed.", e)
+    })?;
+
+    // Capture stderr for monitoring
+    if let Some(stderr) = child.stderr.take() {
+        std::thread::spawn(move || {
+            use std::io::{BufRead, BufReader};
+            let reader = BufReader::new(stderr);
+            for line in reader.lines() {
+                if let Ok(line) = line {
+                    if line.contains("drop") || line.contains("discontinuity") ||
+                       line.contains("buffer") || line.contains("queue") ||
+                       line.contains("audio") || line.contains("overrun") ||
+                       line.contains("underrun") || line.contains("pts") {
+                        log::warn!("[FFmpeg Test] {}", line);
+                    }
+                    log::debug!("[FFmpeg Test] {}", line);
+                }
+            }
+        });
+    }
+
+    std::thread::sleep(std::time::Duration::from_millis(500));
+
+    match child.try_wait() {
+        Ok(Some(status)) => {
+            return Err(format!("FFmpeg test recording exited immediately with status: {}", status));
+        }
+        Ok(None) => {
+            log::info!("Test recording started successfully");
+        }
+        Err(e) => {
+            log::warn!("Could not check FFmpeg test status: {}", e);
+        }
+    }
+
+    *recording_process = Some(child);
+    Ok(options.output_path)
+}
+
+// New ScreenCaptureKit-based recording (macOS only)
+#[cfg(target_os = "macos")]
+#[tauri::command]
+fn start_screencapturekit_recording(
+    output_path: String,
+    duration: f64,
+    audio_enabled: bool,
+    state: State<RecordingState>
+) -> Result<String, String> {
+    log::info!("Starting ScreenCaptureKit recording: path={}, duration={}, audio={}",
+               output_path, duration, audio_enabled);
+
+    let mut recording_process = state.process.lock().unwrap();
+    if recording_process.is_some() {
+        return Err("Recording already in progress".to_string());
+    }
+
+    let screen_recorder = find_screen_recorder();
+    if !screen_recorder.exists() {
+        log::error!("ScreenRecorder binary not found at: {:?}", screen_recorder);
+        return Err("ScreenRecorder binary not found. Please rebuild the application.".to_string());
+    }
+
+    log::info!("Using ScreenRecorder at: {:?}", screen_recorder);
+
+    // Build command: ScreenRecorder <output_path> <display_id> <audio> <duration>
+    let mut cmd = Command::new(&screen_recorder);
+    cmd.arg(&output_path)
+        .arg("0")  // Display 0 (main display)
+        .arg(if audio_enabled { "true" } else { "false" })
+        .arg(duration.to_string())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+
+    log::info!("Starting ScreenRecorder process...");
+
+    let mut child = cmd.spawn().map_err(|e| {
+        log::error!("Failed to spawn ScreenRecorder: {}", e);
+        format!("Failed to start ScreenRecorder: {}", e)
+    })?;
+
+    // Log output in background thread
+    if let Some(stdout) = child.stdout.take() {
+        use std::io::{BufRead, BufReader};
+        std::thread::spawn(move || {
+            let reader = BufReader::new(stdout);
+            for line in reader.lines().flatten() {
+                log::info!("[ScreenRecorder] {}", line);
+            }
+        });
+    }
+
+    if let Some(stderr) = child.stderr.take() {
+        use std::io::{BufRead, BufReader};
+        std::thread::spawn(move || {
+            let reader = BufReader::new(stderr);
+            for line in reader.lines().flatten() {
+                log::info!("[ScreenRecorder] {}", line);
+            }
+        });
+    }
+
+    log::info!("ScreenRecorder started successfully");
+    *recording_process = Some(child);
+    Ok(output_path)
+}
+
+#[tauri::command]
+fn open_in_native_player(path: String) -> Result<(), String> {
+    log::info!("Opening video in native player: {}", path);
+
+    #[cfg(target_os = "macos")]
+    {
+        // Use macOS 'open' command to open in QuickTime or default player
+        Command::new("open")
+            .arg(&path)
+            .spawn()
+            .map_err(|e| format!("Failed to open video: {}", e))?;
+    }
+
+    #[cfg(target_os = "windows")]
+    {
+        Command::new("cmd")
+            .args(&["/C", "start", "", &path])
+            .spawn()
+            .map_err(|e| format!("Failed to open video: {}", e))?;
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        Command::new("xdg-open")
+            .arg(&path)
+            .spawn()
+            .map_err(|e| format!("Failed to open video: {}", e))?;
+    }
+
+    Ok(())
+}
+
+// ===== EXPORT QUEUE =====
+
+// How many queued exports run at once unless the user changes it, and the most they may ask for
+const DEFAULT_EXPORT_QUEUE_CONCURRENCY: usize = 1;
+const MAX_EXPORT_QUEUE_CONCURRENCY: usize = 8;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "options", rename_all = "snake_case")]
+pub enum QueuedExportRequest {
+    Single(ExportOptions),
+    MultiClip(MultiClipExportOptions),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueuedExport {
+    id: String,                   // Also the export job ID while it runs, so cancel_export works on it
+    request: QueuedExportRequest,
+    status: String,               // "queued", "running", "completed", "failed" or "cancelled"
+    output_path: Option<String>,
+    error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportQueueSnapshot {
+    concurrency: usize,
+    paused: bool,
+    jobs: Vec<QueuedExport>,  // In queue order; queued jobs start front to back
+}
+
+impl ExportQueueSnapshot {
+    // Mark as many queued entries "running" as the concurrency limit has free slots for,
+    // front to back, and return them for the caller to start
+    fn start_queued(&mut self) -> Vec<QueuedExport> {
+        if self.paused {
+            return Vec::new();
+        }
+        let running = self.jobs.iter().filter(|job| job.status == "running").count();
+        let free_slots = self.concurrency.saturating_sub(running);
+
+        self.jobs.iter_mut()
+            .filter(|job| job.status == "queued")
+            .take(free_slots)
+            .map(|entry| {
+                entry.status = "running".to_string();
+                entry.output_path = None;
+                entry.error = None;
+                entry.clone()
+            })
+            .collect()
+    }
+
+    // Record how a running entry ended
+    // An entry removed from the queue while it ran has nothing to record
+    fn record_finished(&mut self, id: &str, result: &Result<String, String>, cancelled: bool) {
+        if let Some(entry) = self.jobs.iter_mut().find(|entry| entry.id == id) {
+            match result {
+                Ok(output_path) => {
+                    entry.status = "completed".to_string();
+                    entry.output_path = Some(output_path.clone());
+                }
+                Err(_) if cancelled => entry.status = "cancelled".to_string(),
+                Err(e) => {
+                    entry.status = "failed".to_string();
+                    entry.error = Some(e.clone());
+                }
+            }
+        }
+    }
+}
+
+// Background export queue, persisted as JSON in the app data dir after every change
+struct ExportQueue {
+    path: PathBuf,
+    state: Mutex<ExportQueueSnapshot>,
+}
+
+impl ExportQueue {
+    // Restore the queue saved by a previous run
+    // Jobs that were running when the app quit go back to "queued", and a queue that still has
+    // unfinished work comes back paused so nothing starts encoding until resume_export_queue
+    fn load(path: PathBuf) -> Self {
+        let mut state = match std::fs::read(&path) {
+            Ok(data) => serde_json::from_slice(&data).unwrap_or_else(|e| {
+                log::warn!("Failed to parse saved export queue, starting empty: {}", e);
+                ExportQueue::empty_state()
+            }),
+            Err(e) => {
+                if e.kind() != std::io::ErrorKind::NotFound {
+                    log::warn!("Failed to read saved export queue, starting empty: {}", e);
+                }
+                ExportQueue::empty_state()
+            }
+        };
+
+        for job in state.jobs.iter_mut().filter(|job| job.status == "running") {
+            log::info!("Export {} was interrupted by a restart, re-queueing", job.id);
+            job.status = "queued".to_string();
+        }
+        if state.jobs.iter().any(|job| job.status == "queued") {
+            state.paused = true;
+        }
+
+        ExportQueue { path, state: Mutex::new(state) }
+    }
+
+    fn empty_state() -> ExportQueueSnapshot {
+        ExportQueueSnapshot { concurrency: DEFAULT_EXPORT_QUEUE_CONCURRENCY, paused: false, jobs: Vec::new() }
+    }
+
+    fn save(&self, state: &ExportQueueSnapshot) -> Result<(), String> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create data directory: {}", e))?;
+        }
+        let data = serde_json::to_vec_pretty(state)
+            .map_err(|e| format!("Failed to serialize export queue: {}", e))?;
+        std::fs::write(&self.path, data)
+            .map_err(|e| format!("Failed to save export queue: {}", e))
+    }
+}
+
+// Helper function to persist the queue and tell the frontend about it; returns what was saved
+fn publish_export_queue(window: &tauri::Window, queue: &ExportQueue, state: &ExportQueueSnapshot) -> ExportQueueSnapshot {
+    if let Err(e) = queue.save(state) {
+        log::error!("{}", e);
+    }
+    let _ = window.emit("export-queue", state.clone());
+    state.clone()
+}
+
+// Helper function to start as many queued exports as the concurrency limit allows
+fn pump_export_queue(window: &tauri::Window) -> ExportQueueSnapshot {
+    let queue = window.state::<ExportQueue>();
+    let jobs = window.state::<ExportJobs>();
+    let mut state = queue.state.lock().unwrap();
+
+    for entry in state.start_queued() {
+        log::info!("Starting queued export {}", entry.id);
+        let request = entry.request;
+        let job = Arc::new(ExportJob::with_id(entry.id));
+        spawn_export_job_with(window.clone(), &jobs, job, move |window, job| {
+            let result = match request {
+                QueuedExportRequest::Single(options) => export_video_job(options, window, job),
+                QueuedExportRequest::MultiClip(options) => export_multi_clip_job(options, window, job),
+            };
+            finish_queued_export(window, job, &result);
+            result
+        });
+    }
+
+    publish_export_queue(window, &queue, &state)
+}
+
+// Helper function to record how a queued export ended and start the next one
+fn finish_queued_export(window: &tauri::Window, job: &ExportJob, result: &Result<String, String>) {
+    // Even if the entry was removed from the queue while it ran, its slot is free now,
+    // so the queue is pumped either way
+    window.state::<ExportQueue>().state.lock().unwrap().record_finished(&job.id, result, job.is_cancelled());
+
+    pump_export_queue(window);
+}
+
+// Helper function to add an export to the back of the queue
+fn enqueue_export_request(request: QueuedExportRequest, window: &tauri::Window) -> Result<ExportQueueSnapshot, String> {
+    let queue = window.state::<ExportQueue>();
+    let id = next_export_job_id();
+    log::info!("Queueing export {}", id);
+
+    queue.state.lock().unwrap().jobs.push(QueuedExport {
+        id,
+        request,
+        status: "queued".to_string(),
+        output_path: None,
+        error: None,
+    });
+    Ok(pump_export_queue(window))
+}
+
+// Add a single-file export to the queue; it starts once a slot is free and the queue isn't paused
+// Progress arrives as "merge-progress", the outcome as "export-job", and queue changes as "export-queue"
+#[tauri::command]
+fn enqueue_export(options: ExportOptions, window: tauri::Window) -> Result<ExportQueueSnapshot, String> {
+    if let Some(preset) = &options.preset {
+        validate_export_preset(preset)?;
+    }
+    enqueue_export_request(QueuedExportRequest::Single(options), &window)
+}
+
+#[tauri::command]
+fn enqueue_multi_clip_export(options: MultiClipExportOptions, window: tauri::Window) -> Result<ExportQueueSnapshot, String> {
+    if options.clips.is_empty() {
+        return Err("No clips to export".to_string());
+    }
+    if let Some(preset) = &options.preset {
+        validate_export_preset(preset)?;
+    }
+    enqueue_export_request(QueuedExportRequest::MultiClip(options), &window)
+}
+
+#[tauri::command]
+fn list_export_queue(queue: State<ExportQueue>) -> Result<ExportQueueSnapshot, String> {
+    Ok(queue.state.lock().unwrap().clone())
+}
+
+// Change how many queued exports may run at once; lowering it lets running exports finish
+#[tauri::command]
+fn set_export_queue_concurrency(limit: usize, window: tauri::Window) -> Result<ExportQueueSnapshot, String> {
+    log::info!("set_export_queue_concurrency called with limit: {}", limit);
+
+    if !(1..=MAX_EXPORT_QUEUE_CONCURRENCY).contains(&limit) {
+        return Err(format!("Concurrency must be between 1 and {}", MAX_EXPORT_QUEUE_CONCURRENCY));
+    }
+    window.state::<ExportQueue>().state.lock().unwrap().concurrency = limit;
+    Ok(pump_export_queue(&window))
+}
+
+// Stop starting queued exports; exports already running carry on
+#[tauri::command]
+fn pause_export_queue(window: tauri::Window) -> Result<ExportQueueSnapshot, String> {
+    log::info!("Pausing export queue");
+
+    let queue = window.state::<ExportQueue>();
+    let mut state = queue.state.lock().unwrap();
+    state.paused = true;
+    Ok(publish_export_queue(&window, &queue, &state))
+}
+
+#[tauri::command]
+fn resume_export_queue(window: tauri::Window) -> Result<ExportQueueSnapshot, String> {
+    log::info!("Resuming export queue");
+
+    window.state::<ExportQueue>().state.lock().unwrap().paused = false;
+    Ok(pump_export_queue(&window))
+}
+
+// Move a job to a new position in the queue (0 = front); positions past the end move it to the back
+#[tauri::command]
+fn move_export_queue_job(job_id: String, position: usize, window: tauri::Window) -> Result<ExportQueueSnapshot, String> {
+    log::info!("move_export_queue_job called for job: {} to position {}", job_id, position);
+
+    let queue = window.state::<ExportQueue>();
+    let mut state = queue.state.lock().unwrap();
+    let index = state.jobs.iter()
+        .position(|job| job.id == job_id)
+        .ok_or_else(|| format!("No queued export with ID {}", job_id))?;
+
+    let job = state.jobs.remove(index);
+    let position = position.min(state.jobs.len());
+    state.jobs.insert(position, job);
+    Ok(publish_export_queue(&window, &queue, &state))
+}
+
+// Put a failed or cancelled export back in the queue, at its current position
+#[tauri::command]
+fn retry_export_queue_job(job_id: String, window: tauri::Window) -> Result<ExportQueueSnapshot, String> {
+    log::info!("retry_export_queue_job called for job: {}", job_id);
+
+    {
+        let queue = window.state::<ExportQueue>();
+        let mut state = queue.state.lock().unwrap();
+        let job = state.jobs.iter_mut()
+            .find(|job| job.id == job_id)
+            .ok_or_else(|| format!("No queued export with ID {}", job_id))?;
+
+        if job.status != "failed" && job.status != "cancelled" {
+            return Err(format!("Only failed or cancelled exports can be retried (this one is {})", job.status));
+        }
+        job.status = "queued".to_string();
+        job.output_path = None;
+        job.error = None;
+    }
+
+    Ok(pump_export_queue(&window))
+}
+
+// Remove a job from the queue; a running export must be cancelled with cancel_export first
+#[tauri::command]
+fn remove_export_queue_job(job_id: String, window: tauri::Window) -> Result<ExportQueueSnapshot, String> {
+    log::info!("remove_export_queue_job called for job: {}", job_id);
+
+    let queue = window.state::<ExportQueue>();
+    let mut state = queue.state.lock().unwrap();
+    let index = state.jobs.iter()
+        .position(|job| job.id == job_id)
+        .ok_or_else(|| format!("No queued export with ID {}", job_id))?;
+
+    if state.jobs[index].status == "running" {
+        return Err("This export is running - cancel it before removing it from the queue".to_string());
+    }
+    state.jobs.remove(index);
+    Ok(publish_export_queue(&window, &queue, &state))
+}
+
+// ===== PROXY MEDIA =====
+
+// Proxy files are kept in the source's media cache entry
+const PROXY_CACHE_NAME: &str = "proxy.mp4";
+
+// Maps original source paths to low-resolution preview proxies in the media cache.
+// The video:// protocol serves the proxy in place of the original; exports never see it.
+// Import refills this from the cache, so proxies survive restarts without being regenerated.
+struct ProxyState {
+    proxies: Mutex<HashMap<String, String>>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ProxyInfo {
+    original_path: String,
+    proxy_path: String,
+}
+
+#[derive(Clone, serde::Serialize)]
+struct ProxyProgress {
+    original_path: String,
+    percent: f64,
+    status: String,
+}
+
+// Helper function to remember a source's proxy for the video:// protocol
+fn register_proxy(app: &tauri::AppHandle, original_path: &str, proxy_path: &str) {
+    app.state::<ProxyState>()
+        .proxies
+        .lock()
+        .unwrap()
+        .insert(original_path.to_string(), proxy_path.to_string());
+}
+
+// Generate a low-resolution, all-intra H.264 proxy for smooth scrubbing of large sources
+#[tauri::command]
+async fn generate_proxy(path: String, app: tauri::AppHandle) -> Result<ProxyInfo, String> {
+    log::info!("generate_proxy called with path: {}", path);
+
+    if !PathBuf::from(&path).exists() {
+        return Err("File does not exist".to_string());
+    }
+
+    let probe = probe_video_metadata(&path)?;
+    if !probe.has_video || is_still_image(&probe) {
+        return Err("Proxies can only be generated for video files".to_string());
+    }
+
+    // The cache key covers the source's content and mtime, so a replaced source gets a new proxy
+    let key = media_file_key(&path)?;
+    let cache = app.state::<MediaCache>();
+
+    let proxy_path = match cache.cached_file(&key, PROXY_CACHE_NAME) {
+        Some(cached) => {
+            log::info!("Reusing cached proxy: {:?}", cached);
+            cached
+        }
+        None => {
+            let temp_path = cache.temp_file(&key, PROXY_CACHE_NAME)?;
+            let duration = probe.duration;
+            let source = path.clone();
+            let output = temp_path.clone();
+            let progress_app = app.clone();
+
+            tauri::async_runtime::spawn_blocking(move || {
+                let mut cmd = Command::new(find_ffmpeg());
+                cmd.arg("-i").arg(&source)
+                    .arg("-map").arg("0:v:0")
+                    .arg("-map").arg("0:a:0?")
+                    // Fit inside 960x960 so both landscape and portrait sources shrink the long edge
+                    .arg("-vf").arg("scale=960:960:force_original_aspect_ratio=decrease:force_divisible_by=2")
+                    .arg("-c:v").arg("libx264")
+                    .arg("-preset").arg("veryfast")
+                    .arg("-tune").arg("fastdecode")
+                    .arg("-crf").arg("28")
+                    .arg("-g").arg("1")   // All-intra: every frame is a keyframe for instant seeking
+                    .arg("-bf").arg("0")
+                    .arg("-pix_fmt").arg("yuv420p")
+                    .arg("-c:a").arg("aac")
+                    .arg("-b:a").arg("128k")
+                    .arg("-movflags").arg("+faststart")
+                    .arg("-y")
+                    .arg(&output);
+
+                log::info!("Running proxy generation: {:?}", cmd);
+
+                run_ffmpeg_with_progress(&mut cmd, |seconds| {
+                    let percent = if duration > 0.0 { (seconds / duration * 100.0).clamp(0.0, 100.0) } else { 0.0 };
+                    let _ = progress_app.emit("proxy-progress", ProxyProgress {
+                        original_path: source.clone(),
+                        percent,
+                        status: "Generating proxy...".to_string(),
+                    });
+                })
+            })
+            .await
+            .map_err(|e| format!("Proxy generation task failed: {}", e))?
+            .map_err(|stderr| {
+                log::error!("FFmpeg proxy generation failed: {}", stderr);
+                let _ = std::fs::remove_file(&temp_path);
+                format!("Failed to generate proxy: {}", stderr)
+            })?;
+
+            let proxy_path = cache.commit_file(&key, &temp_path, PROXY_CACHE_NAME)?;
+            cache.evict();
+            proxy_path
+        }
+    };
+    let proxy_path_str = proxy_path.to_string_lossy().to_string();
+
+    let _ = app.emit("proxy-progress", ProxyProgress {
+        original_path: path.clone(),
+        percent: 100.0,
+        status: "Proxy ready".to_string(),
+    });
+
+    register_proxy(&app, &path, &proxy_path_str);
+
+    log::info!("Proxy ready for {}: {}", path, proxy_path_str);
+    Ok(ProxyInfo {
+        original_path: path,
+        proxy_path: proxy_path_str,
+    })
+}
+
+// ===== MEDIA RELINKING =====
+
+// What the project remembers about a source file that can no longer be found
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MissingMedia {
+    path: String,
+    file_size: Option<u64>,
+    duration: Option<f64>,
+    fingerprint: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RelinkCandidate {
+    path: String,
+    confidence: String,         // "exact" (content match), "high" or "low"
+    matched_on: Vec<String>,    // Any of "filename", "size", "duration", "fingerprint"
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RelinkProposal {
+    original_path: String,
+    candidates: Vec<RelinkCandidate>,  // Best match first; empty if nothing was found
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MissingClipFile {
+    clip_index: usize,
+    path: String,
+}
+
+// Helper function to list every clip whose source file is missing, before any encoding starts
+fn find_missing_clip_files(clips: &[ClipSegment]) -> Vec<MissingClipFile> {
+    clips.iter()
+        .enumerate()
+        .filter(|(_, clip)| !PathBuf::from(&clip.input_path).exists())
+        .map(|(i, clip)| MissingClipFile {
+            clip_index: i,
+            path: clip.input_path.clone(),
+        })
+        .collect()
+}
+
+// Preflight check for export: report all missing source files at once
+#[tauri::command]
+fn check_missing_media(clips: Vec<ClipSegment>) -> Result<Vec<MissingClipFile>, String> {
+    let missing = find_missing_clip_files(&clips);
+    log::info!("Preflight found {} missing source file(s) in {} clips", missing.len(), clips.len());
+    Ok(missing)
+}
+
+// Helper function to score one candidate file against a missing source
+fn match_relink_candidate(missing: &MissingMedia, candidate: &str) -> Option<RelinkCandidate> {
+    let missing_name = std::path::Path::new(&missing.path).file_name();
+    let candidate_path = std::path::Path::new(candidate);
+
+    let mut matched_on: Vec<String> = Vec::new();
+
+    if missing_name.is_some() && candidate_path.file_name() == missing_name {
+        matched_on.push("filename".to_string());
+    }
+
+    let candidate_size = std::fs::metadata(candidate_path).map(|m| m.len()).ok();
+    let size_matches = missing.file_size.is_some() && candidate_size == missing.file_size;
+    if size_matches {
+        matched_on.push("size".to_string());
+    }
+
+    // Only do the more expensive checks for files that already look related
+    if matched_on.is_empty() {
+        return None;
+    }
+
+    if size_matches {
+        if let Some(expected) = missing.fingerprint.as_deref() {
+            if media_content_fingerprint(candidate).ok().as_deref() == Some(expected) {
+                matched_on.push("fingerprint".to_string());
+            }
+        }
+    }
+
+    if let Some(expected) = missing.duration {
+        if let Ok(probe) = probe_video_metadata(candidate) {
+            // Container durations can differ by a frame after remuxing
+            if (probe.duration - expected).abs() < 0.1 {
+                matched_on.push("duration".to_string());
+            }
+        }
+    }
+
+    let has = |key: &str| matched_on.iter().any(|m| m == key);
+    let confidence = if has("fingerprint") {
+        "exact"
+    } else if has("duration") && (has("size") || (has("filename") && missing.file_size.is_none())) {
+        // Size + duration, or filename + duration when the project never recorded a size
+        "high"
+    } else {
+        "low"
+    };
+
+    Some(RelinkCandidate {
+        path: candidate.to_string(),
+        confidence: confidence.to_string(),
+        matched_on,
+    })
+}
+
+// Helper function to score every candidate against a missing source, best match first:
+// by confidence, then by how many properties matched
+fn rank_relink_candidates(missing: &MissingMedia, candidates: &[String]) -> Vec<RelinkCandidate> {
+    let confidence_rank = |c: &RelinkCandidate| match c.confidence.as_str() {
+        "exact" => 0,
+        "high" => 1,
+        _ => 2,
+    };
+
+    let mut matches: Vec<RelinkCandidate> = candidates.iter()
+        .filter_map(|candidate| match_relink_candidate(missing, candidate))
+        .collect();
+    matches.sort_by_key(|c| (confidence_rank(c), std::cmp::Reverse(c.matched_on.len())));
+    matches
+}
+
+// Search candidate folders for moved or renamed sources and propose new paths for each
+#[tauri::command]
+async fn relink_media(
+    missing: Vec<MissingMedia>,
+    search_directories: Vec<String>,
+    recursive: Option<bool>,
+) -> Result<Vec<RelinkProposal>, String> {
+    log::info!("Relinking {} missing file(s) across {} folder(s)", missing.len(), search_directories.len());
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let mut candidates: Vec<String> = Vec::new();
+        let mut skipped: Vec<String> = Vec::new();
+        let mut unreadable: Vec<BatchImportError> = Vec::new();
+
+        // Folders that can't be read are logged by collect_media_files and skipped
+        for dir in &search_directories {
+            collect_media_files(std::path::Path::new(dir), recursive.unwrap_or(true), &mut candidates, &mut skipped, &mut unreadable);
+        }
+
+        log::info!("Checking {} candidate media files", candidates.len());
+
+        missing.iter()
+            .map(|item| RelinkProposal {
+                original_path: item.path.clone(),
+                candidates: rank_relink_candidates(item, &candidates),
+            })
+            .collect()
+    })
+    .await
+    .map_err(|e| format!("Relink task failed: {}", e))
+}
+
+// ===== MEDIA ANALYSIS =====
+
+// Default air kept before and after speech when cutting out silence
+const DEFAULT_SILENCE_PADDING_SECS: f64 = 0.15;
+
+// Speech segments shorter than this after padding are dropped as noise
+const MIN_SPEECH_SEGMENT_SECS: f64 = 0.1;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SceneChange {
+    time: f64,   // Timestamp of the first frame of the new scene, in seconds
+    score: f64,  // FFmpeg scene score, 0.0 (identical) to 1.0 (completely different)
+}
+
+// Helper function to parse the output of FFmpeg's `metadata=print` filter
+// Each selected frame prints a "frame:N pts:N pts_time:T" line followed by its metadata keys
+fn parse_scene_metadata(output: &str) -> Vec<SceneChange> {
+    let mut scenes = Vec::new();
+    let mut current_time: Option<f64> = None;
+
+    for line in output.lines() {
+        if let Some(pos) = line.find("pts_time:") {
+            current_time = line[pos + "pts_time:".len()..]
+                .split_whitespace()
+                .next()
+                .and_then(|t| t.parse::<f64>().ok());
+        } else if let Some(score) = line.trim().strip_prefix("lavfi.scene_score=") {
+            if let (Some(time), Ok(score)) = (current_time.take(), score.parse::<f64>()) {
+                scenes.push(SceneChange { time: round_to_millis(time), score });
+            }
+        }
+    }
+
+    scenes
+}
+
+// Detect hard cuts using FFmpeg's scene score; threshold is 0.0-1.0 (0.3-0.4 works for most footage)
+#[tauri::command]
+async fn detect_scenes(path: String, threshold: f64) -> Result<Vec<SceneChange>, String> {
+    log::info!("Detecting scenes in: {} (threshold {})", path, threshold);
+
+    if !(0.0..=1.0).contains(&threshold) {
+        return Err("Scene threshold must be between 0.0 and 1.0".to_string());
+    }
+
+    if !PathBuf::from(&path).exists() {
+        return Err("File does not exist".to_string());
+    }
+
+    // Downscale first: scene scores barely change and analysis runs much faster
+    let filter = format!(
+        "scale=320:-2,select='gt(scene,{})',metadata=print:file=-",
+        threshold
+    );
+
+    // Scoring decodes the whole video, so it runs off the main thread
+    let output = tauri::async_runtime::spawn_blocking(move || {
+        let ffmpeg = find_ffmpeg();
+        Command::new(&ffmpeg)
+            .arg("-i").arg(&path)
+            .arg("-an")
+            .arg("-sn")
+            .arg("-vf").arg(&filter)
+            .arg("-f").arg("null")
+            .arg("-")
+            .output()
+    })
+    .await
+    .map_err(|e| format!("Scene detection task failed: {}", e))?
+    .map_err(|e| format!("Failed to execute FFmpeg for scene detection: {}", e))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        log::error!("FFmpeg scene detection failed: {}", stderr);
+        return Err(format!("Scene detection failed: {}", stderr));
+    }
+
+    let scenes = parse_scene_metadata(&String::from_utf8_lossy(&output.stdout));
+    log::info!("Detected {} scene changes", scenes.len());
+    Ok(scenes)
+}
+
+// Turn cut points into consecutive ClipSegments covering the whole source
+#[tauri::command]
+fn split_at_scene_changes(path: String, cut_times: Vec<f64>) -> Result<Vec<ClipSegment>, String> {
+    log::info!("Splitting {} at {} cut points", path, cut_times.len());
+
+    let duration = probe_video_metadata(&path)?.duration;
+    if duration <= 0.0 {
+        return Err("Could not determine video duration".to_string());
+    }
+
+    let mut boundaries: Vec<f64> = cut_times.into_iter()
+        .map(round_to_millis)
+        .filter(|t| *t > 0.0 && *t < duration)
+        .collect();
+    boundaries.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    boundaries.dedup();
+    boundaries.insert(0, 0.0);
+    boundaries.push(round_to_millis(duration));
+
+    let segments = boundaries
+        .windows(2)
+        .map(|range| clip_segment_for_range(&path, range[0], range[1]))
+        .collect();
+
+    Ok(segments)
+}
+
+// Helper function to parse silencedetect's "silence_start: T" / "silence_end: T | silence_duration: D" log lines
+// A silence still open at the end of the file runs to `duration`
+fn parse_silencedetect_output(stderr: &str, duration: f64) -> Vec<TimeRange> {
+    let mut ranges = Vec::new();
+    let mut open_start: Option<f64> = None;
+
+    let value_after = |line: &str, key: &str| -> Option<f64> {
+        let pos = line.find(key)?;
+        line[pos + key.len()..]
+            .split_whitespace()
+            .next()
+            .and_then(|v| v.parse::<f64>().ok())
+    };
+
+    for line in stderr.lines() {
+        if let Some(start) = value_after(line, "silence_start:") {
+            open_start = Some(start.max(0.0));
+        } else if let Some(end) = value_after(line, "silence_end:") {
+            if let Some(start) = open_start.take() {
+                ranges.push(TimeRange { start: round_to_millis(start), end: round_to_millis(end) });
+            }
+        }
+    }
+
+    if let Some(start) = open_start {
+        if duration > start {
+            ranges.push(TimeRange { start: round_to_millis(start), end: round_to_millis(duration) });
+        }
+    }
+
+    ranges
+}
+
+// Helper function to turn silent ranges into the speech ranges between them
+// Each speech range is widened by `padding` on both sides; ranges that then overlap are merged
+fn speech_ranges(duration: f64, silences: &[TimeRange], padding: f64) -> Vec<TimeRange> {
+    let mut silences: Vec<&TimeRange> = silences.iter().filter(|s| s.end > s.start).collect();
+    silences.sort_by(|a, b| a.start.partial_cmp(&b.start).unwrap());
+
+    let mut speech = Vec::new();
+    let mut cursor = 0.0;
+    for silence in silences {
+        if silence.start > cursor {
+            speech.push((cursor, silence.start));
+        }
+        cursor = cursor.max(silence.end);
+    }
+    if duration > cursor {
+        speech.push((cursor, duration));
+    }
+
+    let mut ranges: Vec<TimeRange> = Vec::new();
+    for (start, end) in speech {
+        let start = round_to_millis((start - padding).max(0.0));
+        let end = round_to_millis((end + padding).min(duration));
+        match ranges.last_mut() {
+            Some(last) if start <= last.end => last.end = last.end.max(end),
+            _ => ranges.push(TimeRange { start, end }),
+        }
+    }
+
+    // Drop slivers too short to be real speech (e.g. a click between two silences)
+    ranges.retain(|r| r.end - r.start >= MIN_SPEECH_SEGMENT_SECS);
+    ranges
+}
+
+// Find silent ranges with FFmpeg's silencedetect
+// noise_db is the level below which audio counts as silence (e.g. -35), min_duration is in seconds
+#[tauri::command]
+async fn detect_silence(path: String, noise_db: f64, min_duration: f64) -> Result<Vec<TimeRange>, String> {
+    log::info!("Detecting silence in: {} (noise {} dB, min duration {}s)", path, noise_db, min_duration);
+
+    if noise_db >= 0.0 {
+        return Err("Noise threshold must be below 0 dB".to_string());
+    }
+    if min_duration <= 0.0 {
+        return Err("Minimum silence duration must be greater than zero".to_string());
+    }
+
+    // silencedetect decodes all of the audio, so it runs off the main thread
+    tauri::async_runtime::spawn_blocking(move || {
+        let probe = probe_video_metadata(&path)?;
+        if probe.audio_streams.is_empty() {
+            return Err("This file has no audio track".to_string());
+        }
+
+        let ffmpeg = find_ffmpeg();
+        let output = Command::new(&ffmpeg)
+            .arg("-hide_banner")
+            .arg("-nostats")
+            .arg("-i").arg(&path)
+            .arg("-vn")
+            .arg("-sn")
+            .arg("-af").arg(format!("silencedetect=noise={}dB:d={}", noise_db, min_duration))
+            .arg("-f").arg("null")
+            .arg("-")
+            .output()
+            .map_err(|e| format!("Failed to execute FFmpeg for silence detection: {}", e))?;
+
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        if !output.status.success() {
+            log::error!("FFmpeg silence detection failed: {}", stderr);
+            return Err(format!("Silence detection failed: {}", stderr));
+        }
+
+        let silences = parse_silencedetect_output(&stderr, probe.duration);
+        log::info!("Detected {} silent ranges", silences.len());
+        Ok(silences)
+    })
+    .await
+    .map_err(|e| format!("Silence detection task failed: {}", e))?
+}
+
+// Turn silent ranges into ClipSegments that keep only the speech (jump cuts)
+// padding (seconds) keeps a little air around each cut so words aren't clipped
+#[tauri::command]
+fn remove_silence(path: String, silences: Vec<TimeRange>, padding: Option<f64>) -> Result<Vec<ClipSegment>, String> {
+    let padding = padding.unwrap_or(DEFAULT_SILENCE_PADDING_SECS);
+    log::info!("Removing {} silent ranges from {} (padding {}s)", silences.len(), path, padding);
+
+    if padding < 0.0 {
+        return Err("Padding cannot be negative".to_string());
+    }
+
+    let duration = probe_video_metadata(&path)?.duration;
+    if duration <= 0.0 {
+        return Err("Could not determine video duration".to_string());
+    }
+
+    let segments: Vec<ClipSegment> = speech_ranges(duration, &silences, padding)
+        .iter()
+        .map(|range| clip_segment_for_range(&path, range.start, range.end))
+        .collect();
+
+    log::info!("Kept {} speech segments", segments.len());
+    Ok(segments)
+}
+
+// ===== LOUDNESS =====
+
+// Two-pass loudnorm keeps true peaks under this ceiling and allows this much loudness range
+const LOUDNORM_TRUE_PEAK_DBTP: f64 = -1.5;
+const LOUDNORM_LOUDNESS_RANGE: f64 = 11.0;
+
+// loudnorm works at 192 kHz internally, so normalized audio is resampled back to this rate
+const LOUDNORM_OUTPUT_SAMPLE_RATE: u32 = 48000;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TimeRange {
+    start: f64,
+    end: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LoudnessMeasurement {
+    integrated_lufs: Option<f64>,  // Integrated loudness (None for silence)
+    loudness_range: f64,           // Loudness range (LRA) in LU
+    true_peak_dbtp: Option<f64>,   // True peak in dBTP (None for silence)
+    threshold_lufs: Option<f64>,   // Gating threshold, needed for the second loudnorm pass
+    target_offset: Option<f64>,    // Offset gain to pass to the second loudnorm pass
+}
+
+// Helper function to check a requested loudness target, e.g. -14 LUFS for streaming or -16 LUFS for podcasts
+fn validate_target_loudness(target: f64) -> Result<(), String> {
+    if (-70.0..=-5.0).contains(&target) {
+        Ok(())
+    } else {
+        Err(format!("Target loudness must be between -70 and -5 LUFS (got {})", target))
+    }
+}
+
+// Helper function to read the JSON block loudnorm prints to stderr at the end of an analysis pass
+// Values are strings; "-inf" (silence) becomes None
+fn parse_loudnorm_json(stderr: &str) -> Result<LoudnessMeasurement, String> {
+    let start = stderr.rfind('{').ok_or("No loudness analysis in FFmpeg output")?;
+    let end = stderr[start..].find('}').map(|pos| start + pos + 1).ok_or("Truncated loudness analysis in FFmpeg output")?;
+    let json: serde_json::Value = serde_json::from_str(&stderr[start..end])
+        .map_err(|e| format!("Failed to parse loudness analysis: {}", e))?;
+
+    let value = |key: &str| probe_number(&json[key]).filter(|v| v.is_finite());
+
+    Ok(LoudnessMeasurement {
+        integrated_lufs: value("input_i"),
+        loudness_range: value("input_lra").unwrap_or(0.0),
+        true_peak_dbtp: value("input_tp"),
+        threshold_lufs: value("input_thresh"),
+        target_offset: value("target_offset"),
+    })
+}
+
+// Helper function to run a loudnorm analysis pass over a file (or part of it)
+// Pass the export target so the reported target_offset matches what the second pass will use,
+// and the export job so cancel_export can stop a long analysis like any other pass
+fn analyze_loudness(path: &str, range: Option<&TimeRange>, target: Option<f64>, job: Option<&ExportJob>) -> Result<LoudnessMeasurement, String> {
+    let ffmpeg = find_ffmpeg();
+    let mut cmd = Command::new(&ffmpeg);
+    cmd.arg("-hide_banner");
+    if let Some(range) = range {
+        cmd.arg("-ss").arg(range.start.to_string())
+            .arg("-t").arg(round_to_millis(range.end - range.start).to_string());
+    }
+    cmd.arg("-i").arg(path);
+
+    let mut filter = format!("loudnorm=TP={}:LRA={}:print_format=json", LOUDNORM_TRUE_PEAK_DBTP, LOUDNORM_LOUDNESS_RANGE);
+    if let Some(target) = target {
+        filter.push_str(&format!(":I={}", target));
+    }
+
+    cmd.arg("-vn")
+        .arg("-af").arg(&filter)
+        .arg("-f").arg("null")
+        .arg("-");
+
+    let stderr = match run_ffmpeg_for_job_output(&mut cmd, job, |_| {}) {
+        Ok(stderr) => stderr,
+        Err(e) if job.map(|job| job.is_cancelled()).unwrap_or(false) => return Err(e),
+        Err(stderr) => {
+            log::error!("FFmpeg loudness analysis failed: {}", stderr);
+            return Err(format!("Loudness analysis failed: {}", stderr));
+        }
+    };
+
+    parse_loudnorm_json(&stderr)
+}
+
+// Helper function to build the second-pass loudnorm filter from a first-pass measurement
+// Returns None for silent audio, which has nothing to normalize
+fn loudnorm_filter(target: f64, measured: &LoudnessMeasurement) -> Option<String> {
+    let integrated = measured.integrated_lufs?;
+    let true_peak = measured.true_peak_dbtp?;
+    let threshold = measured.threshold_lufs?;
+
+    // linear=true applies one gain for the whole clip when the measurement allows it,
+    // so dynamics are preserved instead of being compressed
+    Some(format!(
+        "loudnorm=I={}:TP={}:LRA={}:measured_I={}:measured_TP={}:measured_LRA={}:measured_thresh={}:offset={}:linear=true,aresample={}",
+        target,
+        LOUDNORM_TRUE_PEAK_DBTP,
+        LOUDNORM_LOUDNESS_RANGE,
+        integrated,
+        true_peak,
+        measured.loudness_range,
+        threshold,
+        measured.target_offset.unwrap_or(0.0),
+        LOUDNORM_OUTPUT_SAMPLE_RATE
+    ))
+}
+
+// Measure EBU R128 loudness: integrated LUFS, loudness range and true peak
+#[tauri::command]
+async fn measure_loudness(path: String, range: Option<TimeRange>) -> Result<LoudnessMeasurement, String> {
+    log::info!("Measuring loudness of: {} (range: {:?})", path, range);
+
+    if !PathBuf::from(&path).exists() {
+        return Err("File does not exist".to_string());
+    }
+    if let Some(range) = &range {
+        if range.end <= range.start || range.start < 0.0 {
+            return Err(format!("Invalid range: {}s - {}s", range.start, range.end));
+        }
+    }
+
+    let measurement = tauri::async_runtime::spawn_blocking(move || analyze_loudness(&path, range.as_ref(), None, None))
+        .await
+        .map_err(|e| format!("Loudness task failed: {}", e))??;
+
+    log::info!("Loudness: {:?}", measurement);
+    Ok(measurement)
+}
+
+// ===== GOOGLE DRIVE EXPORT =====
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GoogleDriveConfig {
+    api_key: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GoogleDriveFileMetadata {
+    name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    parents: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    mime_type: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct GoogleDriveFile {
+    id: String,
+    name: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct GoogleDriveFileList {
+    files: Vec<GoogleDriveFile>,
+}
+
+// Find or create ClipForge folder in Google Drive
+async fn get_or_create_clipforge_folder(api_key: &str) -> Result<String, String> {
+    log::info!("Getting or creating ClipForge folder");
+
+    let client = reqwest::Client::new();
+
+    // Search for existing ClipForge folder
+    let search_url = "https://www.googleapis.com/drive/v3/files";
+    let query = "name='ClipForge' and mimeType='application/vnd.google-apps.folder' and trashed=false";
+
+    let response = client
+        .get(search_url)
+        .query(&[("q", query), ("fields", "files(id,name)")])
+        .header("Authorization", format!("Bearer {}", api_key))
+        .send()
+        .await
+        .map_err(|e| format!("Failed to search for ClipForge folder: {}", e))?;
+
+    if !response.status().is_success() {
+        let status = response.status();
+        let error_text = response.text().await.unwrap_or_else(|_| "Unknown error".to_string());
+        return Err(format!("Google Drive API error ({}): {}. Please check your OAuth access token.", status, error_text));
+    }
+
+    let file_list: GoogleDriveFileList = response
+        .json()
+        .await
+        .map_err(|e| format!("Failed to parse folder search response: {}", e))?;
+
+    // If folder exists, return its ID
+    if let Some(folder) = file_list.files.first() {
+        log::info!("Found existing ClipForge folder: {}", folder.id);
+        return Ok(folder.id.clone());
+    }
+
+    // Create new folder
+    log::info!("Creating new ClipForge folder");
+    let folder_metadata = GoogleDriveFileMetadata {
+        name: "ClipForge".to_string(),
+        parents: None,
+        mime_type: Some("application/vnd.google-apps.folder".to_string()),
+    };
+
+    let create_response = client
+        .post(search_url)
+        .query(&[("fields", "id,name")])
+        .header("Authorization", format!("Bearer {}", api_key))
+        .header("Content-Type", "application/json")
+        .json(&folder_metadata)
+        .send()
+        .await
+        .map_err(|e| format!("Failed to create ClipForge folder: {}", e))?;
+
+    if !create_response.status().is_success() {
+        let status = create_response.status();
+        let error_text = create_response.text().await.unwrap_or_else(|_| "Unknown error".to_string());
+        return Err(format!("Failed to create folder ({}): {}", status, error_text));
+    }
+
+    let created_folder: GoogleDriveFile = create_response
+        .json()
+        .await
+        .map_err(|e| format!("Failed to parse folder creation response: {}", e))?;
+
+    log::info!("Created ClipForge folder: {}", created_folder.id);
+    Ok(created_folder.id)
+}
+
+// Upload file to Google Drive
+async fn upload_to_google_drive(file_path: &str, filename: &str, folder_id: &str, api_key: &str, mime_type: &str) -> Result<String, String> {
+    log::info!("Uploading {} to Google Drive folder {}", filename, folder_id);
+
+    let client = reqwest::Client::new();
+
+    // Read the video file
+    let file_data = std::fs::read(file_path)
+        .map_err(|e| format!("Failed to read video file: {}", e))?;
+
+    let file_size = file_data.len();
+    log::info!("File size: {} bytes ({:.2} MB)", file_size, file_size as f64 / 1_048_576.0);
+
+    // Create metadata
+    let metadata = GoogleDriveFileMetadata {
+        name: filename.to_string(),
+        parents: Some(vec![folder_id.to_string()]),
+        mime_type: Some(mime_type.to_string()),
+    };
+
+    let metadata_json = serde_json::to_string(&metadata)
+        .map_err(|e| format!("Failed to serialize metadata: {}", e))?;
+
+    // Create multipart upload
+    let metadata_part = reqwest::multipart::Part::text(metadata_json)
+        .mime_str("application/json")
+        .map_err(|e| format!("Failed to create metadata part: {}", e))?;
+
+    let file_part = reqwest::multipart::Part::bytes(file_data)
+        .file_name(filename.to_string())
+        .mime_str(mime_type)
+        .map_err(|e| format!("Failed to create file part: {}", e))?;
+
+    let form = reqwest::multipart::Form::new()
+        .part("metadata", metadata_part)
+        .part("file", file_part);
+
+    // Upload to Google Drive (multipart upload)
+    let upload_url = "https://www.googleapis.com/upload/drive/v3/files?uploadType=multipart&fields=id,name,webViewLink";
+
+    log::info!("Sending upload request...");
+    let response = client
+        .post(upload_url)
+        .header("Authorization", format!("Bearer {}", api_key))
+        .multipart(form)
+        .send()
+        .await
+        .map_err(|e| format!("Failed to upload to Google Drive: {}", e))?;
+
+    if !response.status().is_success() {
+        let status = response.status();
+        let error_text = response.text().await.unwrap_or_else(|_| "Unknown error".to_string());
+        return Err(format!("Upload failed ({}): {}", status, error_text));
+    }
+
+    let upload_response: serde_json::Value = response
+        .json()
+        .await
+        .map_err(|e| format!("Failed to parse upload response: {}", e))?;
+
+    let file_id = upload_response["id"]
+        .as_str()
+        .ok_or("No file ID in response")?
+        .to_string();
+
+    let web_view_link = upload_response["webViewLink"]
+        .as_str()
+        .unwrap_or("No link available");
+
+    log::info!("Upload successful! File ID: {}, Link: {}", file_id, web_view_link);
+    Ok(web_view_link.to_string())
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GoogleDriveExportOptions {
+    input_path: String,
+    filename: String,
+    api_key: String,
+    trim_start: Option<f64>,
+    trim_end: Option<f64>,
+    preset: Option<ExportPreset>,
+}
+
+#[tauri::command]
+async fn export_to_google_drive(options: GoogleDriveExportOptions, window: tauri::Window) -> Result<String, String> {
+    log::info!("Starting Google Drive export: {}", options.filename);
+
+    // First, export the video locally to a temp file
+    let temp_dir = std::env::temp_dir();
+    let temp_output = temp_dir.join(format!("clipforge_gdrive_{}", options.filename));
+    let temp_output_str = temp_output.to_string_lossy().to_string();
+
+    log::info!("Exporting to temp file: {}", temp_output_str);
+
+    // Export video locally first
+    let preset = resolve_export_preset(options.preset.clone(), DEFAULT_EXPORT_PRESET)?;
+    let mime_type = container_mime_type(&preset.container);
+    export_video(ExportOptions {
+        input_path: options.input_path.clone(),
+        output_path: temp_output_str.clone(),
+        trim_start: options.trim_start,
+        trim_end: options.trim_end,
+        target_loudness: None,
+        preset: Some(preset),
+    }, window)?;
+
+    // Get or create ClipForge folder
+    let folder_id = get_or_create_clipforge_folder(&options.api_key).await?;
+
+    // Upload to Google Drive
+    let drive_link = upload_to_google_drive(&temp_output_str, &options.filename, &folder_id, &options.api_key, mime_type).await?;
+
+    // Clean up temp file
+    let _ = std::fs::remove_file(temp_output);
+
+    log::info!("Google Drive export complete: {}", drive_link);
+    Ok(drive_link)
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GoogleDriveMultiClipExportOptions {
+    clips: Vec<ClipSegment>,
+    filename: String,
+    api_key: String,
+    preset: Option<ExportPreset>,
+}
+
+#[tauri::command]
+async fn export_multi_clip_to_google_drive(options: GoogleDriveMultiClipExportOptions, window: tauri::Window) -> Result<String, String> {
+    log::info!("Starting multi-clip Google Drive export: {}", options.filename);
+
+    // First, export the multi-clip video locally to a temp file
+    let temp_dir = std::env::temp_dir();
+    let temp_output = temp_dir.join(format!("clipforge_gdrive_{}", options.filename));
+    let temp_output_str = temp_output.to_string_lossy().to_string();
+
+    log::info!("Exporting multi-clip to temp file: {}", temp_output_str);
+
+    // Export multi-clip video locally first
+    let preset = resolve_export_preset(options.preset.clone(), DEFAULT_MERGE_PRESET)?;
+    let mime_type = container_mime_type(&preset.container);
+    export_multi_clip(MultiClipExportOptions {
+        clips: options.clips,
+        output_path: temp_output_str.clone(),
+        target_loudness: None,
+        preset: Some(preset),
+    }, window)?;
+
+    // Get or create ClipForge folder
+    let folder_id = get_or_create_clipforge_folder(&options.api_key).await?;
+
+    // Upload to Google Drive
+    let drive_link = upload_to_google_drive(&temp_output_str, &options.filename, &folder_id, &options.api_key, mime_type).await?;
+
+    // Clean up temp file
+    let _ = std::fs::remove_file(temp_output);
+
+    log::info!("Google Drive multi-clip export complete: {}", drive_link);
+    Ok(drive_link)
+}
+
+// ===== TRANSCRIPTION =====
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TranscriptSegment {
+    start: f64,
+    end: f64,
+    text: String,
+    confidence: f64,
+    #[serde(default)]
+    is_filler: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TranscriptionResult {
+    segments: Vec<TranscriptSegment>,
+    full_text: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct OpenAIWord {
+    word: String,
+    start: f64,
+    end: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct OpenAISegment {
+    id: u32,
+    start: f64,
+    end: f64,
+    text: String,
+    #[serde(default)]
+    tokens: Vec<u32>,
+    temperature: f64,
+    avg_logprob: f64,
+    compression_ratio: f64,
+    no_speech_prob: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct OpenAITranscriptionResponse {
+    task: String,
+    language: String,
+    duration: f64,
+    text: String,
+    segments: Vec<OpenAISegment>,
+    #[serde(default)]
+    words: Vec<OpenAIWord>,
+}
+
+// Extract audio from video file
+// audio_stream selects a specific source stream by absolute index (default: FFmpeg's pick)
+fn extract_audio(video_path: &str, audio_stream: Option<u32>) -> Result<PathBuf, String> {
+    log::info!("Checking for audio stream in: {}", video_path);
+
+    // First, check if the video has an audio stream using ffprobe
+    let ffprobe = find_ffprobe();
+    let probe_output = Command::new(&ffprobe)
+        .args(&[
+            "-v", "quiet",
+            "-print_format", "json",
+            "-show_streams",
+            "-select_streams", "a",  // Only select audio streams
+            video_path
+        ])
+        .output()
+        .map_err(|e| format!("Failed to execute ffprobe: {}", e))?;
+
+    let json_str = String::from_utf8_lossy(&probe_output.stdout);
+    let json: serde_json::Value = serde_json::from_str(&json_str)
+        .map_err(|e| format!("Failed to parse ffprobe output: {}", e))?;
+
+    // Check if there are any audio streams
+    let audio_indexes: Vec<u64> = json["streams"].as_array()
+        .map(|arr| arr.iter().filter_map(|s| s["index"].as_u64()).collect())
+        .unwrap_or_default();
+
+    if audio_indexes.is_empty() {
+        log::warn!("No audio stream found in video: {}", video_path);
+        return Err("This video has no audio track. Please select a video with audio to transcribe.".to_string());
+    }
+
+    if let Some(index) = audio_stream {
+        if !audio_indexes.contains(&(index as u64)) {
+            return Err(format!("Audio stream {} not found (available audio streams: {:?})", index, audio_indexes));
+        }
+    }
+
+    log::info!("Found {} audio stream(s), extracting audio (stream: {:?})", audio_indexes.len(), audio_stream);
+
+    let temp_dir = std::env::temp_dir();
+    let audio_path = temp_dir.join("clipforge_audio.mp3");
+
+    let ffmpeg = find_ffmpeg();
+    let mut cmd = Command::new(&ffmpeg);
+    cmd.arg("-i").arg(video_path);
+    if let Some(index) = audio_stream {
+        cmd.arg("-map").arg(format!("0:{}", index));
+    }
+    let output = cmd
+        .args(&[
+            "-vn",  // No video
+            "-acodec", "libmp3lame",
+            "-ab", "192k",
+            "-ar", "44100",
+            "-af", "aresample=async=1:first_pts=0",  // Ensure audio starts at 0 and preserves duration
+            "-y",
+            audio_path.to_str().unwrap()
+        ])
+        .output()
+        .map_err(|e| format!("Failed to execute FFmpeg: {}", e))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("FFmpeg audio extraction failed: {}", stderr));
+    }
+
+    // Verify extracted audio duration matches video
+    let ffprobe = find_ffprobe();
+    let audio_probe = Command::new(&ffprobe)
+        .args(&[
+            "-v", "quiet",
+            "-print_format", "json",
+            "-show_format",
+            audio_path.to_str().unwrap()
+        ])
+        .output()
+        .map_err(|e| format!("Failed to probe audio: {}", e))?;
+
+    let audio_json_str = String::from_utf8_lossy(&audio_probe.stdout);
+    if let Ok(audio_json) = serde_json::from_str::<serde_json::Value>(&audio_json_str) {
+        if let Some(duration_str) = audio_json["format"]["duration"].as_str() {
+            if let Ok(audio_duration) = duration_str.parse::<f64>() {
+                log::info!("Extracted audio duration: {:.2}s", audio_duration);
+            }
         }
     }
 
-    // Output settings: H.264/MP4 optimized for smooth browser playback
+    log::info!("Audio extracted to: {:?}", audio_path);
+    Ok(audio_path)
+}
 
-    // Video filtering depends on mode:
-    // - For screen/webcam: simple scale filter
-    // - For combo: overlay webcam on screen (picture-in-picture)
-    #[cfg(target_os = "macos")]
-    let is_combo_mode = options.mode == "combo";
+// Filler words flagged in transcripts and extracted subtitles
+const FILLER_WORDS: [&str; 9] = ["um", "uh", "like", "so", "basically", "actually", "literally", "you know", "i mean"];
 
-    #[cfg(not(target_os = "macos"))]
-    let is_combo_mode = false;
+// Helper function to check if subtitle text contains filler words
+// Matches whole words only, so "umbrella" or "also" don't count as "um" or "so"
+fn is_filler_text(text: &str) -> bool {
+    let lower = text.to_lowercase();
+    let words: Vec<&str> = lower
+        .split(|c: char| !c.is_alphanumeric() && c != '\'')
+        .filter(|w| !w.is_empty())
+        .collect();
 
-    if !is_combo_mode {
-        // Simple scale for screen and webcam modes - 720p to reduce CPU load
-        cmd.arg("-vf").arg("scale=1280:720:force_original_aspect_ratio=decrease:force_divisible_by=2");
-    }
+    FILLER_WORDS.iter().any(|filler| {
+        let filler: Vec<&str> = filler.split(' ').collect();
+        words.windows(filler.len()).any(|window| window == filler.as_slice())
+    })
+}
 
-    #[cfg(target_os = "macos")]
-    {
-        // Use hardware encoding on macOS (VideoToolbox) - offloads video to GPU
-        cmd.arg("-c:v").arg("h264_videotoolbox")
-            .arg("-b:v").arg("5000k") // 5 Mbps bitrate for good quality
-            .arg("-allow_sw").arg("1") // Fallback to software if hardware unavailable
-            .arg("-require_sw").arg("0"); // Prefer hardware encoding
-    }
+// Transcribe audio using OpenAI Whisper API
+async fn transcribe_with_openai(audio_path: &PathBuf, api_key: &str) -> Result<TranscriptionResult, String> {
+    log::info!("Transcribing audio with OpenAI Whisper: {:?}", audio_path);
 
-    #[cfg(not(target_os = "macos"))]
-    {
-        // Software encoding for non-macOS platforms
-        cmd.arg("-c:v").arg("libx264")
-            .arg("-preset").arg("ultrafast")
-            .arg("-crf").arg("23");
-    }
+    let client = reqwest::Client::new();
 
-    cmd
-        .arg("-pix_fmt").arg("yuv420p") // Standard pixel format
-        // CRITICAL: Force constant frame rate at 15fps
-        .arg("-r").arg("15")
-        .arg("-vsync").arg("cfr")
-        .arg("-g").arg("15") // GOP size = framerate for consistent keyframes
-        .arg("-bf").arg("0") // No B-frames for simpler decode
-        // Web-optimized MP4 container
-        .arg("-movflags").arg("+faststart") // Move moov atom to beginning for web streaming
-        .arg("-video_track_timescale").arg("90000"); // Standard MPEG timescale
+    // Read the audio file
+    let file_data = std::fs::read(audio_path)
+        .map_err(|e| format!("Failed to read audio file: {}", e))?;
 
-    // Add audio encoding based on audio settings
-    let audio_settings = options.audio_settings.as_ref();
-    let mic_enabled = audio_settings.map(|s| s.microphone_enabled).unwrap_or(true);
+    // Get filename for the multipart form
+    let filename = audio_path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or("audio.mp3");
 
-    // Only enable system audio if enabled AND a valid device is selected
-    let sys_audio_enabled = audio_settings
-        .map(|s| {
-            s.system_audio_enabled
-            && !s.system_audio_device.is_empty()
-            && s.system_audio_device != "none"
-        })
-        .unwrap_or(false);
+    // Create multipart form
+    let file_part = reqwest::multipart::Part::bytes(file_data)
+        .file_name(filename.to_string())
+        .mime_str("audio/mpeg")
+        .map_err(|e| format!("Failed to create file part: {}", e))?;
 
-    let has_audio = mic_enabled || sys_audio_enabled;
+    let form = reqwest::multipart::Form::new()
+        .part("file", file_part)
+        .text("model", "whisper-1")
+        .text("response_format", "verbose_json")
+        .text("timestamp_granularities[]", "segment");
 
-    if has_audio {
-        let audio_quality = audio_settings
-            .map(|s| s.audio_quality.as_str())
-            .unwrap_or("standard");
+    // Send request to OpenAI
+    let response = client
+        .post("https://api.openai.com/v1/audio/transcriptions")
+        .header("Authorization", format!("Bearer {}", api_key))
+        .multipart(form)
+        .send()
+        .await
+        .map_err(|e| format!("Failed to send transcription request: {}", e))?;
 
-        // Set bitrate based on quality (increased for better audio quality and less crackling)
-        let bitrate = match audio_quality {
-            "voice" => "96k",     // Increased from 64k
-            "standard" => "192k", // Increased from 128k (test 4 winner!)
-            "high" => "256k",     // Unchanged
-            _ => "192k",          // Default to standard
-        };
+    if !response.status().is_success() {
+        let status = response.status();
+        let error_text = response.text().await.unwrap_or_else(|_| "Unknown error".to_string());
+        return Err(format!("OpenAI API request failed ({}): {}", status, error_text));
+    }
 
-        // Check if we're mixing dual audio (mic + system) or doing video overlay (combo mode)
-        #[cfg(target_os = "macos")]
-        if options.mode == "combo" {
-            // Combo mode: overlay webcam on screen + handle audio
-            // Inputs: 0=screen video, 1=webcam video, 2=mic audio (optional), 3=system audio (optional)
+    let openai_response: OpenAITranscriptionResponse = response
+        .json()
+        .await
+        .map_err(|e| format!("Failed to parse OpenAI response: {}", e))?;
 
-            // Create picture-in-picture effect: webcam in bottom-right corner
-            // Use fps filter and setpts to ensure smooth, synchronized playback
-            // Scale screen to 1280x720 (720p), scale webcam to 213x120, overlay at bottom-right with 20px margin
-            let video_filter = "[0:v]fps=15,setpts=PTS-STARTPTS,scale=1280:720:force_original_aspect_ratio=decrease:force_divisible_by=2[screen];\
-                               [1:v]fps=15,setpts=PTS-STARTPTS,scale=213:120:force_original_aspect_ratio=decrease:force_divisible_by=2[webcam];\
-                               [screen][webcam]overlay=W-w-20:H-h-20:shortest=1[vout]";
+    log::info!("Transcription completed. Duration: {}s, Language: {}",
+               openai_response.duration, openai_response.language);
 
-            if mic_enabled && sys_audio_enabled {
-                // Both audio sources: mix them with simple timestamp normalization
-                // Using asetpts instead of aresample to avoid resampling overhead
-                let filter = format!("{};[2:a]asetpts=PTS-STARTPTS[a1];[3:a]asetpts=PTS-STARTPTS[a2];[a1][a2]amix=inputs=2:duration=first:dropout_transition=2[aout]", video_filter);
-                cmd.arg("-filter_complex").arg(&filter)
-                    .arg("-map").arg("[vout]")    // Overlayed video
-                    .arg("-map").arg("[aout]");   // Mixed audio
-            } else if mic_enabled || sys_audio_enabled {
-                // Single audio source - simple timestamp normalization
-                let filter = format!("{};[2:a]asetpts=PTS-STARTPTS[aout]", video_filter);
-                cmd.arg("-filter_complex").arg(&filter)
-                    .arg("-map").arg("[vout]")    // Overlayed video
-                    .arg("-map").arg("[aout]");   // Synced audio
-            } else {
-                // No audio
-                cmd.arg("-filter_complex").arg(video_filter)
-                    .arg("-map").arg("[vout]");   // Overlayed video only
-            }
-        } else if options.mode == "screen" && mic_enabled && sys_audio_enabled {
-            // Screen mode with dual audio mixing: mix mic (input 1) + system (input 2)
-            // Using asetpts instead of aresample to avoid resampling overhead
-            cmd.arg("-filter_complex")
-                .arg("[1:a]asetpts=PTS-STARTPTS[a1];[2:a]asetpts=PTS-STARTPTS[a2];[a1][a2]amix=inputs=2:duration=first:dropout_transition=2[aout]")
-                .arg("-map").arg("0:v")  // Video from input 0 (screen)
-                .arg("-map").arg("[aout]"); // Mixed audio output
-        }
+    // Convert OpenAI segments to our format
+    let segments: Vec<TranscriptSegment> = openai_response.segments
+        .iter()
+        .enumerate()
+        .map(|(i, seg)| {
+            log::info!("Segment {}: {:.2}s - {:.2}s: \"{}\"", i, seg.start, seg.end, seg.text.trim());
 
-        // Audio encoding settings (optimized for quality and reduced crackling)
-        cmd.arg("-c:a").arg("aac")
-            .arg("-b:a").arg(bitrate)
-            .arg("-profile:a").arg("aac_low")  // AAC-LC profile for better quality
-            .arg("-ar").arg("48000")
-            .arg("-ac").arg("2"); // Stereo output
+            // Check if segment contains filler words
+            let is_filler = FILLER_WORDS.iter().any(|filler| {
+                seg.text.to_lowercase().contains(filler)
+            });
 
-        // For simple captures (no filter_complex), add simple timestamp normalization
-        // Using asetpts instead of aresample to avoid resampling overhead
-        #[cfg(target_os = "macos")]
-        if options.mode != "combo" && !(options.mode == "screen" && mic_enabled && sys_audio_enabled) {
-            // Only add audio filter if we're not already using filter_complex
-            cmd.arg("-af").arg("asetpts=PTS-STARTPTS");
-        }
-    }
+            TranscriptSegment {
+                start: seg.start,
+                end: seg.end,
+                text: seg.text.trim().to_string(),
+                confidence: (-seg.avg_logprob).min(1.0).max(0.0), // Convert logprob to confidence-like score
+                is_filler,
+            }
+        })
+        .collect();
 
-    cmd.arg("-y")
-        .arg(&options.output_path)
-        .stdout(Stdio::null())
-        .stderr(Stdio::piped()); // Capture stderr for diagnostics
+    log::info!("Processed {} segments", segments.len());
 
-    log::info!("Starting FFmpeg process: {:?}", cmd);
+    Ok(TranscriptionResult {
+        segments,
+        full_text: openai_response.text,
+    })
+}
 
-    let mut child = cmd.spawn().map_err(|e| {
-        format!("Failed to start FFmpeg recording: {}. Make sure FFmpeg is installed.", e)
-    })?;
+#[tauri::command]
+async fn transcribe_video(video_path: String, api_key: String, audio_stream: Option<u32>) -> Result<TranscriptionResult, String> {
+    log::info!("Starting transcription for video: {} (audio stream: {:?})", video_path, audio_stream);
 
-    // Capture stderr for continuous monitoring
-    if let Some(stderr) = child.stderr.take() {
-        // Spawn a thread to read and log FFmpeg output
-        std::thread::spawn(move || {
-            use std::io::{BufRead, BufReader};
-            let reader = BufReader::new(stderr);
-            for line in reader.lines() {
-                if let Ok(line) = line {
-                    // Log important messages
-                    if line.contains("drop") || line.contains("discontinuity") ||
-                       line.contains("buffer") || line.contains("queue") ||
-                       line.contains("audio") || line.contains("overrun") ||
-                       line.contains("underrun") || line.contains("pts") {
-                        log::warn!("[FFmpeg Audio] {}", line);
-                    }
-                    // Also log at debug level for full capture
-                    log::debug!("[FFmpeg] {}", line);
-                }
-            }
-        });
-    }
+    // Extract audio from video
+    let audio_path = extract_audio(&video_path, audio_stream)?;
 
-    // Give FFmpeg a moment to start and validate inputs
-    std::thread::sleep(std::time::Duration::from_millis(500));
+    // Transcribe with OpenAI Whisper
+    let result = transcribe_with_openai(&audio_path, &api_key).await?;
 
-    // Check if the process is still running (didn't immediately fail)
-    match child.try_wait() {
-        Ok(Some(status)) => {
-            return Err(format!("FFmpeg exited immediately with status: {}", status));
-        }
-        Ok(None) => {
-            // Process is still running - good!
-            log::info!("FFmpeg process started successfully");
-        }
-        Err(e) => {
-            log::warn!("Could not check FFmpeg status: {}", e);
-        }
-    }
+    // Clean up temp audio file
+    let _ = std::fs::remove_file(audio_path);
+
+    log::info!("Transcription complete!");
+    Ok(result)
+}
 
-    *recording_process = Some(child);
-    Ok("Recording started".to_string())
+// ===== EMBEDDED SUBTITLES AND CHAPTERS =====
+
+// Helper function to parse an SRT timestamp ("00:01:02,345") into seconds
+fn parse_srt_timestamp(value: &str) -> Option<f64> {
+    let value = value.trim().replace(',', ".");
+    let mut parts = value.split(':');
+    let hours: f64 = parts.next()?.parse().ok()?;
+    let minutes: f64 = parts.next()?.parse().ok()?;
+    let seconds: f64 = parts.next()?.parse().ok()?;
+    Some(hours * 3600.0 + minutes * 60.0 + seconds)
 }
 
-#[tauri::command]
-fn stop_recording(state: State<RecordingState>) -> Result<String, String> {
-    log::info!("Stopping recording");
+// Helper function to measure a well-formed markup tag at the start of `text`, if there is one
+// HTML-style tags start with a letter or "/" (<i>, </i>, <font ...>), ASS override blocks with a
+// backslash ({\an8}, {\i1}); anything else, like "x < 5" or an unclosed "{", is caption text
+fn subtitle_tag_len(text: &str) -> Option<usize> {
+    let (closing, valid_start): (char, fn(char) -> bool) = match text.chars().next()? {
+        '<' => ('>', |c| c.is_ascii_alphabetic() || c == '/'),
+        '{' => ('}', |c| c == '\\'),
+        _ => return None,
+    };
 
-    let mut recording_process = state.process.lock().unwrap();
+    let body = &text[1..];
+    if !body.chars().next().map(valid_start).unwrap_or(false) {
+        return None;
+    }
+    let end = body.find([closing, '<', '{'])?;
+    if !body[end..].starts_with(closing) {
+        return None;
+    }
+    Some(end + 2)
+}
 
-    if let Some(mut child) = recording_process.take() {
-        // Send SIGINT to FFmpeg to gracefully stop
-        #[cfg(unix)]
-        {
-            unsafe {
-                libc::kill(child.id() as i32, libc::SIGINT);
+// Helper function to strip formatting from subtitle text
+// Removes HTML-style tags (<i>, <font ...>) and ASS override blocks ({\an8}, {\i1}); stray
+// "<" or "{" characters that don't open a well-formed tag are kept as text
+fn strip_subtitle_markup(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(c) = rest.chars().next() {
+        match subtitle_tag_len(rest) {
+            Some(len) => rest = &rest[len..],
+            None => {
+                result.push(c);
+                rest = &rest[c.len_utf8()..];
             }
         }
+    }
 
-        #[cfg(not(unix))]
-        {
-            let _ = child.kill();
-        }
-
-        // Wait for process to finish
-        match child.wait() {
-            Ok(status) => {
-                log::info!("Recording stopped with status: {:?}", status);
+    result.replace("\\N", " ").replace("\\n", " ")
+}
 
-                // FFmpeg exits with 255 when stopped via SIGINT - this is NORMAL and expected
-                // Only check for errors if the exit code indicates a real failure
-                #[cfg(unix)]
-                let is_normal_exit = {
-                    use std::os::unix::process::ExitStatusExt;
-                    status.code() == Some(255) || status.signal() == Some(2) || status.success()
-                };
+// Helper function to parse SRT text into transcript segments
+fn parse_srt(srt: &str) -> Vec<TranscriptSegment> {
+    let normalized = srt.replace("\r\n", "\n");
+    let mut segments = Vec::new();
 
-                #[cfg(not(unix))]
-                let is_normal_exit = status.success();
+    for block in normalized.split("\n\n") {
+        let mut lines = block.lines().map(|l| l.trim()).filter(|l| !l.is_empty()).peekable();
 
-                if !is_normal_exit {
-                    if let Some(mut stderr) = child.stderr.take() {
-                        use std::io::Read;
-                        let mut error_msg = String::new();
-                        if stderr.read_to_string(&mut error_msg).is_ok() {
-                            log::error!("FFmpeg stderr: {}", error_msg);
+        // Skip the cue number if present
+        if lines.peek().map(|l| l.chars().all(|c| c.is_ascii_digit())).unwrap_or(false) {
+            lines.next();
+        }
 
-                            // Check for common permission errors
-                            if error_msg.contains("not permitted") || error_msg.contains("Operation not permitted") {
-                                return Err("Recording failed: Screen recording permission denied. Please grant permission in System Settings > Privacy & Security > Screen Recording".to_string());
-                            }
-                            if error_msg.contains("Invalid device") {
-                                return Err("Recording failed: Invalid audio device selected".to_string());
-                            }
-                            if error_msg.contains("Error") && !error_msg.contains("Exiting normally") {
-                                return Err(format!("Recording failed: {}", error_msg.lines().find(|l| l.contains("Error")).unwrap_or("Unknown error")));
-                            }
-                        }
-                    }
-                    return Err(format!("Recording failed with status: {}. Check the logs for details.", status));
-                }
+        let timing = match lines.next() {
+            Some(line) if line.contains("-->") => line,
+            _ => continue,
+        };
 
-                // Give FFmpeg a moment to flush and close the file
-                std::thread::sleep(std::time::Duration::from_millis(500));
+        let (start, end) = match timing.split_once("-->") {
+            // Cue settings may follow the end time ("00:00:02,000 X1:40")
+            Some((start, end)) => (
+                parse_srt_timestamp(start),
+                end.split_whitespace().next().and_then(parse_srt_timestamp),
+            ),
+            None => continue,
+        };
 
-                log::info!("Recording completed successfully");
-                Ok("Recording stopped".to_string())
-            }
-            Err(e) => {
-                log::error!("Error waiting for FFmpeg process: {}", e);
-                Err(format!("Failed to stop recording cleanly: {}", e))
+        let text = lines.map(strip_subtitle_markup).collect::<Vec<_>>().join(" ");
+        let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+
+        if let (Some(start), Some(end)) = (start, end) {
+            if !text.is_empty() {
+                segments.push(TranscriptSegment {
+                    start,
+                    end,
+                    is_filler: is_filler_text(&text),
+                    text,
+                    confidence: 1.0, // Authored captions, not a recognition guess
+                });
             }
         }
-    } else {
-        Err("No active recording".to_string())
     }
+
+    segments
 }
 
+// Extract an embedded text subtitle track into the same structure transcribe_video returns
 #[tauri::command]
-fn start_test_recording(options: StartTestRecordingOptions, state: State<RecordingState>) -> Result<String, String> {
-    log::info!("Starting test recording with custom settings: {:?}", options);
+async fn extract_subtitles(path: String, stream_index: u32) -> Result<TranscriptionResult, String> {
+    log::info!("Extracting subtitle stream {} from: {}", stream_index, path);
 
-    let mut recording_process = state.process.lock().unwrap();
-    if recording_process.is_some() {
-        return Err("Recording already in progress".to_string());
+    let probe = probe_video_metadata(&path)?;
+    let stream = probe.subtitle_streams
+        .iter()
+        .find(|s| s.index == stream_index)
+        .ok_or_else(|| format!("No subtitle stream with index {}", stream_index))?;
+
+    if !stream.is_text {
+        return Err(format!(
+            "Subtitle stream {} is image-based ({}) and can't be converted to text",
+            stream_index,
+            stream.codec.as_deref().unwrap_or("unknown")
+        ));
     }
 
-    // Build FFmpeg command with test settings
-    let ffmpeg = find_ffmpeg();
-    let mut cmd = Command::new(&ffmpeg);
+    // Let FFmpeg convert mov_text/ass/webvtt/subrip to SRT and parse that one format
+    // Demuxing reads the whole container, so it runs off the main thread
+    let output = tauri::async_runtime::spawn_blocking(move || {
+        let ffmpeg = find_ffmpeg();
+        Command::new(&ffmpeg)
+            .arg("-i").arg(&path)
+            .arg("-map").arg(format!("0:{}", stream_index))
+            .arg("-c:s").arg("srt")
+            .arg("-f").arg("srt")
+            .arg("-")
+            .output()
+    })
+    .await
+    .map_err(|e| format!("Subtitle extraction task failed: {}", e))?
+    .map_err(|e| format!("Failed to execute FFmpeg for subtitle extraction: {}", e))?;
 
-    // Apply test settings for buffer and logging
-    cmd.arg("-loglevel").arg("verbose")
-        .arg("-rtbufsize").arg(&options.test_settings.rtbufsize);
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        log::error!("FFmpeg subtitle extraction failed: {}", stderr);
+        return Err(format!("Failed to extract subtitles: {}", stderr));
+    }
 
-    #[cfg(target_os = "macos")]
-    {
-        // Get audio settings
-        let audio_settings = options.audio_settings.as_ref();
-        let mic_enabled = audio_settings.map(|s| s.microphone_enabled).unwrap_or(true);
-        let mic_device = audio_settings
-            .and_then(|s| {
-                if s.microphone_device == "default" {
-                    Some("0")
-                } else {
-                    Some(s.microphone_device.as_str())
-                }
-            })
-            .unwrap_or("0");
+    let segments = parse_srt(&String::from_utf8_lossy(&output.stdout));
+    let full_text = segments.iter()
+        .map(|s| s.text.as_str())
+        .collect::<Vec<_>>()
+        .join(" ");
 
-        // Screen capture with test settings
-        if options.mode == "screen" {
-            cmd.arg("-f").arg("avfoundation")
-                .arg("-capture_cursor").arg("1")
-                .arg("-framerate").arg("30");
+    log::info!("Extracted {} subtitle segments", segments.len());
+    Ok(TranscriptionResult { segments, full_text })
+}
 
-            // Apply wallclock timestamp test setting
-            if options.test_settings.use_wallclock_as_timestamps {
-                cmd.arg("-use_wallclock_as_timestamps").arg("1");
-            }
+// List chapter markers embedded in a file
+#[tauri::command]
+fn extract_chapters(path: String) -> Result<Vec<ChapterMarker>, String> {
+    log::info!("Extracting chapters from: {}", path);
+    Ok(probe_video_metadata(&path)?.chapters)
+}
 
-            // Input
-            if mic_enabled {
-                cmd.arg("-i").arg(&format!("1:{}", mic_device));
-            } else {
-                cmd.arg("-i").arg("1:none");
-            }
-        }
+#[cfg_attr(mobile, tauri::mobile_entry_point)]
+pub fn run() {
+  tauri::Builder::default()
+    .plugin(tauri_plugin_dialog::init())
+    .plugin(tauri_plugin_shell::init())
+    .manage(RecordingState {
+      process: Mutex::new(None),
+    })
+    .manage(NormalizeState {
+      jobs: Mutex::new(HashMap::new()),
+    })
+    .manage(ProxyState {
+      proxies: Mutex::new(HashMap::new()),
+    })
+    .manage(ExportJobs {
+      jobs: Mutex::new(HashMap::new()),
+    })
+    .invoke_handler(tauri::generate_handler![
+      import_video,
+      import_media_batch,
+      normalize_frame_rate,
+      cancel_normalize_frame_rate,
+      export_video,
+      export_multi_clip,
+      export_to_google_drive,
+      export_multi_clip_to_google_drive,
+      start_export,
+      start_multi_clip_export,
+      cancel_export,
+      enqueue_export,
+      enqueue_multi_clip_export,
+      list_export_queue,
+      set_export_queue_concurrency,
+      pause_export_queue,
+      resume_export_queue,
+      move_export_queue_job,
+      retry_export_queue_job,
+      remove_export_queue_job,
+      list_export_presets,
+      save_export_preset,
+      delete_export_preset,
+      start_recording,
+      stop_recording,
+      start_test_recording,
+      #[cfg(target_os = "macos")]
+      start_screencapturekit_recording,
+      open_in_native_player,
+      list_audio_devices,
+      transcribe_video,
+      generate_waveform,
+      get_waveform_range,
+      generate_channel_waveforms,
+      analyze_audio_problems,
+      generate_spectrogram,
+      generate_proxy,
+      generate_filmstrip,
+      detect_scenes,
+      measure_loudness,
+      split_at_scene_changes,
+      detect_silence,
+      remove_silence,
+      check_missing_media,
+      relink_media,
+      extract_subtitles,
+      extract_chapters,
+      get_media_cache_stats,
+      clear_media_cache
+    ])
+    .register_uri_scheme_protocol("video", |ctx, request| {
+      use tauri::http::{Response, StatusCode};
+      use std::io::{Seek, SeekFrom, Read};
 
-        // Thread queue size
-        cmd.arg("-thread_queue_size").arg(options.test_settings.thread_queue_size.to_string());
+      // Custom protocol handler for serving video files with proper HTTP Range support
+      // This is critical for video streaming performance
 
-        // Video encoding
-        cmd.arg("-c:v").arg("libx264")
-            .arg("-preset").arg("ultrafast")
-            .arg("-pix_fmt").arg("yuv420p");
+      let uri = request.uri().to_string();
+      // log::info!("Video protocol request: {}", uri); // Disabled for performance
 
-        // Audio encoding
-        cmd.arg("-c:a").arg("aac")
-            .arg("-b:a").arg("128k")
-            .arg("-ar").arg("48000")
-            .arg("-ac").arg("2");
+      // Extract the file path from the URI (format: video://localhost/<path>)
+      let path = uri
+        .strip_prefix("video://localhost")
+        .unwrap_or(&uri);
 
-        // Apply audio filter test setting if provided
-        if !options.test_settings.audio_filter.is_empty() && mic_enabled {
-            cmd.arg("-af").arg(&options.test_settings.audio_filter);
+      // Preview large sources through their proxy if one has been generated
+      // Cache eviction can remove a proxy, in which case the original is served again
+      let proxy_path = {
+        let state = ctx.app_handle().state::<ProxyState>();
+        let mut proxies = state.proxies.lock().unwrap();
+        match proxies.get(path) {
+          Some(proxy) if std::path::Path::new(proxy).exists() => Some(proxy.clone()),
+          Some(_) => {
+            proxies.remove(path);
+            None
+          }
+          None => None,
         }
-    }
+      };
+      let path = proxy_path.as_deref().unwrap_or(path);
 
-    cmd.arg("-y")
-        .arg(&options.output_path)
-        .stdout(Stdio::null())
-        .stderr(Stdio::piped());
+      // log::info!("Attempting to serve video file: {}", path); // Disabled for performance
 
-    log::info!("Starting test recording FFmpeg process: {:?}", cmd);
+      // Open the file
+      let mut file = match std::fs::File::open(path) {
+        Ok(f) => f,
+        Err(e) => {
+          log::error!("Failed to open video file: {}", e);
+          return Response::builder()
+            .header("Access-Control-Allow-Origin", "*")
+            .status(StatusCode::NOT_FOUND)
+            .body(format!("Video not found: {}", e).into_bytes())
+            .unwrap();
+        }
+      };
 
-    let mut child = cmd.spawn().map_err(|e| {
-        format!("Failed to start test recording: {}. Make sure FFmpeg is installed.", e)
-    })?;
+      // Get file size
+      let file_size = match file.metadata() {
+        Ok(metadata) => metadata.len(),
+        Err(e) => {
+          log::error!("Failed to get file metadata: {}", e);
+          return Response::builder()
+            .header("Access-Control-Allow-Origin", "*")
+            .status(StatusCode::INTERNAL_SERVER_ERROR)
+            .body(format!("Failed to read file metadata: {}", e).into_bytes())
+            .unwrap();
+        }
+      };
 
-    // Capture stderr for monitoring
-    if let Some(stderr) = child.stderr.take() {
-        std::thread::spawn(move || {
-            use std::io::{BufRead, BufReader};
-            let reader = BufReader::new(stderr);
-            for line in reader.lines() {
-                if let Ok(line) = line {
-                    if line.contains("drop") || line.contains("discontinuity") ||
-                       line.contains("buffer") || line.contains("queue") ||
-                       line.contains("audio") || line.contains("overrun") ||
-                       line.contains("underrun") || line.contains("pts") {
-                        log::warn!("[FFmpeg Test] {}", line);
-                    }
-                    log::debug!("[FFmpeg Test] {}", line);
-                }
+      // Check for Range header to support partial content requests
+      let range_header = request.headers().get("Range")
+        .and_then(|v| v.to_str().ok());
+
+      if let Some(range_str) = range_header {
+        // Parse range header (format: "bytes=start-end")
+        // log::info!("Range request: {}", range_str); // Disabled for performance
+
+        if let Some(range_values) = range_str.strip_prefix("bytes=") {
+          let parts: Vec<&str> = range_values.split('-').collect();
+          if parts.len() == 2 {
+            let start: u64 = parts[0].parse().unwrap_or(0);
+            let end: u64 = if parts[1].is_empty() {
+              file_size.saturating_sub(1)
+            } else {
+              parts[1].parse().unwrap_or(file_size.saturating_sub(1)).min(file_size.saturating_sub(1))
+            };
+
+            let content_length = end - start + 1;
+
+            // log::info!("Serving range: {}-{}/{} ({} bytes)", start, end, file_size, content_length); // Disabled for performance
+
+            // Seek to start position
+            if let Err(e) = file.seek(SeekFrom::Start(start)) {
+              log::error!("Failed to seek: {}", e);
+              return Response::builder()
+                .header("Access-Control-Allow-Origin", "*")
+                .status(StatusCode::INTERNAL_SERVER_ERROR)
+                .body(vec![])
+                .unwrap();
             }
-        });
-    }
 
-    std::thread::sleep(std::time::Duration::from_millis(500));
+            // Read the requested range
+            let mut buffer = vec![0u8; content_length as usize];
+            if let Err(e) = file.read_exact(&mut buffer) {
+              log::error!("Failed to read range: {}", e);
+              return Response::builder()
+                .header("Access-Control-Allow-Origin", "*")
+                .status(StatusCode::INTERNAL_SERVER_ERROR)
+                .body(vec![])
+                .unwrap();
+            }
 
-    match child.try_wait() {
-        Ok(Some(status)) => {
-            return Err(format!("FFmpeg test recording exited immediately with status: {}", status));
+            // Return 206 Partial Content
+            return Response::builder()
+              .header("Content-Type", "video/mp4")
+              .header("Content-Range", format!("bytes {}-{}/{}", start, end, file_size))
+              .header("Content-Length", content_length.to_string())
+              .header("Accept-Ranges", "bytes")
+              .header("Access-Control-Allow-Origin", "*")
+              .header("Access-Control-Allow-Methods", "GET, HEAD, OPTIONS")
+              .header("Access-Control-Allow-Headers", "*")
+              .status(StatusCode::PARTIAL_CONTENT)
+              .body(buffer)
+              .unwrap();
+          }
         }
-        Ok(None) => {
-            log::info!("Test recording started successfully");
+      }
+
+      // No range request - serve entire file
+      log::info!("Serving entire file: {} bytes", file_size);
+
+      match std::fs::read(path) {
+        Ok(data) => {
+          Response::builder()
+            .header("Content-Type", "video/mp4")
+            .header("Content-Length", file_size.to_string())
+            .header("Accept-Ranges", "bytes")
+            .header("Access-Control-Allow-Origin", "*")
+            .header("Access-Control-Allow-Methods", "GET, HEAD, OPTIONS")
+            .header("Access-Control-Allow-Headers", "*")
+            .status(StatusCode::OK)
+            .body(data)
+            .unwrap()
         }
         Err(e) => {
-            log::warn!("Could not check FFmpeg test status: {}", e);
+          log::error!("Failed to read entire file: {}", e);
+          Response::builder()
+            .header("Access-Control-Allow-Origin", "*")
+            .status(StatusCode::INTERNAL_SERVER_ERROR)
+            .body(format!("Failed to read file: {}", e).into_bytes())
+            .unwrap()
         }
-    }
+      }
+    })
+    .setup(|app| {
+      // Media cache lives in the app cache dir so it persists across launches
+      let cache_root = app.path()
+        .app_cache_dir()
+        .unwrap_or_else(|_| std::env::temp_dir().join("clipforge_cache"))
+        .join("media");
+      app.manage(MediaCache::new(cache_root));
+
+      // User export presets are settings, so they live in the config dir rather than the cache
+      let presets_path = app.path()
+        .app_config_dir()
+        .unwrap_or_else(|_| std::env::temp_dir().join("clipforge_config"))
+        .join("export_presets.json");
+      app.manage(ExportPresetStore::new(presets_path));
+
+      // The export queue is app state rather than a setting, so it goes in the data dir
+      let queue_path = app.path()
+        .app_data_dir()
+        .unwrap_or_else(|_| std::env::temp_dir().join("clipforge_data"))
+        .join("export_queue.json");
+      app.manage(ExportQueue::load(queue_path));
 
-    *recording_process = Some(child);
-    Ok(options.output_path)
+      if cfg!(debug_assertions) {
+        app.handle().plugin(
+          tauri_plugin_log::Builder::default()
+            .level(log::LevelFilter::Info)
+            .build(),
+        )?;
+      }
+      Ok(())
+    })
+    .run(tauri::generate_context!())
+    .expect("error while running tauri application");
 }
 
-// New ScreenCaptureKit-based recording (macOS only)
-#[cfg(target_os = "macos")]
-#[tauri::command]
-fn start_screencapturekit_recording(
-    output_path: String,
-    duration: f64,
-    audio_enabled: bool,
-    state: State<RecordingState>
-) -> Result<String, String> {
-    log::info!("Starting ScreenCaptureKit recording: path={}, duration={}, audio={}",
-               output_path, duration, audio_enabled);
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // ffprobe JSON for a 1920x1080 coded video stream with optional display-matrix side data
+    fn rotated_probe_json(rotation: Option<f64>, displaymatrix: Option<&str>) -> serde_json::Value {
+        let mut stream = serde_json::json!({
+            "index": 0,
+            "codec_type": "video",
+            "codec_name": "h264",
+            "width": 1920,
+            "height": 1080,
+            "avg_frame_rate": "30/1",
+            "r_frame_rate": "30/1",
+        });
+        if rotation.is_some() || displaymatrix.is_some() {
+            stream["side_data_list"] = serde_json::json!([{
+                "side_data_type": "Display Matrix",
+                "displaymatrix": displaymatrix.unwrap_or(""),
+                "rotation": rotation.unwrap_or(0.0),
+            }]);
+        }
+        serde_json::json!({
+            "format": { "duration": "2.000000", "format_name": "mov,mp4,m4a,3gp,3g2,mj2" },
+            "streams": [stream],
+        })
+    }
 
-    let mut recording_process = state.process.lock().unwrap();
-    if recording_process.is_some() {
-        return Err("Recording already in progress".to_string());
+    // Display matrices as ffprobe prints them, for an unrotated and a horizontally flipped image
+    const IDENTITY_MATRIX: &str = "\n00000000:        65536           0           0\n00000001:            0       65536           0\n00000002:            0           0  1073741824\n";
+    const HFLIP_MATRIX: &str = "\n00000000:       -65536           0           0\n00000001:            0       65536           0\n00000002:            0           0  1073741824\n";
+
+    fn displayed(probe: &MediaProbe) -> (u32, u32) {
+        (probe.display_width.unwrap(), probe.display_height.unwrap())
     }
 
-    let screen_recorder = find_screen_recorder();
-    if !screen_recorder.exists() {
-        log::error!("ScreenRecorder binary not found at: {:?}", screen_recorder);
-        return Err("ScreenRecorder binary not found. Please rebuild the application.".to_string());
+    #[test]
+    fn probe_reports_displayed_size_for_rotated_sources() {
+        for (rotation, expected_rotation, expected_size) in [
+            (0.0, 0, (1920, 1080)),
+            (90.0, 90, (1080, 1920)),
+            (-90.0, 270, (1080, 1920)),
+            (180.0, 180, (1920, 1080)),
+            (-180.0, 180, (1920, 1080)),
+            (270.0, 270, (1080, 1920)),
+        ] {
+            let probe = parse_media_probe(&rotated_probe_json(Some(rotation), Some(IDENTITY_MATRIX))).unwrap();
+            assert_eq!(probe.rotation, expected_rotation, "rotation {}", rotation);
+            assert_eq!((probe.width, probe.height), (Some(1920), Some(1080)), "coded size, rotation {}", rotation);
+            assert_eq!(displayed(&probe), expected_size, "displayed size, rotation {}", rotation);
+            assert!(!probe.mirrored);
+        }
     }
 
-    log::info!("Using ScreenRecorder at: {:?}", screen_recorder);
+    #[test]
+    fn probe_reads_legacy_rotate_tag() {
+        let mut json = rotated_probe_json(None, None);
+        json["streams"][0]["tags"] = serde_json::json!({ "rotate": "90" });
 
-    // Build command: ScreenRecorder <output_path> <display_id> <audio> <duration>
-    let mut cmd = Command::new(&screen_recorder);
-    cmd.arg(&output_path)
-        .arg("0")  // Display 0 (main display)
-        .arg(if audio_enabled { "true" } else { "false" })
-        .arg(duration.to_string())
-        .stdout(Stdio::piped())
-        .stderr(Stdio::piped());
+        let probe = parse_media_probe(&json).unwrap();
+        assert_eq!(probe.rotation, 90);
+        assert_eq!(displayed(&probe), (1080, 1920));
+    }
 
-    log::info!("Starting ScreenRecorder process...");
+    #[test]
+    fn probe_detects_mirrored_sources() {
+        let mirrored = parse_media_probe(&rotated_probe_json(Some(0.0), Some(HFLIP_MATRIX))).unwrap();
+        assert!(mirrored.mirrored);
+        assert_eq!(displayed(&mirrored), (1920, 1080));
 
-    let mut child = cmd.spawn().map_err(|e| {
-        log::error!("Failed to spawn ScreenRecorder: {}", e);
-        format!("Failed to start ScreenRecorder: {}", e)
-    })?;
+        let rotated_mirrored = parse_media_probe(&rotated_probe_json(Some(-90.0), Some(HFLIP_MATRIX))).unwrap();
+        assert!(rotated_mirrored.mirrored);
+        assert_eq!(displayed(&rotated_mirrored), (1080, 1920));
 
-    // Log output in background thread
-    if let Some(stdout) = child.stdout.take() {
-        use std::io::{BufRead, BufReader};
-        std::thread::spawn(move || {
-            let reader = BufReader::new(stdout);
-            for line in reader.lines().flatten() {
-                log::info!("[ScreenRecorder] {}", line);
-            }
-        });
+        let plain = parse_media_probe(&rotated_probe_json(None, None)).unwrap();
+        assert!(!plain.mirrored);
+        assert_eq!(plain.rotation, 0);
     }
 
-    if let Some(stderr) = child.stderr.take() {
-        use std::io::{BufRead, BufReader};
-        std::thread::spawn(move || {
-            let reader = BufReader::new(stderr);
-            for line in reader.lines().flatten() {
-                log::info!("[ScreenRecorder] {}", line);
-            }
-        });
+    #[test]
+    fn concat_canvas_follows_first_clip_orientation() {
+        // Portrait phone clip first: canvas stays portrait and fits the landscape clip's long edge
+        assert_eq!(concat_canvas_size(&[(1080, 1920), (1920, 1080)]), (1080, 1920));
+        // Landscape first: canvas stays landscape
+        assert_eq!(concat_canvas_size(&[(1920, 1080), (1080, 1920)]), (1920, 1080));
+        // Long and short edges come from the largest clip on each
+        assert_eq!(concat_canvas_size(&[(1280, 720), (1080, 1920)]), (1920, 1080));
+        // Odd sizes round up to even for yuv420p
+        assert_eq!(concat_canvas_size(&[(641, 479)]), (642, 480));
+        assert_eq!(concat_canvas_size(&[]), (1920, 1080));
     }
 
-    log::info!("ScreenRecorder started successfully");
-    *recording_process = Some(child);
-    Ok(output_path)
-}
+    #[test]
+    fn concat_canvas_uses_displayed_size_of_rotated_probes() {
+        let portrait = parse_media_probe(&rotated_probe_json(Some(-90.0), Some(IDENTITY_MATRIX))).unwrap();
+        let landscape = parse_media_probe(&rotated_probe_json(Some(180.0), Some(IDENTITY_MATRIX))).unwrap();
 
-#[tauri::command]
-fn open_in_native_player(path: String) -> Result<(), String> {
-    log::info!("Opening video in native player: {}", path);
+        assert_eq!(concat_canvas_size(&[displayed(&portrait), displayed(&landscape)]), (1080, 1920));
+        assert_eq!(concat_canvas_size(&[displayed(&landscape), displayed(&portrait)]), (1920, 1080));
+    }
 
-    #[cfg(target_os = "macos")]
-    {
-        // Use macOS 'open' command to open in QuickTime or default player
-        Command::new("open")
-            .arg(&path)
-            .spawn()
-            .map_err(|e| format!("Failed to open video: {}", e))?;
+    #[test]
+    fn parse_rational_handles_ffprobe_rates() {
+        assert_eq!(parse_rational("30/1"), Some(30.0));
+        assert!((parse_rational("30000/1001").unwrap() - 29.97).abs() < 0.001);
+        assert_eq!(parse_rational(" 25 "), Some(25.0));
+        assert_eq!(parse_rational("0/0"), None);
+        assert_eq!(parse_rational("30/0"), None);
+        assert_eq!(parse_rational("0/1"), None);
+        assert_eq!(parse_rational(""), None);
+        assert_eq!(parse_rational("abc/1"), None);
     }
 
-    #[cfg(target_os = "windows")]
-    {
-        Command::new("cmd")
-            .args(&["/C", "start", "", &path])
-            .spawn()
-            .map_err(|e| format!("Failed to open video: {}", e))?;
+    #[test]
+    fn variable_frame_rate_needs_rates_to_disagree() {
+        assert!(!is_variable_frame_rate(Some(30.0), Some(30.0)));
+        // avg_frame_rate rounding on short clips stays within the slack
+        assert!(!is_variable_frame_rate(Some(29.9), Some(30.0)));
+        // Screen recording: r_frame_rate 60, but frames only arrive when the screen changes
+        assert!(is_variable_frame_rate(Some(42.7), Some(60.0)));
+        assert!(is_variable_frame_rate(Some(29.0), Some(30.0)));
+        assert!(!is_variable_frame_rate(None, Some(30.0)));
+        assert!(!is_variable_frame_rate(Some(30.0), None));
     }
 
-    #[cfg(target_os = "linux")]
-    {
-        Command::new("xdg-open")
-            .arg(&path)
-            .spawn()
-            .map_err(|e| format!("Failed to open video: {}", e))?;
+    #[test]
+    fn target_cfr_rate_snaps_to_standard_rates() {
+        assert_eq!(target_cfr_rate(29.93), "30000/1001");
+        assert_eq!(target_cfr_rate(23.98), "24000/1001");
+        assert_eq!(target_cfr_rate(25.1), "25");
+        assert_eq!(target_cfr_rate(59.5), "60000/1001");
+        assert_eq!(target_cfr_rate(42.7), "43");
+        assert_eq!(target_cfr_rate(0.2), "1");
     }
 
-    Ok(())
-}
+    // Fresh scratch directory under the system temp dir for tests that touch the filesystem
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("clipforge_test_{}_{}", std::process::id(), name));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
 
-// ===== GOOGLE DRIVE EXPORT =====
+    #[test]
+    fn cfr_copy_stream_index_follows_audio_order() {
+        // Source: video 0, subtitles 1, audio 2 and 4, data 3
+        let source_audio = [2, 4];
+        assert_eq!(cfr_copy_stream_index(&source_audio, 2), Some(1));
+        assert_eq!(cfr_copy_stream_index(&source_audio, 4), Some(2));
+        assert_eq!(cfr_copy_stream_index(&source_audio, 3), None);
+    }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct GoogleDriveConfig {
-    api_key: String,
-}
+    #[test]
+    fn export_reads_cfr_working_copy_when_cached() {
+        let dir = test_dir("export_source");
+        let cache = MediaCache::new(dir.join("cache"));
+        let source = dir.join("phone.mp4").to_string_lossy().to_string();
+        std::fs::write(&source, b"variable frame rate source").unwrap();
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct GoogleDriveFileMetadata {
-    name: String,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    parents: Option<Vec<String>>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    mime_type: Option<String>,
-}
+        assert_eq!(export_source_path(&source, &cache), source);
 
-#[derive(Debug, Serialize, Deserialize)]
-struct GoogleDriveFile {
-    id: String,
-    name: String,
-}
+        let key = media_file_key(&source).unwrap();
+        let temp_path = cache.temp_file(&key, CFR_WORKING_COPY_NAME).unwrap();
+        std::fs::write(&temp_path, b"constant frame rate copy").unwrap();
+        let working_path = cache.commit_file(&key, &temp_path, CFR_WORKING_COPY_NAME).unwrap();
 
-#[derive(Debug, Serialize, Deserialize)]
-struct GoogleDriveFileList {
-    files: Vec<GoogleDriveFile>,
-}
+        assert_eq!(export_source_path(&source, &cache), working_path.to_string_lossy());
+    }
 
-// Find or create ClipForge folder in Google Drive
-async fn get_or_create_clipforge_folder(api_key: &str) -> Result<String, String> {
-    log::info!("Getting or creating ClipForge folder");
+    #[test]
+    fn fnv1a_matches_reference_vectors() {
+        let basis = 0xcbf29ce484222325;
+        assert_eq!(fnv1a_64(basis, b""), 0xcbf29ce484222325);
+        assert_eq!(fnv1a_64(basis, b"a"), 0xaf63dc4c8601ec8c);
+        assert_eq!(fnv1a_64(basis, b"foobar"), 0x85944171f73967e8);
+    }
 
-    let client = reqwest::Client::new();
+    #[test]
+    fn media_file_key_follows_content() {
+        let dir = test_dir("media_key");
+        let path = dir.join("clip.mp4");
+        let path_str = path.to_string_lossy().to_string();
+
+        std::fs::write(&path, vec![1u8; 200_000]).unwrap();
+        let first = media_file_key(&path_str).unwrap();
+        assert_eq!(first, media_file_key(&path_str).unwrap());
+
+        // Same size, different tail: only the sampled content differs
+        let mut replaced = vec![1u8; 200_000];
+        replaced[199_999] = 2;
+        std::fs::write(&path, replaced).unwrap();
+        assert_ne!(first, media_file_key(&path_str).unwrap());
+    }
 
-    // Search for existing ClipForge folder
-    let search_url = "https://www.googleapis.com/drive/v3/files";
-    let query = "name='ClipForge' and mimeType='application/vnd.google-apps.folder' and trashed=false";
+    #[test]
+    fn media_cache_only_serves_committed_files() {
+        let cache = MediaCache::new(test_dir("media_cache"));
 
-    let response = client
-        .get(search_url)
-        .query(&[("q", query), ("fields", "files(id,name)")])
-        .header("Authorization", format!("Bearer {}", api_key))
-        .send()
-        .await
-        .map_err(|e| format!("Failed to search for ClipForge folder: {}", e))?;
+        let temp_path = cache.temp_file("key", "thumbnail.jpg").unwrap();
+        assert_eq!(temp_path.extension().and_then(|e| e.to_str()), Some("jpg"));
+        assert_ne!(temp_path, cache.temp_file("key", "thumbnail.jpg").unwrap());
 
-    if !response.status().is_success() {
-        let status = response.status();
-        let error_text = response.text().await.unwrap_or_else(|_| "Unknown error".to_string());
-        return Err(format!("Google Drive API error ({}): {}. Please check your OAuth access token.", status, error_text));
+        // A render in progress (or abandoned) is never a cache hit
+        std::fs::write(&temp_path, b"partial").unwrap();
+        assert!(cache.cached_file("key", "thumbnail.jpg").is_none());
+
+        let committed = cache.commit_file("key", &temp_path, "thumbnail.jpg").unwrap();
+        assert!(!temp_path.exists());
+        assert_eq!(cache.cached_file("key", "thumbnail.jpg"), Some(committed));
+
+        cache.write_json("key", "probe.json", &vec![1, 2, 3]).unwrap();
+        assert_eq!(cache.read_json::<Vec<i32>>("key", "probe.json"), Some(vec![1, 2, 3]));
     }
 
-    let file_list: GoogleDriveFileList = response
-        .json()
-        .await
-        .map_err(|e| format!("Failed to parse folder search response: {}", e))?;
+    #[test]
+    fn media_cache_eviction_keeps_newest_entry() {
+        let mut cache = MediaCache::new(test_dir("media_cache_evict"));
+        cache.limit_bytes = 1000;
 
-    // If folder exists, return its ID
-    if let Some(folder) = file_list.files.first() {
-        log::info!("Found existing ClipForge folder: {}", folder.id);
-        return Ok(folder.id.clone());
+        let write = |key: &str| {
+            let temp_path = cache.temp_file(key, PROXY_CACHE_NAME).unwrap();
+            std::fs::write(&temp_path, vec![0u8; 800]).unwrap();
+            cache.commit_file(key, &temp_path, PROXY_CACHE_NAME).unwrap();
+            std::thread::sleep(std::time::Duration::from_millis(20));
+        };
+        write("old");
+        write("new");
+
+        // Both together are over the limit: the least recently used entry goes
+        cache.evict();
+        assert!(cache.cached_file("old", PROXY_CACHE_NAME).is_none());
+        assert!(cache.cached_file("new", PROXY_CACHE_NAME).is_some());
+
+        // A single entry over the limit (a long source's proxy) is kept
+        cache.limit_bytes = 10;
+        cache.evict();
+        assert!(cache.cached_file("new", PROXY_CACHE_NAME).is_some());
     }
 
-    // Create new folder
-    log::info!("Creating new ClipForge folder");
-    let folder_metadata = GoogleDriveFileMetadata {
-        name: "ClipForge".to_string(),
-        parents: None,
-        mime_type: Some("application/vnd.google-apps.folder".to_string()),
-    };
+    #[test]
+    fn media_cache_eviction_skips_entries_being_written() {
+        let mut cache = MediaCache::new(test_dir("media_cache_pending"));
+        cache.limit_bytes = 1000;
+
+        // A long build has started in "building"; its entry is the least recently used
+        let pending = cache.temp_file("building", PROXY_CACHE_NAME).unwrap();
+        std::fs::write(&pending, vec![0u8; 800]).unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(20));
+
+        // Left behind by an earlier run of the app, so not pending
+        let stale_dir = cache.entry_dir("stale").unwrap();
+        std::fs::write(stale_dir.join(".tmp-0-0-proxy.mp4"), vec![0u8; 800]).unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(20));
+
+        let done = cache.temp_file("done", PROXY_CACHE_NAME).unwrap();
+        std::fs::write(&done, vec![0u8; 800]).unwrap();
+        cache.commit_file("done", &done, PROXY_CACHE_NAME).unwrap();
+
+        cache.evict();
+        assert!(!stale_dir.exists());
+        assert!(pending.exists());
+        assert!(cache.commit_file("building", &pending, PROXY_CACHE_NAME).is_ok());
+
+        // Once committed, the entry is evictable again
+        cache.limit_bytes = 900;
+        cache.evict();
+        assert!(cache.cached_file("building", PROXY_CACHE_NAME).is_none());
+    }
 
-    let create_response = client
-        .post(search_url)
-        .query(&[("fields", "id,name")])
-        .header("Authorization", format!("Bearer {}", api_key))
-        .header("Content-Type", "application/json")
-        .json(&folder_metadata)
-        .send()
-        .await
-        .map_err(|e| format!("Failed to create ClipForge folder: {}", e))?;
+    #[test]
+    fn filmstrip_layout_packs_frames_into_rows() {
+        let (columns, rows, frames) = filmstrip_layout(10.0, 20.0, 25, 160, 90).unwrap();
+        assert_eq!((columns, rows), (FILMSTRIP_MAX_COLUMNS, 3));
+        assert_eq!(frames.len(), 25);
+        assert_eq!((frames[0].time, frames[0].x, frames[0].y), (10.0, 0, 0));
+        assert_eq!((frames[9].x, frames[9].y), (9 * 160, 0));
+        assert_eq!((frames[10].x, frames[10].y), (0, 90));
+        assert_eq!(frames[24].time, 19.6);
+
+        // Fewer frames than a full row: a single row exactly as wide as the frames
+        let (columns, rows, frames) = filmstrip_layout(0.0, 3.0, 3, 160, 90).unwrap();
+        assert_eq!((columns, rows), (3, 1));
+        assert_eq!(frames.iter().map(|f| f.time).collect::<Vec<_>>(), vec![0.0, 1.0, 2.0]);
+    }
 
-    if !create_response.status().is_success() {
-        let status = create_response.status();
-        let error_text = create_response.text().await.unwrap_or_else(|_| "Unknown error".to_string());
-        return Err(format!("Failed to create folder ({}): {}", status, error_text));
+    #[test]
+    fn filmstrip_layout_rejects_sprites_over_jpeg_limit() {
+        // 728 rows of 90px = 65520px fits; one more row does not
+        assert!(filmstrip_layout(0.0, 100.0, 7280, 160, 90).is_ok());
+        let error = filmstrip_layout(0.0, 100.0, 7281, 160, 90).unwrap_err();
+        assert!(error.contains("at most 7280 frames"), "{}", error);
+
+        // Very tall frames overflow the width too
+        assert!(filmstrip_layout(0.0, 10.0, 10, 7000, 4000).is_err());
     }
 
-    let created_folder: GoogleDriveFile = create_response
-        .json()
-        .await
-        .map_err(|e| format!("Failed to parse folder creation response: {}", e))?;
+    #[test]
+    fn parse_scene_metadata_pairs_times_with_scores() {
+        // metadata=print:file=- output on stdout
+        let output = "\
+frame:0    pts:60060   pts_time:2.002
+lavfi.scene_score=0.412000
+frame:1    pts:180180  pts_time:6.0060004
+lavfi.scene_score=0.873000
+";
+        let scenes = parse_scene_metadata(output);
+        assert_eq!(scenes.len(), 2);
+        assert_eq!((scenes[0].time, scenes[0].score), (2.002, 0.412));
+        assert_eq!((scenes[1].time, scenes[1].score), (6.006, 0.873));
+    }
 
-    log::info!("Created ClipForge folder: {}", created_folder.id);
-    Ok(created_folder.id)
-}
+    #[test]
+    fn parse_scene_metadata_ignores_scores_without_a_frame() {
+        assert!(parse_scene_metadata("lavfi.scene_score=0.5\n").is_empty());
+        assert!(parse_scene_metadata("frame:0 pts:0 pts_time:1.0\nlavfi.scene_score=bogus\n").is_empty());
+        assert!(parse_scene_metadata("").is_empty());
+    }
 
-// Upload file to Google Drive
-async fn upload_to_google_drive(file_path: &str, filename: &str, folder_id: &str, api_key: &str) -> Result<String, String> {
-    log::info!("Uploading {} to Google Drive folder {}", filename, folder_id);
+    #[test]
+    fn collect_media_files_splits_media_and_skips_hidden() {
+        let dir = test_dir("collect_media");
+        std::fs::create_dir_all(dir.join("nested")).unwrap();
+        for name in ["b.MP4", "a.mov", "notes.txt", ".hidden.mp4", "nested/c.wav"] {
+            std::fs::write(dir.join(name), b"").unwrap();
+        }
+        let name_of = |p: &String| PathBuf::from(p).file_name().unwrap().to_string_lossy().to_string();
 
-    let client = reqwest::Client::new();
+        let (mut media, mut skipped, mut errors) = (Vec::new(), Vec::new(), Vec::new());
+        collect_media_files(&dir, false, &mut media, &mut skipped, &mut errors);
+        assert_eq!(media.iter().map(name_of).collect::<Vec<_>>(), vec!["a.mov", "b.MP4"]);
+        assert_eq!(skipped.iter().map(name_of).collect::<Vec<_>>(), vec!["notes.txt"]);
+        assert!(errors.is_empty());
 
-    // Read the video file
-    let file_data = std::fs::read(file_path)
-        .map_err(|e| format!("Failed to read video file: {}", e))?;
+        let (mut media, mut skipped, mut errors) = (Vec::new(), Vec::new(), Vec::new());
+        collect_media_files(&dir, true, &mut media, &mut skipped, &mut errors);
+        assert_eq!(media.iter().map(name_of).collect::<Vec<_>>(), vec!["a.mov", "b.MP4", "c.wav"]);
+    }
+
+    #[test]
+    fn collect_media_files_reports_unreadable_folders_and_keeps_going() {
+        let dir = test_dir("collect_media_missing");
+        let (mut media, mut skipped, mut errors) = (Vec::new(), Vec::new(), Vec::new());
 
-    let file_size = file_data.len();
-    log::info!("File size: {} bytes ({:.2} MB)", file_size, file_size as f64 / 1_048_576.0);
+        collect_media_files(&dir.join("gone"), true, &mut media, &mut skipped, &mut errors);
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].path.ends_with("gone"));
 
-    // Create metadata
-    let metadata = GoogleDriveFileMetadata {
-        name: filename.to_string(),
-        parents: Some(vec![folder_id.to_string()]),
-        mime_type: Some("video/mp4".to_string()),
-    };
+        std::fs::write(dir.join("clip.mp4"), b"").unwrap();
+        collect_media_files(&dir, true, &mut media, &mut skipped, &mut errors);
+        assert_eq!(media.len(), 1);
+        assert_eq!(errors.len(), 1);
+    }
 
-    let metadata_json = serde_json::to_string(&metadata)
-        .map_err(|e| format!("Failed to serialize metadata: {}", e))?;
+    #[test]
+    fn gather_import_files_filters_explicit_paths_like_folder_entries() {
+        let dir = test_dir("gather_import");
+        for name in ["a.mov", "notes.txt"] {
+            std::fs::write(dir.join(name), b"").unwrap();
+        }
+        let path_of = |name: &str| dir.join(name).to_string_lossy().to_string();
+        let paths = vec![
+            "/elsewhere/interview.MKV".to_string(),
+            "/elsewhere/project.clipforge".to_string(),
+            "/elsewhere/no_extension".to_string(),
+            path_of("a.mov"),
+            path_of("notes.txt"),
+        ];
+
+        let (files, skipped, errors) = gather_import_files(paths, Some(&dir.to_string_lossy()), false);
+        assert_eq!(files, vec!["/elsewhere/interview.MKV".to_string(), path_of("a.mov")]);
+        assert_eq!(skipped, vec![
+            "/elsewhere/project.clipforge".to_string(),
+            "/elsewhere/no_extension".to_string(),
+            path_of("notes.txt"),
+        ]);
+        assert!(errors.is_empty());
+    }
 
-    // Create multipart upload
-    let metadata_part = reqwest::multipart::Part::text(metadata_json)
-        .mime_str("application/json")
-        .map_err(|e| format!("Failed to create metadata part: {}", e))?;
+    #[cfg(unix)]
+    #[test]
+    fn collect_media_files_does_not_follow_symlinked_folders() {
+        let dir = test_dir("collect_media_symlink");
+        std::fs::create_dir_all(dir.join("sub")).unwrap();
+        std::fs::write(dir.join("sub/clip.mp4"), b"").unwrap();
+        // A link back to the root would recurse forever if followed
+        std::os::unix::fs::symlink(&dir, dir.join("sub/loop")).unwrap();
+
+        let (mut media, mut skipped, mut errors) = (Vec::new(), Vec::new(), Vec::new());
+        collect_media_files(&dir, true, &mut media, &mut skipped, &mut errors);
+        assert_eq!(media.len(), 1);
+        assert!(skipped.is_empty());
+        assert!(errors.is_empty());
+    }
 
-    let file_part = reqwest::multipart::Part::bytes(file_data)
-        .file_name(filename.to_string())
-        .mime_str("video/mp4")
-        .map_err(|e| format!("Failed to create file part: {}", e))?;
+    #[test]
+    fn strip_subtitle_markup_removes_only_well_formed_tags() {
+        assert_eq!(strip_subtitle_markup("<i>Hello</i> <font color=\"red\">there</font>"), "Hello there");
+        assert_eq!(strip_subtitle_markup("{\\an8}{\\i1}Top line{\\i0}"), "Top line");
+        assert_eq!(strip_subtitle_markup("First\\NSecond"), "First Second");
+        // Stray brackets are caption text, not the start of a tag
+        assert_eq!(strip_subtitle_markup("x < 5 and y > 2"), "x < 5 and y > 2");
+        assert_eq!(strip_subtitle_markup("I <3 you"), "I <3 you");
+        assert_eq!(strip_subtitle_markup("set {a, b} of values"), "set {a, b} of values");
+        assert_eq!(strip_subtitle_markup("unclosed <i and more"), "unclosed <i and more");
+        assert_eq!(strip_subtitle_markup("unclosed {\\an8 text"), "unclosed {\\an8 text");
+        assert_eq!(strip_subtitle_markup("<<i>nested</i>"), "<nested");
+    }
 
-    let form = reqwest::multipart::Form::new()
-        .part("metadata", metadata_part)
-        .part("file", file_part);
+    #[test]
+    fn parse_srt_reads_cues_and_markup() {
+        let srt = "1\r\n00:00:01,000 --> 00:00:02,500\r\n<i>Hello</i>\r\nworld\r\n\r\n\
+2\r\n00:01:02,345 --> 00:01:04,000 X1:40 X2:600\r\n{\\an8}Um, I think so\r\n\r\n\
+3\r\nnot a timing line\r\nignored\r\n\r\n\
+4\r\n00:02:00,000 --> 00:02:01,000\r\n<i></i>\r\n";
+        let segments = parse_srt(srt);
+
+        assert_eq!(segments.len(), 2);
+        assert_eq!((segments[0].start, segments[0].end), (1.0, 2.5));
+        assert_eq!(segments[0].text, "Hello world");
+        assert!(!segments[0].is_filler);
+        assert_eq!((segments[1].start, segments[1].end), (62.345, 64.0));
+        assert_eq!(segments[1].text, "Um, I think so");
+        assert!(segments[1].is_filler);
+    }
 
-    // Upload to Google Drive (multipart upload)
-    let upload_url = "https://www.googleapis.com/upload/drive/v3/files?uploadType=multipart&fields=id,name,webViewLink";
+    #[test]
+    fn filler_words_match_whole_words_only() {
+        assert!(is_filler_text("Um, that's fine"));
+        assert!(is_filler_text("it was, you know, fine"));
+        assert!(is_filler_text("I MEAN it"));
+        assert!(!is_filler_text("Bring an umbrella"));
+        assert!(!is_filler_text("The album is also great"));
+        assert!(!is_filler_text("You knowingly did it"));
+        assert!(!is_filler_text("Uhura and Sulu"));
+    }
 
-    log::info!("Sending upload request...");
-    let response = client
-        .post(upload_url)
-        .header("Authorization", format!("Bearer {}", api_key))
-        .multipart(form)
-        .send()
-        .await
-        .map_err(|e| format!("Failed to upload to Google Drive: {}", e))?;
+    // 1024 samples at 8 kHz: 512 at +1000, then 512 at -2000, i.e. two buckets at the partial level
+    fn build_test_peaks(bin_path: &std::path::Path) -> (PeaksIndex, f64, Vec<PeakBucket>) {
+        let mut builder = PeaksBuilder::new(bin_path).unwrap();
+        for i in 0..1024 {
+            builder.push(if i < 512 { 1000 } else { -2000 }).unwrap();
+        }
+        let (start, partial) = builder.take_partial();
+        (builder.finish(bin_path).unwrap(), start, partial)
+    }
 
-    if !response.status().is_success() {
-        let status = response.status();
-        let error_text = response.text().await.unwrap_or_else(|_| "Unknown error".to_string());
-        return Err(format!("Upload failed ({}): {}", status, error_text));
+    #[test]
+    fn peaks_builder_writes_every_level_and_cleans_up() {
+        let dir = test_dir("peaks_builder");
+        let bin_path = dir.join("peaks.bin");
+        let (index, start, partial) = build_test_peaks(&bin_path);
+
+        assert_eq!(start, 0.0);
+        assert_eq!(partial.len(), 2);
+        assert_eq!((partial[0].min, partial[0].max), (1000, 1000));
+        assert_eq!((partial[1].min, partial[1].max, partial[1].rms), (-2000, -2000, 2000));
+
+        assert_eq!(index.total_samples, 1024);
+        assert_eq!(index.duration(), 0.128);
+        let counts: Vec<u64> = index.levels.iter().map(|level| level.bucket_count).collect();
+        assert_eq!(counts, vec![8, 2, 1, 1, 1]);
+        assert_eq!(index.levels[1].byte_offset, 8 * PEAKS_BUCKET_BYTES as u64);
+        assert_eq!(std::fs::metadata(&bin_path).unwrap().len(), 13 * PEAKS_BUCKET_BYTES as u64);
+
+        // Only the finished file is left behind
+        let files: Vec<_> = std::fs::read_dir(&dir).unwrap().map(|e| e.unwrap().file_name()).collect();
+        assert_eq!(files, vec![std::ffi::OsString::from("peaks.bin")]);
     }
 
-    let upload_response: serde_json::Value = response
-        .json()
-        .await
-        .map_err(|e| format!("Failed to parse upload response: {}", e))?;
+    #[test]
+    fn concurrent_peaks_builds_use_separate_scratch_files() {
+        let cache = MediaCache::new(test_dir("peaks_concurrent"));
+        let first = cache.temp_file("key", "peaks.bin").unwrap();
+        let second = cache.temp_file("key", "peaks.bin").unwrap();
 
-    let file_id = upload_response["id"]
-        .as_str()
-        .ok_or("No file ID in response")?
-        .to_string();
+        let first_builder = PeaksBuilder::new(&first).unwrap();
+        let second_builder = PeaksBuilder::new(&second).unwrap();
+        for (a, b) in first_builder.levels.iter().zip(&second_builder.levels) {
+            assert_ne!(a.path, b.path);
+        }
+    }
 
-    let web_view_link = upload_response["webViewLink"]
-        .as_str()
-        .unwrap_or("No link available");
+    #[test]
+    fn slice_waveform_peaks_resamples_and_pads_with_silence() {
+        let bin_path = test_dir("peaks_slice").join("peaks.bin");
+        let (index, _, _) = build_test_peaks(&bin_path);
+
+        let range = slice_waveform_peaks(&bin_path, &index, 0.0, index.duration(), 2).unwrap();
+        assert_eq!(range.bucket_duration, 0.064);
+        assert_eq!(range.peaks[0].max, 1000.0 / 32767.0);
+        assert_eq!(range.peaks[1].min, -2000.0 / 32768.0);
+        assert_eq!(range.peaks[1].rms, 2000.0 / 32767.0);
+
+        // One coarse bucket spans both halves
+        let whole = slice_waveform_peaks(&bin_path, &index, 0.0, index.duration(), 1).unwrap();
+        assert_eq!((whole.peaks[0].min, whole.peaks[0].max), (-2000.0 / 32768.0, 1000.0 / 32767.0));
+
+        // Past the end of the audio is silence
+        let padded = slice_waveform_peaks(&bin_path, &index, 0.0, 0.256, 4).unwrap();
+        assert_eq!(padded.peaks.len(), 4);
+        assert_eq!(padded.peaks[1].min, -2000.0 / 32768.0);
+        assert_eq!((padded.peaks[2].max, padded.peaks[3].rms), (0.0, 0.0));
+    }
 
-    log::info!("Upload successful! File ID: {}, Link: {}", file_id, web_view_link);
-    Ok(web_view_link.to_string())
+    #[test]
+    fn parse_loudnorm_json_reads_the_last_analysis_block() {
+        let stderr = r#"[Parsed_loudnorm_0 @ 0x5581] Using {unrelated} braces in a log line
+[Parsed_loudnorm_0 @ 0x5581]
+{
+	"input_i" : "-23.54",
+	"input_tp" : "-4.12",
+	"input_lra" : "6.30",
+	"input_thresh" : "-33.91",
+	"output_i" : "-14.02",
+	"output_tp" : "-1.50",
+	"output_lra" : "5.10",
+	"output_thresh" : "-24.35",
+	"normalization_type" : "dynamic",
+	"target_offset" : "0.02"
 }
+"#;
+        let measured = parse_loudnorm_json(stderr).unwrap();
+        assert_eq!(measured.integrated_lufs, Some(-23.54));
+        assert_eq!(measured.true_peak_dbtp, Some(-4.12));
+        assert_eq!(measured.loudness_range, 6.3);
+        assert_eq!(measured.threshold_lufs, Some(-33.91));
+        assert_eq!(measured.target_offset, Some(0.02));
+    }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct GoogleDriveExportOptions {
-    input_path: String,
-    filename: String,
-    api_key: String,
-    trim_start: Option<f64>,
-    trim_end: Option<f64>,
-}
+    #[test]
+    fn parse_loudnorm_json_treats_silence_as_unmeasured() {
+        let stderr = r#"{
+	"input_i" : "-inf",
+	"input_tp" : "-inf",
+	"input_lra" : "0.00",
+	"input_thresh" : "-inf",
+	"target_offset" : "inf"
+}"#;
+        let measured = parse_loudnorm_json(stderr).unwrap();
+        assert_eq!(measured.integrated_lufs, None);
+        assert_eq!(measured.true_peak_dbtp, None);
+        assert_eq!(measured.target_offset, None);
+        assert!(loudnorm_filter(-14.0, &measured).is_none());
+
+        assert!(parse_loudnorm_json("Conversion failed!").is_err());
+        assert!(parse_loudnorm_json("{ \"input_i\" : \"-23.0\"").is_err());
+    }
 
-#[tauri::command]
-async fn export_to_google_drive(options: GoogleDriveExportOptions) -> Result<String, String> {
-    log::info!("Starting Google Drive export: {}", options.filename);
+    #[test]
+    fn loudnorm_filter_passes_first_pass_measurements() {
+        let measured = LoudnessMeasurement {
+            integrated_lufs: Some(-23.5),
+            loudness_range: 6.3,
+            true_peak_dbtp: Some(-4.1),
+            threshold_lufs: Some(-33.9),
+            target_offset: None,
+        };
+        assert_eq!(
+            loudnorm_filter(-14.0, &measured).unwrap(),
+            "loudnorm=I=-14:TP=-1.5:LRA=11:measured_I=-23.5:measured_TP=-4.1:measured_LRA=6.3:measured_thresh=-33.9:offset=0:linear=true,aresample=48000"
+        );
+    }
 
-    // First, export the video locally to a temp file
-    let temp_dir = std::env::temp_dir();
-    let temp_output = temp_dir.join(format!("clipforge_gdrive_{}", options.filename));
-    let temp_output_str = temp_output.to_string_lossy().to_string();
+    fn spans(ranges: &[TimeRange]) -> Vec<(f64, f64)> {
+        ranges.iter().map(|r| (r.start, r.end)).collect()
+    }
 
-    log::info!("Exporting to temp file: {}", temp_output_str);
+    #[test]
+    fn parse_silencedetect_output_pairs_starts_with_ends() {
+        let stderr = "\
+[silencedetect @ 0x55d1] silence_start: -0.0213
+[silencedetect @ 0x55d1] silence_end: 1.52 | silence_duration: 1.5413
+size=N/A time=00:00:05.00 bitrate=N/A speed= 250x
+[silencedetect @ 0x55d1] silence_start: 4.2
+[silencedetect @ 0x55d1] silence_end: 6.0004 | silence_duration: 1.8004
+[silencedetect @ 0x55d1] silence_end: 7.1 | silence_duration: 0.5
+[silencedetect @ 0x55d1] silence_start: 9.5
+";
+        // The unmatched end is ignored and the trailing silence runs to the end of the file
+        assert_eq!(
+            spans(&parse_silencedetect_output(stderr, 10.0)),
+            vec![(0.0, 1.52), (4.2, 6.0), (9.5, 10.0)]
+        );
+        assert!(parse_silencedetect_output("", 10.0).is_empty());
+    }
 
-    // Export video locally first
-    export_video(ExportOptions {
-        input_path: options.input_path.clone(),
-        output_path: temp_output_str.clone(),
-        trim_start: options.trim_start,
-        trim_end: options.trim_end,
-    })?;
+    #[test]
+    fn speech_ranges_pad_and_merge_the_gaps_between_silences() {
+        let silences = vec![
+            TimeRange { start: 4.2, end: 6.0 },
+            TimeRange { start: 0.0, end: 1.52 },
+            TimeRange { start: 9.5, end: 10.0 },
+        ];
+        assert_eq!(spans(&speech_ranges(10.0, &silences, 0.25)), vec![(1.27, 4.45), (5.75, 9.75)]);
 
-    // Get or create ClipForge folder
-    let folder_id = get_or_create_clipforge_folder(&options.api_key).await?;
+        // Padding that closes a short silence joins the speech on either side
+        let short_pause = vec![TimeRange { start: 1.0, end: 1.3 }];
+        assert_eq!(spans(&speech_ranges(10.0, &short_pause, 0.25)), vec![(0.0, 10.0)]);
 
-    // Upload to Google Drive
-    let drive_link = upload_to_google_drive(&temp_output_str, &options.filename, &folder_id, &options.api_key).await?;
+        // A click between two silences is not speech
+        let click = vec![TimeRange { start: 0.0, end: 2.0 }, TimeRange { start: 2.05, end: 5.0 }];
+        assert!(speech_ranges(5.0, &click, 0.0).is_empty());
 
-    // Clean up temp file
-    let _ = std::fs::remove_file(temp_output);
+        assert_eq!(spans(&speech_ranges(3.0, &[], 0.25)), vec![(0.0, 3.0)]);
+    }
 
-    log::info!("Google Drive export complete: {}", drive_link);
-    Ok(drive_link)
-}
+    fn builtin_preset(name: &str) -> ExportPreset {
+        builtin_export_presets().into_iter().find(|p| p.name == name).unwrap()
+    }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct GoogleDriveMultiClipExportOptions {
-    clips: Vec<ClipSegment>,
-    filename: String,
-    api_key: String,
-}
+    #[test]
+    fn builtin_export_presets_are_valid() {
+        for preset in builtin_export_presets() {
+            assert_eq!(validate_export_preset(&preset), Ok(()), "preset {}", preset.name);
+        }
+    }
 
-#[tauri::command]
-async fn export_multi_clip_to_google_drive(options: GoogleDriveMultiClipExportOptions, window: tauri::Window) -> Result<String, String> {
-    log::info!("Starting multi-clip Google Drive export: {}", options.filename);
+    #[test]
+    fn validate_export_preset_rejects_impossible_combinations() {
+        let base = builtin_preset(DEFAULT_MERGE_PRESET);
+        let invalid = |change: &dyn Fn(&mut ExportPreset)| {
+            let mut preset = base.clone();
+            change(&mut preset);
+            validate_export_preset(&preset).unwrap_err()
+        };
 
-    // First, export the multi-clip video locally to a temp file
-    let temp_dir = std::env::temp_dir();
-    let temp_output = temp_dir.join(format!("clipforge_gdrive_{}", options.filename));
-    let temp_output_str = temp_output.to_string_lossy().to_string();
+        assert_eq!(invalid(&|p| p.name = "  ".to_string()), "Export preset needs a name");
+        assert_eq!(invalid(&|p| p.video_codec = "mpeg2".to_string()), "Unsupported video codec: mpeg2");
+        assert_eq!(invalid(&|p| p.container = "avi".to_string()), "Unsupported container: avi");
+        assert_eq!(invalid(&|p| p.audio_codec = "pcm".to_string()), "A mp4 file can't hold x264 video with pcm audio");
+        assert_eq!(invalid(&|p| p.crf = Some(52)), "CRF for x264 must be between 0 and 51");
+        assert_eq!(
+            invalid(&|p| {
+                p.crf = None;
+                p.video_bitrate_kbps = None;
+            }),
+            "x264 needs either a CRF or a video bitrate"
+        );
+        assert_eq!(invalid(&|p| p.audio_bitrate_kbps = Some(0)), "Bitrates must be greater than zero");
+        assert_eq!(invalid(&|p| p.preset = Some("warp".to_string())), "Invalid preset 'warp' for x264");
+        assert_eq!(invalid(&|p| p.width = Some(1279)), "Output size must be even and between 2 and 8192 pixels");
+        assert_eq!(invalid(&|p| p.height = Some(8194)), "Output size must be even and between 2 and 8192 pixels");
+    }
 
-    log::info!("Exporting multi-clip to temp file: {}", temp_output_str);
+    #[test]
+    fn validate_export_preset_checks_codec_specific_settings() {
+        let mut vp9 = builtin_preset(DEFAULT_MERGE_PRESET);
+        vp9.video_codec = "vp9".to_string();
+        vp9.container = "webm".to_string();
+        vp9.audio_codec = "opus".to_string();
+        vp9.crf = Some(63);
+        vp9.preset = Some("8".to_string());
+        assert_eq!(validate_export_preset(&vp9), Ok(()));
+        vp9.preset = Some("9".to_string());
+        assert!(validate_export_preset(&vp9).is_err());
+
+        // ProRes has no quality setting, so neither CRF nor bitrate is required
+        let mut prores = builtin_preset("ProRes 422 HQ");
+        prores.preset = Some("4444".to_string());
+        assert_eq!(validate_export_preset(&prores), Ok(()));
+        prores.container = "webm".to_string();
+        assert!(validate_export_preset(&prores).is_err());
+    }
 
-    // Export multi-clip video locally first
-    export_multi_clip(MultiClipExportOptions {
-        clips: options.clips,
-        output_path: temp_output_str.clone(),
-    }, window)?;
+    #[test]
+    fn temp_clips_are_lossless_for_lossless_presets() {
+        for name in ["ProRes 422 HQ", "FFV1 Lossless"] {
+            let (extension, args) = temp_clip_format(&builtin_preset(name));
+            assert_eq!(extension, "mkv");
+            assert!(args.windows(2).any(|w| w == ["-c:v", "ffv1"]), "preset {}", name);
+            assert!(args.windows(2).any(|w| w == ["-c:a", "flac"]), "preset {}", name);
+        }
 
-    // Get or create ClipForge folder
-    let folder_id = get_or_create_clipforge_folder(&options.api_key).await?;
+        let (extension, args) = temp_clip_format(&builtin_preset(DEFAULT_MERGE_PRESET));
+        assert_eq!(extension, "mp4");
+        assert!(args.windows(2).any(|w| w == ["-c:v", "libx264"]));
+    }
 
-    // Upload to Google Drive
-    let drive_link = upload_to_google_drive(&temp_output_str, &options.filename, &folder_id, &options.api_key).await?;
+    #[test]
+    fn parse_progress_out_time_reads_microseconds() {
+        assert_eq!(parse_progress_out_time("out_time_us=12500000"), Some(12.5));
+        // Despite the name, out_time_ms is also in microseconds
+        assert_eq!(parse_progress_out_time("out_time_ms=1500000"), Some(1.5));
+        assert_eq!(parse_progress_out_time("out_time=00:00:12.500000"), None);
+        assert_eq!(parse_progress_out_time("out_time_us=N/A"), None);
+        assert_eq!(parse_progress_out_time("progress=continue"), None);
+    }
 
-    // Clean up temp file
-    let _ = std::fs::remove_file(temp_output);
+    #[test]
+    fn export_progress_tracker_sums_passes() {
+        let mut progress = ExportProgressTracker::new(100.0);
+        assert_eq!(progress.report(0, 4, "Starting".to_string(), 0.0).percent, Some(0.0));
 
-    log::info!("Google Drive multi-clip export complete: {}", drive_link);
-    Ok(drive_link)
-}
+        progress.complete(25.0);
+        let report = progress.report(2, 4, "Processing".to_string(), 12.5);
+        assert_eq!((report.current, report.total, report.percent), (2, 4, Some(37.5)));
 
-// ===== TRANSCRIPTION =====
+        // Too early for a meaningful speed or ETA
+        assert_eq!((report.speed, report.eta_seconds), (None, None));
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct TranscriptSegment {
-    start: f64,
-    end: f64,
-    text: String,
-    confidence: f64,
-    #[serde(default)]
-    is_filler: bool,
-}
+        // Positions past the estimate don't push the total over 100%, and negative ones are ignored
+        assert_eq!(progress.report(4, 4, String::new(), 500.0).percent, Some(100.0));
+        assert_eq!(progress.report(4, 4, String::new(), -3.0).percent, Some(25.0));
+    }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct TranscriptionResult {
-    segments: Vec<TranscriptSegment>,
-    full_text: String,
-}
+    #[test]
+    fn export_progress_tracker_without_duration_has_no_percent() {
+        let progress = ExportProgressTracker::new(0.0);
+        let report = progress.report(1, 2, "Processing".to_string(), 5.0);
+        assert_eq!((report.percent, report.speed, report.eta_seconds), (None, None, None));
+    }
 
-#[derive(Debug, Serialize, Deserialize)]
-struct OpenAIWord {
-    word: String,
-    start: f64,
-    end: f64,
-}
+    #[test]
+    fn clip_audio_source_defaults_to_first_audio_stream() {
+        let clip = clip_segment_for_range("interview.mov", 0.0, 5.0);
+        assert_eq!(clip_audio_source(&clip, &[1, 2]).unwrap(), (None, "[0:a]".to_string()));
+    }
 
-#[derive(Debug, Serialize, Deserialize)]
-struct OpenAISegment {
-    id: u32,
-    start: f64,
-    end: f64,
-    text: String,
-    #[serde(default)]
-    tokens: Vec<u32>,
-    temperature: f64,
-    avg_logprob: f64,
-    compression_ratio: f64,
-    no_speech_prob: f64,
-}
+    #[test]
+    fn clip_audio_source_selects_one_stream_by_absolute_index() {
+        let mut clip = clip_segment_for_range("interview.mov", 0.0, 5.0);
+        clip.audio_stream_index = Some(2);
+        assert_eq!(clip_audio_source(&clip, &[1, 2]).unwrap(), (None, "[0:2]".to_string()));
+
+        clip.audio_stream_index = Some(0);
+        assert_eq!(
+            clip_audio_source(&clip, &[1, 2]).unwrap_err(),
+            "Audio stream 0 not found in interview.mov (available audio streams: [1, 2])"
+        );
+    }
 
-#[derive(Debug, Serialize, Deserialize)]
-struct OpenAITranscriptionResponse {
-    task: String,
-    language: String,
-    duration: f64,
-    text: String,
-    segments: Vec<OpenAISegment>,
-    #[serde(default)]
-    words: Vec<OpenAIWord>,
-}
+    #[test]
+    fn clip_audio_source_mixes_tracks_without_attenuation() {
+        let mut clip = clip_segment_for_range("interview.mov", 0.0, 5.0);
+        clip.audio_stream_index = Some(1);  // Ignored when tracks are given
+        clip.audio_tracks = Some(vec![
+            AudioTrackMix { stream_index: 1, gain_db: None },
+            AudioTrackMix { stream_index: 2, gain_db: Some(-6.0) },
+            AudioTrackMix { stream_index: 3, gain_db: Some(3.5) },
+        ]);
+        let (chain, label) = clip_audio_source(&clip, &[1, 2, 3]).unwrap();
+        assert_eq!(
+            chain.unwrap(),
+            "[0:1]volume=0dB[srca0];[0:2]volume=-6dB[srca1];[0:3]volume=3.5dB[srca2];\
+             [srca0][srca1][srca2]amix=inputs=3:duration=longest:normalize=0[srcmix]"
+        );
+        assert_eq!(label, "[srcmix]");
+
+        // A single track only gets its gain
+        clip.audio_tracks = Some(vec![AudioTrackMix { stream_index: 3, gain_db: Some(-2.0) }]);
+        assert_eq!(
+            clip_audio_source(&clip, &[1, 2, 3]).unwrap(),
+            (Some("[0:3]volume=-2dB[srca0]".to_string()), "[srca0]".to_string())
+        );
+    }
 
-// Extract audio from video file
-fn extract_audio(video_path: &str) -> Result<PathBuf, String> {
-    log::info!("Checking for audio stream in: {}", video_path);
+    #[test]
+    fn clip_audio_source_for_clip_without_audio() {
+        // No selection: the render checks for audio itself and adds silence
+        let mut clip = clip_segment_for_range("screen.mp4", 0.0, 5.0);
+        assert_eq!(clip_audio_source(&clip, &[]).unwrap(), (None, "[0:a]".to_string()));
 
-    // First, check if the video has an audio stream using ffprobe
-    let ffprobe = find_ffprobe();
-    let probe_output = Command::new(&ffprobe)
-        .args(&[
-            "-v", "quiet",
-            "-print_format", "json",
-            "-show_streams",
-            "-select_streams", "a",  // Only select audio streams
-            video_path
-        ])
-        .output()
-        .map_err(|e| format!("Failed to execute ffprobe: {}", e))?;
+        // An empty track list is the same as no selection
+        clip.audio_tracks = Some(Vec::new());
+        assert_eq!(clip_audio_source(&clip, &[]).unwrap(), (None, "[0:a]".to_string()));
 
-    let json_str = String::from_utf8_lossy(&probe_output.stdout);
-    let json: serde_json::Value = serde_json::from_str(&json_str)
-        .map_err(|e| format!("Failed to parse ffprobe output: {}", e))?;
+        clip.audio_tracks = Some(vec![AudioTrackMix { stream_index: 1, gain_db: None }]);
+        assert!(clip_audio_source(&clip, &[]).is_err());
+    }
 
-    // Check if there are any audio streams
-    let audio_streams = json["streams"].as_array()
-        .map(|arr| arr.len())
-        .unwrap_or(0);
+    // A moved source as the project remembers it: name, size and content fingerprint of `content`
+    fn missing_media(dir: &std::path::Path, name: &str, content: &[u8]) -> MissingMedia {
+        let original = dir.join("original").join(name);
+        std::fs::create_dir_all(original.parent().unwrap()).unwrap();
+        std::fs::write(&original, content).unwrap();
+        let fingerprint = media_content_fingerprint(&original.to_string_lossy()).unwrap();
+        std::fs::remove_file(&original).unwrap();
+
+        MissingMedia {
+            path: original.to_string_lossy().to_string(),
+            file_size: Some(content.len() as u64),
+            duration: None,
+            fingerprint: Some(fingerprint),
+        }
+    }
 
-    if audio_streams == 0 {
-        log::warn!("No audio stream found in video: {}", video_path);
-        return Err("This video has no audio track. Please select a video with audio to transcribe.".to_string());
+    fn candidate(dir: &std::path::Path, name: &str, content: &[u8]) -> String {
+        let path = dir.join(name);
+        std::fs::write(&path, content).unwrap();
+        path.to_string_lossy().to_string()
     }
 
-    log::info!("Found {} audio stream(s), extracting audio", audio_streams);
+    #[test]
+    fn relink_matches_moved_file_by_content() {
+        let dir = test_dir("relink_moved");
+        let missing = missing_media(&dir, "beach.mp4", b"beach footage");
 
-    let temp_dir = std::env::temp_dir();
-    let audio_path = temp_dir.join("clipforge_audio.mp3");
+        // Same name, size and content in another folder
+        let moved = match_relink_candidate(&missing, &candidate(&dir, "beach.mp4", b"beach footage")).unwrap();
+        assert_eq!(moved.confidence, "exact");
+        assert_eq!(moved.matched_on, vec!["filename", "size", "fingerprint"]);
 
-    let ffmpeg = find_ffmpeg();
-    let output = Command::new(&ffmpeg)
-        .args(&[
-            "-i", video_path,
-            "-vn",  // No video
-            "-acodec", "libmp3lame",
-            "-ab", "192k",
-            "-ar", "44100",
-            "-af", "aresample=async=1:first_pts=0",  // Ensure audio starts at 0 and preserves duration
-            "-y",
-            audio_path.to_str().unwrap()
-        ])
-        .output()
-        .map_err(|e| format!("Failed to execute FFmpeg: {}", e))?;
+        // Renamed copy: the content still identifies it
+        let renamed = match_relink_candidate(&missing, &candidate(&dir, "IMG_0042.mp4", b"beach footage")).unwrap();
+        assert_eq!(renamed.confidence, "exact");
+        assert_eq!(renamed.matched_on, vec!["size", "fingerprint"]);
+    }
 
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        return Err(format!("FFmpeg audio extraction failed: {}", stderr));
+    #[test]
+    fn relink_reports_partial_matches_as_low_confidence() {
+        let dir = test_dir("relink_partial");
+        let missing = missing_media(&dir, "beach.mp4", b"beach footage");
+
+        // Same name, different size (a re-export, say): fingerprint isn't even checked
+        let resized = match_relink_candidate(&missing, &candidate(&dir, "beach.mp4", b"re-encoded beach footage")).unwrap();
+        assert_eq!((resized.confidence.as_str(), resized.matched_on.clone()), ("low", vec!["filename".to_string()]));
+
+        // Same size but different content
+        let lookalike = match_relink_candidate(&missing, &candidate(&dir, "other.mp4", b"beach fOOtage")).unwrap();
+        assert_eq!((lookalike.confidence.as_str(), lookalike.matched_on.clone()), ("low", vec!["size".to_string()]));
+
+        // Nothing in common
+        assert!(match_relink_candidate(&missing, &candidate(&dir, "city.mp4", b"city")).is_none());
     }
 
-    // Verify extracted audio duration matches video
-    let ffprobe = find_ffprobe();
-    let audio_probe = Command::new(&ffprobe)
-        .args(&[
-            "-v", "quiet",
-            "-print_format", "json",
-            "-show_format",
-            audio_path.to_str().unwrap()
-        ])
-        .output()
-        .map_err(|e| format!("Failed to probe audio: {}", e))?;
+    #[test]
+    fn relink_ranks_several_candidates_best_first() {
+        let dir = test_dir("relink_ranked");
+        let missing = missing_media(&dir, "beach.mp4", b"beach footage");
+        let candidates = vec![
+            candidate(&dir, "city.mp4", b"city"),
+            candidate(&dir, "other.mp4", b"beach fOOtage"),
+            candidate(&dir, "IMG_0042.mp4", b"beach footage"),
+            candidate(&dir, "beach.mp4", b"beach fOOtage"),
+        ];
+
+        let ranked: Vec<(String, String)> = rank_relink_candidates(&missing, &candidates)
+            .into_iter()
+            .map(|c| (c.path, c.confidence))
+            .collect();
+        assert_eq!(ranked, vec![
+            (candidates[2].clone(), "exact".to_string()),
+            (candidates[3].clone(), "low".to_string()),  // Name and size
+            (candidates[1].clone(), "low".to_string()),  // Size only
+        ]);
+    }
 
-    let audio_json_str = String::from_utf8_lossy(&audio_probe.stdout);
-    if let Ok(audio_json) = serde_json::from_str::<serde_json::Value>(&audio_json_str) {
-        if let Some(duration_str) = audio_json["format"]["duration"].as_str() {
-            if let Ok(audio_duration) = duration_str.parse::<f64>() {
-                log::info!("Extracted audio duration: {:.2}s", audio_duration);
-            }
+    #[test]
+    fn find_missing_clip_files_lists_every_missing_source() {
+        let dir = test_dir("missing_clips");
+        let present = candidate(&dir, "present.mp4", b"video");
+        let gone = dir.join("gone.mp4").to_string_lossy().to_string();
+        let clips = vec![
+            clip_segment_for_range(&present, 0.0, 1.0),
+            clip_segment_for_range(&gone, 0.0, 1.0),
+            clip_segment_for_range(&present, 1.0, 2.0),
+            clip_segment_for_range(&gone, 1.0, 2.0),
+        ];
+
+        let missing: Vec<(usize, String)> = find_missing_clip_files(&clips)
+            .into_iter()
+            .map(|m| (m.clip_index, m.path))
+            .collect();
+        assert_eq!(missing, vec![(1, gone.clone()), (3, gone)]);
+    }
+
+    fn queued_export(id: &str, status: &str) -> QueuedExport {
+        QueuedExport {
+            id: id.to_string(),
+            request: QueuedExportRequest::MultiClip(MultiClipExportOptions {
+                clips: Vec::new(),
+                output_path: format!("/exports/{}.mp4", id),
+                target_loudness: None,
+                preset: None,
+            }),
+            status: status.to_string(),
+            output_path: None,
+            error: None,
         }
     }
 
-    log::info!("Audio extracted to: {:?}", audio_path);
-    Ok(audio_path)
-}
+    fn queue_statuses(state: &ExportQueueSnapshot) -> Vec<(String, String)> {
+        state.jobs.iter().map(|job| (job.id.clone(), job.status.clone())).collect()
+    }
 
-// Transcribe audio using OpenAI Whisper API
-async fn transcribe_with_openai(audio_path: &PathBuf, api_key: &str) -> Result<TranscriptionResult, String> {
-    log::info!("Transcribing audio with OpenAI Whisper: {:?}", audio_path);
+    #[test]
+    fn export_queue_load_requeues_interrupted_exports_paused() {
+        let dir = test_dir("export_queue_load");
+        let path = dir.join("export-queue.json");
+        let saved = ExportQueueSnapshot {
+            concurrency: 2,
+            paused: false,
+            jobs: vec![
+                queued_export("done", "completed"),
+                queued_export("interrupted", "running"),
+                queued_export("next", "queued"),
+            ],
+        };
+        std::fs::write(&path, serde_json::to_vec(&saved).unwrap()).unwrap();
+
+        let queue = ExportQueue::load(path);
+        let mut state = queue.state.lock().unwrap();
+        assert!(state.paused);
+        assert_eq!(state.concurrency, 2);
+        assert_eq!(queue_statuses(&state), vec![
+            ("done".to_string(), "completed".to_string()),
+            ("interrupted".to_string(), "queued".to_string()),
+            ("next".to_string(), "queued".to_string()),
+        ]);
+
+        // Nothing starts until the queue is resumed
+        assert!(state.start_queued().is_empty());
+        state.paused = false;
+        let started: Vec<String> = state.start_queued().into_iter().map(|job| job.id).collect();
+        assert_eq!(started, vec!["interrupted", "next"]);
+    }
+
+    #[test]
+    fn export_queue_load_without_unfinished_work_stays_unpaused() {
+        let dir = test_dir("export_queue_load_finished");
+        let path = dir.join("export-queue.json");
+        let saved = ExportQueueSnapshot {
+            concurrency: 1,
+            paused: false,
+            jobs: vec![queued_export("done", "completed"), queued_export("broken", "failed")],
+        };
+        std::fs::write(&path, serde_json::to_vec(&saved).unwrap()).unwrap();
 
-    let client = reqwest::Client::new();
+        assert!(!ExportQueue::load(path).state.lock().unwrap().paused);
 
-    // Read the audio file
-    let file_data = std::fs::read(audio_path)
-        .map_err(|e| format!("Failed to read audio file: {}", e))?;
+        // A missing or corrupt file starts an empty queue
+        std::fs::write(dir.join("corrupt.json"), b"{ not json").unwrap();
+        for path in [dir.join("missing.json"), dir.join("corrupt.json")] {
+            let queue = ExportQueue::load(path);
+            let state = queue.state.lock().unwrap();
+            assert!(state.jobs.is_empty() && !state.paused);
+            assert_eq!(state.concurrency, DEFAULT_EXPORT_QUEUE_CONCURRENCY);
+        }
+    }
 
-    // Get filename for the multipart form
-    let filename = audio_path
-        .file_name()
-        .and_then(|n| n.to_str())
-        .unwrap_or("audio.mp3");
+    #[test]
+    fn export_queue_records_results_and_fills_free_slots() {
+        let mut state = ExportQueueSnapshot {
+            concurrency: 1,
+            paused: false,
+            jobs: vec![queued_export("a", "queued"), queued_export("b", "queued"), queued_export("c", "queued")],
+        };
 
-    // Create multipart form
-    let file_part = reqwest::multipart::Part::bytes(file_data)
-        .file_name(filename.to_string())
-        .mime_str("audio/mpeg")
-        .map_err(|e| format!("Failed to create file part: {}", e))?;
+        assert_eq!(state.start_queued().len(), 1);
+        assert!(state.start_queued().is_empty());  // The only slot is taken
 
-    let form = reqwest::multipart::Form::new()
-        .part("file", file_part)
-        .text("model", "whisper-1")
-        .text("response_format", "verbose_json")
-        .text("timestamp_granularities[]", "segment");
+        state.record_finished("a", &Ok("/exports/a.mp4".to_string()), false);
+        assert_eq!(state.jobs[0].output_path.as_deref(), Some("/exports/a.mp4"));
+        assert_eq!(state.start_queued()[0].id, "b");
 
-    // Send request to OpenAI
-    let response = client
-        .post("https://api.openai.com/v1/audio/transcriptions")
-        .header("Authorization", format!("Bearer {}", api_key))
-        .multipart(form)
-        .send()
-        .await
-        .map_err(|e| format!("Failed to send transcription request: {}", e))?;
+        state.record_finished("b", &Err("Export cancelled".to_string()), true);
+        assert_eq!(state.start_queued()[0].id, "c");
+        state.record_finished("c", &Err("FFmpeg failed".to_string()), false);
 
-    if !response.status().is_success() {
-        let status = response.status();
-        let error_text = response.text().await.unwrap_or_else(|_| "Unknown error".to_string());
-        return Err(format!("OpenAI API request failed ({}): {}", status, error_text));
+        assert_eq!(queue_statuses(&state), vec![
+            ("a".to_string(), "completed".to_string()),
+            ("b".to_string(), "cancelled".to_string()),
+            ("c".to_string(), "failed".to_string()),
+        ]);
+        assert_eq!(state.jobs[2].error.as_deref(), Some("FFmpeg failed"));
     }
 
-    let openai_response: OpenAITranscriptionResponse = response
-        .json()
-        .await
-        .map_err(|e| format!("Failed to parse OpenAI response: {}", e))?;
+    #[test]
+    fn export_queue_keeps_pumping_after_running_entry_is_removed() {
+        let mut state = ExportQueueSnapshot {
+            concurrency: 1,
+            paused: false,
+            jobs: vec![queued_export("a", "queued"), queued_export("b", "queued")],
+        };
+        assert_eq!(state.start_queued()[0].id, "a");
 
-    log::info!("Transcription completed. Duration: {}s, Language: {}",
-               openai_response.duration, openai_response.language);
+        // The entry is gone from the queue by the time its export reports back
+        state.jobs.retain(|job| job.id != "a");
+        state.record_finished("a", &Ok("/exports/a.mp4".to_string()), false);
 
-    // Convert OpenAI segments to our format
-    let filler_words = vec!["um", "uh", "like", "so", "basically", "actually", "literally", "you know", "i mean"];
+        let started: Vec<String> = state.start_queued().into_iter().map(|job| job.id).collect();
+        assert_eq!(started, vec!["b"]);
+        assert_eq!(queue_statuses(&state), vec![("b".to_string(), "running".to_string())]);
+    }
 
-    let segments: Vec<TranscriptSegment> = openai_response.segments
-        .iter()
-        .enumerate()
-        .map(|(i, seg)| {
-            log::info!("Segment {}: {:.2}s - {:.2}s: \"{}\"", i, seg.start, seg.end, seg.text.trim());
+    #[test]
+    fn clip_render_split_shares_cores_between_workers() {
+        // A single core still renders, one clip at a time
+        assert_eq!(clip_render_split(1, 5), (1, 1));
+        assert_eq!(clip_render_split(2, 5), (1, 2));
+        // More clips than workers: half the cores render, each with two threads
+        assert_eq!(clip_render_split(8, 20), (4, 2));
+        assert_eq!(clip_render_split(6, 10), (3, 2));
+        // The pool is capped, leaving the extra cores to the encodes
+        assert_eq!(clip_render_split(64, 100), (MAX_PARALLEL_CLIP_RENDERS, 8));
+        // Fewer clips than workers: each clip gets more threads
+        assert_eq!(clip_render_split(16, 2), (2, 8));
+        assert_eq!(clip_render_split(16, 1), (1, 16));
+    }
 
-            // Check if segment contains filler words
-            let is_filler = filler_words.iter().any(|filler| {
-                seg.text.to_lowercase().contains(filler)
-            });
+    #[test]
+    fn render_clips_fail_fast_reports_first_failure_and_aborts() {
+        let job = ExportJob::new();
+        let started = Mutex::new(Vec::new());
 
-            TranscriptSegment {
-                start: seg.start,
-                end: seg.end,
-                text: seg.text.trim().to_string(),
-                confidence: (-seg.avg_logprob).min(1.0).max(0.0), // Convert logprob to confidence-like score
-                is_filler,
+        let failure = render_clips_fail_fast(10, 2, &job, |i| {
+            started.lock().unwrap().push(i);
+            if i == 1 {
+                return Err("Clip 2 failed".to_string());
             }
-        })
-        .collect();
+            // Stand-in for an encode that only ends when the abort kills it
+            let waited = std::time::Instant::now();
+            while !job.is_aborted() {
+                assert!(waited.elapsed() < std::time::Duration::from_secs(10), "clip {} was never aborted", i);
+                std::thread::sleep(std::time::Duration::from_millis(1));
+            }
+            Err(format!("Clip {} was killed", i + 1))
+        });
 
-    log::info!("Processed {} segments", segments.len());
+        assert_eq!(failure.as_deref(), Some("Clip 2 failed"));
+        assert!(job.is_aborted() && !job.is_cancelled());
+        // Nothing new starts once a clip has failed
+        let mut started = started.into_inner().unwrap();
+        started.sort();
+        assert_eq!(started, vec![0, 1]);
+    }
 
-    Ok(TranscriptionResult {
-        segments,
-        full_text: openai_response.text,
-    })
-}
+    #[test]
+    fn render_clips_fail_fast_renders_every_clip_once() {
+        let job = ExportJob::new();
+        let rendered = Mutex::new(Vec::new());
 
-#[tauri::command]
-async fn transcribe_video(video_path: String, api_key: String) -> Result<TranscriptionResult, String> {
-    log::info!("Starting transcription for video: {}", video_path);
+        let failure = render_clips_fail_fast(7, 3, &job, |i| {
+            rendered.lock().unwrap().push(i);
+            Ok(())
+        });
 
-    // Extract audio from video
-    let audio_path = extract_audio(&video_path)?;
+        assert!(failure.is_none());
+        assert!(!job.is_aborted());
+        let mut rendered = rendered.into_inner().unwrap();
+        rendered.sort();
+        assert_eq!(rendered, (0..7).collect::<Vec<_>>());
+    }
 
-    // Transcribe with OpenAI Whisper
-    let result = transcribe_with_openai(&audio_path, &api_key).await?;
+    // (kind, start, end, channel) of a reported audio problem
+    type ProblemSummary = (String, f64, f64, Option<u32>);
 
-    // Clean up temp audio file
-    let _ = std::fs::remove_file(audio_path);
+    // Feeds `frames` frames of interleaved audio at 1 kHz (50-frame analysis windows) to a scanner
+    fn scan_audio_problems(channels: usize, frames: usize, sample: impl Fn(usize, usize) -> i16) -> (Vec<ProblemSummary>, u64) {
+        let mut scanner = AudioProblemScanner::new(channels, 1000, DEFAULT_LOW_LEVEL_DB);
+        for frame in 0..frames {
+            for channel in 0..channels {
+                scanner.push(sample(frame, channel));
+            }
+        }
+        let (problems, scanned) = scanner.finish();
+        let problems = problems.into_iter().map(|p| (p.kind, p.start, p.end, p.channel)).collect();
+        (problems, scanned)
+    }
 
-    log::info!("Transcription complete!");
-    Ok(result)
-}
+    // A -10 dBFS square wave: comfortably above the low level threshold, nowhere near clipping
+    fn speech_level(frame: usize) -> i16 {
+        if frame % 2 == 0 { 10000 } else { -10000 }
+    }
 
-#[cfg_attr(mobile, tauri::mobile_entry_point)]
-pub fn run() {
-  tauri::Builder::default()
-    .plugin(tauri_plugin_dialog::init())
-    .plugin(tauri_plugin_shell::init())
-    .manage(RecordingState {
-      process: Mutex::new(None),
-    })
-    .invoke_handler(tauri::generate_handler![
-      import_video,
-      export_video,
-      export_multi_clip,
-      export_to_google_drive,
-      export_multi_clip_to_google_drive,
-      start_recording,
-      stop_recording,
-      start_test_recording,
-      #[cfg(target_os = "macos")]
-      start_screencapturekit_recording,
-      open_in_native_player,
-      list_audio_devices,
-      transcribe_video,
-      generate_waveform
-    ])
-    .register_uri_scheme_protocol("video", |_app, request| {
-      use tauri::http::{Response, StatusCode};
-      use std::io::{Seek, SeekFrom, Read};
+    #[test]
+    fn audio_problems_flag_runs_of_full_scale_samples() {
+        let (problems, frames) = scan_audio_problems(1, 2000, |frame, _| match frame {
+            500..=509 => -32768,         // Ten full-scale samples
+            1199..=1201 => 32767,        // Shortest run that counts, across a window boundary
+            1500 | 1600 | 1601 => 32767, // A lone peak and a run of two are legitimate
+            _ => speech_level(frame),
+        });
 
-      // Custom protocol handler for serving video files with proper HTTP Range support
-      // This is critical for video streaming performance
+        assert_eq!(frames, 2000);
+        assert_eq!(problems, vec![
+            ("clipping".to_string(), 0.5, 0.55, Some(0)),
+            ("clipping".to_string(), 1.2, 1.25, Some(0)),
+        ]);
+    }
 
-      let uri = request.uri().to_string();
-      // log::info!("Video protocol request: {}", uri); // Disabled for performance
+    #[test]
+    fn audio_problems_flag_sustained_low_level() {
+        // -50 dBFS is below the -40 dBFS default; only the stretch lasting 3 s or more is reported
+        let (problems, _) = scan_audio_problems(1, 10000, |frame, _| match frame {
+            1000..=4999 | 6000..=7999 => if frame % 2 == 0 { 100 } else { -100 },
+            _ => speech_level(frame),
+        });
 
-      // Extract the file path from the URI (format: video://localhost/<path>)
-      let path = uri
-        .strip_prefix("video://localhost")
-        .unwrap_or(&uri);
+        assert_eq!(problems, vec![("low_level".to_string(), 1.0, 5.0, None)]);
+    }
 
-      // log::info!("Attempting to serve video file: {}", path); // Disabled for performance
+    #[test]
+    fn audio_problems_flag_one_silent_channel_next_to_a_live_one() {
+        let (problems, frames) = scan_audio_problems(2, 14000, |frame, channel| match (frame, channel) {
+            (1000..=2999, 1) => 0,      // Right channel drops out for 2 s
+            (6000..=6499, 1) => 0,      // Too short to report
+            (9000..=12999, _) => 0,     // Both channels silent: low level, not a silent channel
+            _ => speech_level(frame),
+        });
 
-      // Open the file
-      let mut file = match std::fs::File::open(path) {
-        Ok(f) => f,
-        Err(e) => {
-          log::error!("Failed to open video file: {}", e);
-          return Response::builder()
-            .header("Access-Control-Allow-Origin", "*")
-            .status(StatusCode::NOT_FOUND)
-            .body(format!("Video not found: {}", e).into_bytes())
-            .unwrap();
-        }
-      };
+        assert_eq!(frames, 14000);
+        assert_eq!(problems, vec![
+            ("silent_channel".to_string(), 1.0, 3.0, Some(1)),
+            ("low_level".to_string(), 9.0, 13.0, None),
+        ]);
+    }
 
-      // Get file size
-      let file_size = match file.metadata() {
-        Ok(metadata) => metadata.len(),
-        Err(e) => {
-          log::error!("Failed to get file metadata: {}", e);
-          return Response::builder()
-            .header("Access-Control-Allow-Origin", "*")
-            .status(StatusCode::INTERNAL_SERVER_ERROR)
-            .body(format!("Failed to read file metadata: {}", e).into_bytes())
-            .unwrap();
-        }
-      };
+    #[test]
+    fn audio_problems_flush_trailing_partial_window() {
+        // 1020 frames: the last 20 don't fill a window but are still analyzed
+        let (problems, frames) = scan_audio_problems(1, 1020, |frame, _| {
+            if frame >= 1010 { 32767 } else { speech_level(frame) }
+        });
 
-      // Check for Range header to support partial content requests
-      let range_header = request.headers().get("Range")
-        .and_then(|v| v.to_str().ok());
+        assert_eq!(frames, 1020);
+        assert_eq!(problems, vec![("clipping".to_string(), 1.0, 1.05, Some(0))]);
+    }
 
-      if let Some(range_str) = range_header {
-        // Parse range header (format: "bytes=start-end")
-        // log::info!("Range request: {}", range_str); // Disabled for performance
+    #[test]
+    fn channel_waveforms_bucket_by_decoded_length() {
+        // Half a second of audio, loud then silent: the buckets follow the samples that were
+        // decoded, not any duration the container claims
+        let mut builder = ChannelWaveformBuilder::new(1, 4);
+        for frame in 0..500 {
+            builder.push(if frame < 250 { 16384 } else { 0 });
+        }
+        let (channels, frames) = builder.finish();
 
-        if let Some(range_values) = range_str.strip_prefix("bytes=") {
-          let parts: Vec<&str> = range_values.split('-').collect();
-          if parts.len() == 2 {
-            let start: u64 = parts[0].parse().unwrap_or(0);
-            let end: u64 = if parts[1].is_empty() {
-              file_size.saturating_sub(1)
-            } else {
-              parts[1].parse().unwrap_or(file_size.saturating_sub(1)).min(file_size.saturating_sub(1))
-            };
+        assert_eq!(frames, 500);
+        let loud = (16384.0 / 32767.0) as f32;
+        assert_eq!(channels[0].waveform, vec![loud, loud, 0.0, 0.0]);
+    }
 
-            let content_length = end - start + 1;
+    #[test]
+    fn channel_waveforms_merge_blocks_on_long_audio() {
+        // Far more frames than blocks: the blocks double in size many times over
+        let mut builder = ChannelWaveformBuilder::new(2, 4);
+        for frame in 0..100_000 {
+            let left: i16 = if frame < 25_000 { 16384 } else { 8192 };
+            builder.push(left);
+            builder.push(if frame % 3 == 0 { 32767 } else { 0 });
+        }
+        let (channels, frames) = builder.finish();
+
+        assert_eq!(frames, 100_000);
+        let left = &channels[0].waveform;
+        assert_eq!(left.len(), 4);
+        assert!((left[0] - 0.5).abs() < 0.02, "first quarter is loud: {:?}", left);
+        assert!(left[1..].iter().all(|v| (v - 0.25).abs() < 0.001), "rest is quieter: {:?}", left);
+        assert_eq!(channels[0].clipped_samples, 0);
+
+        // Right channel samples stay on the right channel
+        assert_eq!(channels[1].clipped_samples, 33_334);
+        assert_eq!(channels[1].peak, 1.0);
+        assert!(channels[1].waveform.iter().all(|v| (v - (1.0f32 / 3.0).sqrt()).abs() < 0.01));
+    }
 
-            // log::info!("Serving range: {}-{}/{} ({} bytes)", start, end, file_size, content_length); // Disabled for performance
+    // Parses ffprobe output of real lavfi fixtures written by test-rotation.sh, named
+    // "<fixture>_<width>x<height>.json" after the expected displayed size
+    // Skipped unless CLIPFORGE_ROTATION_FIXTURES points at the fixture directory
+    #[test]
+    fn probe_matches_lavfi_rotation_fixtures() {
+        let dir = match std::env::var("CLIPFORGE_ROTATION_FIXTURES") {
+            Ok(dir) => PathBuf::from(dir),
+            Err(_) => return,
+        };
 
-            // Seek to start position
-            if let Err(e) = file.seek(SeekFrom::Start(start)) {
-              log::error!("Failed to seek: {}", e);
-              return Response::builder()
-                .header("Access-Control-Allow-Origin", "*")
-                .status(StatusCode::INTERNAL_SERVER_ERROR)
-                .body(vec![])
-                .unwrap();
+        let mut checked = 0;
+        for entry in std::fs::read_dir(&dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.extension().and_then(|e| e.to_str()) != Some("json") {
+                continue;
             }
+            let stem = path.file_stem().unwrap().to_string_lossy().to_string();
+            let (name, size) = stem.rsplit_once('_').unwrap();
+            let (width, height) = size.split_once('x').unwrap();
+            let expected: (u32, u32) = (width.parse().unwrap(), height.parse().unwrap());
+
+            let json: serde_json::Value = serde_json::from_slice(&std::fs::read(&path).unwrap()).unwrap();
+            let probe = parse_media_probe(&json).unwrap();
+            assert_eq!(displayed(&probe), expected, "fixture {}", name);
+            assert_eq!(probe.mirrored, name.contains("mirrored"), "fixture {}", name);
+            checked += 1;
+        }
+        assert!(checked > 0, "no fixtures found in {:?}", dir);
+    }
 
-            // Read the requested range
-            let mut buffer = vec![0u8; content_length as usize];
-            if let Err(e) = file.read_exact(&mut buffer) {
-              log::error!("Failed to read range: {}", e);
-              return Response::builder()
-                .header("Access-Control-Allow-Origin", "*")
-                .status(StatusCode::INTERNAL_SERVER_ERROR)
-                .body(vec![])
-                .unwrap();
-            }
+    // PSNR of the first frame of `a` against the first frame of `b`, in dB (infinite when identical)
+    fn first_frame_psnr(a: &std::path::Path, b: &std::path::Path) -> f64 {
+        let output = Command::new(find_ffmpeg())
+            .arg("-i").arg(a)
+            .arg("-i").arg(b)
+            .args(["-lavfi", "[0:v][1:v]psnr", "-frames:v", "1", "-f", "null", "-"])
+            .output()
+            .unwrap();
+        String::from_utf8_lossy(&output.stderr)
+            .lines()
+            .find_map(|line| line.split("average:").nth(1))
+            .and_then(|rest| rest.split_whitespace().next())
+            .and_then(|value| value.parse::<f64>().ok())
+            .unwrap_or(0.0)
+    }
 
-            // Return 206 Partial Content
-            return Response::builder()
-              .header("Content-Type", "video/mp4")
-              .header("Content-Range", format!("bytes {}-{}/{}", start, end, file_size))
-              .header("Content-Length", content_length.to_string())
-              .header("Accept-Ranges", "bytes")
-              .header("Access-Control-Allow-Origin", "*")
-              .header("Access-Control-Allow-Methods", "GET, HEAD, OPTIONS")
-              .header("Access-Control-Allow-Headers", "*")
-              .status(StatusCode::PARTIAL_CONTENT)
-              .body(buffer)
-              .unwrap();
-          }
-        }
-      }
+    // Exports each fixture written by test-rotation.sh through the multi-clip render path and checks
+    // the result is upright: coded at the displayed size, no display matrix left for players to apply
+    // again, and the same picture as "<fixture>.reference.mp4", which the script rendered from the
+    // unrotated base with explicit transpose/flip filters. Mirrored fixtures only pass when the flip
+    // is applied, whatever FFmpeg version does the autorotation.
+    // Skipped unless CLIPFORGE_ROTATION_FIXTURES points at the fixture directory
+    #[test]
+    fn export_renders_lavfi_rotation_fixtures_upright() {
+        let dir = match std::env::var("CLIPFORGE_ROTATION_FIXTURES") {
+            Ok(dir) => PathBuf::from(dir),
+            Err(_) => return,
+        };
+        let preset = resolve_export_preset(None, DEFAULT_MERGE_PRESET).unwrap();
+        let (extension, codec_args) = temp_clip_format(&preset);
 
-      // No range request - serve entire file
-      log::info!("Serving entire file: {} bytes", file_size);
+        let mut checked = 0;
+        for entry in std::fs::read_dir(&dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.extension().and_then(|e| e.to_str()) != Some("json") {
+                continue;
+            }
+            let stem = path.file_stem().unwrap().to_string_lossy().to_string();
+            let (name, size) = stem.rsplit_once('_').unwrap();
+            let (width, height) = size.split_once('x').unwrap();
+            let expected: (u32, u32) = (width.parse().unwrap(), height.parse().unwrap());
+
+            let fixture = dir.join(format!("{}.mp4", name));
+            let reference = dir.join(format!("{}.reference.mp4", name));
+            let exported = dir.join(format!("{}.exported.{}", name, extension));
+
+            let clip = clip_segment_for_range(&fixture.to_string_lossy(), 0.0, 1.0);
+            render_temp_clip(&clip, 0, &exported, &codec_args, &ExportJob::new(), |_| {})
+                .unwrap_or_else(|e| panic!("fixture {}: export failed: {}", name, e));
+
+            let output = Command::new(find_ffprobe())
+                .args(["-v", "quiet", "-print_format", "json", "-show_format", "-show_streams"])
+                .arg(&exported)
+                .output()
+                .unwrap();
+            let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+            let probe = parse_media_probe(&json).unwrap();
+            assert_eq!(probe.width.zip(probe.height), Some(expected), "fixture {}", name);
 
-      match std::fs::read(path) {
-        Ok(data) => {
-          Response::builder()
-            .header("Content-Type", "video/mp4")
-            .header("Content-Length", file_size.to_string())
-            .header("Accept-Ranges", "bytes")
-            .header("Access-Control-Allow-Origin", "*")
-            .header("Access-Control-Allow-Methods", "GET, HEAD, OPTIONS")
-            .header("Access-Control-Allow-Headers", "*")
-            .status(StatusCode::OK)
-            .body(data)
-            .unwrap()
-        }
-        Err(e) => {
-          log::error!("Failed to read entire file: {}", e);
-          Response::builder()
-            .header("Access-Control-Allow-Origin", "*")
-            .status(StatusCode::INTERNAL_SERVER_ERROR)
-            .body(format!("Failed to read file: {}", e).into_bytes())
-            .unwrap()
+            let video = json["streams"].as_array().unwrap()
+                .iter()
+                .find(|stream| stream["codec_type"] == "video")
+                .unwrap();
+            assert!(display_matrix_side_data(video).is_none(), "fixture {}: export kept a display matrix", name);
+            assert!(video["tags"]["rotate"].is_null(), "fixture {}: export kept a rotate tag", name);
+
+            let psnr = first_frame_psnr(&exported, &reference);
+            assert!(psnr > 30.0, "fixture {}: export doesn't match its upright reference (PSNR {:.1} dB)", name, psnr);
+            checked += 1;
         }
-      }
-    })
-    .setup(|app| {
-      if cfg!(debug_assertions) {
-        app.handle().plugin(
-          tauri_plugin_log::Builder::default()
-            .level(log::LevelFilter::Info)
-            .build(),
-        )?;
-      }
-      Ok(())
-    })
-    .run(tauri::generate_context!())
-    .expect("error while running tauri application");
+        assert!(checked > 0, "no fixtures found in {:?}", dir);
+    }
 }
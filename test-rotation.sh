@@ -0,0 +1,73 @@
+#!/bin/bash
+
+# Rotation Test: Verify phone-style rotated sources import and export upright
+# Generates lavfi fixtures with display-matrix rotation metadata (90/180/270 and mirrored),
+# dumps their ffprobe JSON and renders an upright reference for each, then runs the app's Rust
+# rotation tests against them: parse_media_probe must report the displayed size and mirroring,
+# and exporting each fixture through the real render path must produce the displayed size, no
+# leftover display matrix, and the same picture as the reference.
+# Requires FFmpeg 6.0+ (for -display_rotation / -display_hflip) and cargo
+
+echo "📱 Rotation Import/Export Test"
+echo "=============================="
+echo ""
+
+SCRIPT_DIR="$(cd "$(dirname "$0")" && pwd)"
+OUTDIR="${TMPDIR:-/tmp}/clipforge-rotation-test"
+rm -rf "$OUTDIR"
+mkdir -p "$OUTDIR"
+
+# Base fixture: 320x240 landscape test pattern with a tone, like a short phone clip
+ffmpeg -loglevel error \
+    -f lavfi -i "testsrc=size=320x240:rate=30:duration=2" \
+    -f lavfi -i "sine=frequency=440:duration=2" \
+    -c:v libx264 -pix_fmt yuv420p -c:a aac -shortest \
+    -y "$OUTDIR/base.mp4" || { echo "❌ Failed to generate base fixture"; exit 1; }
+
+# fixture <name> <rotation> <hflip> <expected_width> <expected_height> <upright_filter>
+# Writes <name>_<width>x<height>.json, which the Rust tests read their expectations from, and
+# <name>.reference.mp4: the base turned upright with explicit filters, to compare exports against.
+# -display_rotation turns the picture counter-clockwise on display, and -display_hflip flips it
+# after the rotation, so upright_filter spells out the same transform.
+fixture() {
+    local name="$1" rotation="$2" hflip="$3" expected_w="$4" expected_h="$5" upright="$6"
+    local video="$OUTDIR/$name.mp4"
+
+    local flip_args=()
+    if [ "$hflip" = "1" ]; then
+        flip_args=(-display_hflip)
+    fi
+
+    # Attach display-matrix side data without re-encoding (what phones do)
+    ffmpeg -loglevel error -display_rotation "$rotation" "${flip_args[@]}" \
+        -i "$OUTDIR/base.mp4" -c copy -y "$video" \
+        || { echo "❌ $name: failed to generate fixture"; exit 1; }
+
+    # Same ffprobe invocation as probe_video_metadata
+    ffprobe -v quiet -print_format json -show_format -show_streams -show_chapters "$video" \
+        > "$OUTDIR/${name}_${expected_w}x${expected_h}.json" \
+        || { echo "❌ $name: ffprobe failed"; exit 1; }
+
+    ffmpeg -loglevel error -i "$OUTDIR/base.mp4" -vf "$upright" \
+        -c:v libx264 -crf 18 -pix_fmt yuv420p -an -y "$OUTDIR/$name.reference.mp4" \
+        || { echo "❌ $name: failed to render reference"; exit 1; }
+
+    echo "   $name: rotation $rotation, hflip $hflip, expecting ${expected_w}x${expected_h}"
+}
+
+fixture "rot0"           0 0 320 240 "null"
+fixture "rot90"         90 0 240 320 "transpose=cclock"
+fixture "rot180"       180 0 320 240 "hflip,vflip"
+fixture "rot270"       -90 0 240 320 "transpose=clock"
+fixture "mirrored"       0 1 320 240 "hflip"
+fixture "rot90-mirrored" 90 1 240 320 "transpose=cclock,hflip"
+
+echo ""
+echo "Fixtures, references and exports kept in: $OUTDIR"
+echo ""
+
+CLIPFORGE_ROTATION_FIXTURES="$OUTDIR" cargo test --manifest-path "$SCRIPT_DIR/src-tauri/Cargo.toml" --lib \
+    -- tests::probe tests::concat_canvas tests::export_renders \
+    || { echo "❌ Rotation tests failed"; exit 1; }
+
+echo "✅ All rotation checks passed"